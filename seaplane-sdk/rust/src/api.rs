@@ -6,7 +6,7 @@ pub mod locks;
 pub mod metadata;
 pub mod restrict;
 #[cfg(any(
-    feature = "compute_api_v1",
+    feature = "compute_api_v2",
     feature = "locks_api_v1",
     feature = "metadata_api_v1",
     feature = "restrict_api_v1"
@@ -17,18 +17,193 @@ pub mod shared;
 pub mod error;
 pub use error::*;
 
+/// Rate limits reported by the APIs
+mod rate_limit;
+pub use rate_limit::*;
+
 /// Request builder base structs that handle token reuse
 #[cfg(any(
-    feature = "compute_api_v1",
+    feature = "compute_api_v2",
+    feature = "identity_api_v1",
     feature = "locks_api_v1",
     feature = "metadata_api_v1",
     feature = "restrict_api_v1",
 ))]
 mod request;
 #[cfg(any(
-    feature = "compute_api_v1",
+    feature = "compute_api_v2",
+    feature = "identity_api_v1",
     feature = "locks_api_v1",
     feature = "metadata_api_v1",
     feature = "restrict_api_v1",
 ))]
 pub(crate) use request::*;
+
+/// Pluggable transport for sending HTTP requests
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "identity_api_v1",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1",
+))]
+mod transport;
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "identity_api_v1",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1",
+))]
+pub use transport::*;
+
+/// Recording requests to a file and replaying them offline
+#[cfg(all(
+    feature = "cassette",
+    any(
+        feature = "compute_api_v2",
+        feature = "identity_api_v1",
+        feature = "locks_api_v1",
+        feature = "metadata_api_v1",
+        feature = "restrict_api_v1",
+    )
+))]
+mod cassette;
+#[cfg(all(
+    feature = "cassette",
+    any(
+        feature = "compute_api_v2",
+        feature = "identity_api_v1",
+        feature = "locks_api_v1",
+        feature = "metadata_api_v1",
+        feature = "restrict_api_v1",
+    )
+))]
+#[cfg_attr(docsrs, doc(cfg(feature = "cassette")))]
+pub use cassette::*;
+
+/// The status and headers of responses alongside their bodies
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "identity_api_v1",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1",
+))]
+mod response;
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "identity_api_v1",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1",
+))]
+pub use response::*;
+
+/// Observing the outcome of API calls
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "identity_api_v1",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1",
+))]
+mod observer;
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "identity_api_v1",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1",
+))]
+pub use observer::*;
+
+/// Optional `tracing` instrumentation of API calls
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "identity_api_v1",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1",
+))]
+mod trace;
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "identity_api_v1",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1",
+))]
+pub(crate) use trace::*;
+
+/// Transport level settings for HTTP connections
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "identity_api_v1",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1",
+))]
+mod options;
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "identity_api_v1",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1",
+))]
+pub use options::*;
+
+/// Retrying of requests which failed for transient reasons
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "identity_api_v1",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1",
+))]
+mod retry;
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "identity_api_v1",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1",
+))]
+pub use retry::*;
+
+/// Client-side throttling of requests
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "identity_api_v1",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1",
+))]
+mod limiter;
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "identity_api_v1",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1",
+))]
+pub use limiter::*;
+
+/// A single client sharing one connection pool between requests to all APIs
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "identity_api_v1",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1",
+))]
+mod client;
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "identity_api_v1",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1",
+))]
+pub use client::*;
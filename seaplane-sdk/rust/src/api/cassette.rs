@@ -0,0 +1,372 @@
+//! Recording requests to a file and replaying them offline
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::{
+    header::{
+        HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION, SET_COOKIE,
+    },
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[cfg(feature = "async")]
+use crate::api::TransportFuture;
+use crate::{
+    api::{HttpRequest, HttpResponse, Transport, TransportError},
+    error::{ErrorKind, Result},
+};
+
+/// The value which replaces tokens and other secrets in a cassette
+pub const REDACTED: &str = "<redacted>";
+
+/// A [`Transport`] which records the requests sent through another transport, along with their
+/// responses, to a JSON file (the "cassette") and later replays the responses without any network
+/// access.
+///
+/// Secrets are scrubbed before anything is written to the cassette: the values of the
+/// `Authorization`, `Proxy-Authorization`, `Cookie` and `Set-Cookie` headers (and any other header
+/// marked as sensitive), the access tokens returned by the Identity API, and any strings added
+/// with [`Cassette::redact`] are all replaced with [`REDACTED`].
+///
+/// When replaying, each request is answered with the first not yet replayed response which was
+/// recorded for the same method and URL, so a sequence of requests to the same endpoint (such as
+/// paging through a range) replays in the order it was recorded. Headers and bodies of requests
+/// are not compared. A request without a matching recording fails with an error.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use seaplane::api::{metadata::v1::MetadataRequest, Cassette, HttpClient, HttpOptions};
+/// // Record against the real API when asked to, and otherwise replay offline
+/// let path = "tests/cassettes/get_value.json";
+/// let cassette = if std::env::var_os("RECORD").is_some() {
+///     Cassette::record(path, HttpClient::new(HttpOptions::default()).unwrap())
+/// } else {
+///     Cassette::replay(path).unwrap()
+/// };
+///
+/// let req = MetadataRequest::builder()
+///     .token(std::env::var("SEAPLANE_TOKEN").unwrap_or_default())
+///     .encoded_key("Zm9v")
+///     .transport(cassette)
+///     .build()
+///     .unwrap();
+/// let value = req.get_value().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    // The transport requests are recorded from, or `None` when replaying
+    recorder: Option<Arc<dyn Transport>>,
+    redactions: Vec<String>,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    interactions: Vec<Interaction>,
+    // Which of the interactions have been replayed
+    replayed: Vec<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    url: String,
+    headers: BTreeMap<String, String>,
+    #[serde(flatten)]
+    body: RecordedBody,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    headers: BTreeMap<String, String>,
+    #[serde(flatten)]
+    body: RecordedBody,
+}
+
+/// A body is stored as text when it is valid UTF-8 (which keeps the cassette readable and easy
+/// to edit by hand) and as base64 otherwise
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct RecordedBody {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body_base64: Option<String>,
+}
+
+impl Cassette {
+    /// Records all requests sent through `transport` to the cassette at `path`, replacing any
+    /// existing cassette. The cassette is written after every request.
+    pub fn record<P: AsRef<Path>, T: Transport + 'static>(path: P, transport: T) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            recorder: Some(Arc::new(transport)),
+            redactions: Vec::new(),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Replays the responses recorded in the cassette at `path`, returning an error if it cannot
+    /// be read
+    pub fn replay<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file: CassetteFile = serde_json::from_slice(&fs::read(path.as_ref())?)?;
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            recorder: None,
+            redactions: Vec::new(),
+            state: Mutex::new(State {
+                replayed: vec![false; file.interactions.len()],
+                interactions: file.interactions,
+            }),
+        })
+    }
+
+    /// Additionally replaces every occurrence of `secret` in the recorded URLs, headers and bodies
+    /// with [`REDACTED`], such as a tenant ID which should not be committed.
+    ///
+    /// The same redactions must be added when replaying, so that requests still match the
+    /// recorded URLs.
+    #[must_use]
+    pub fn redact<S: Into<String>>(mut self, secret: S) -> Self {
+        let secret = secret.into();
+        if !secret.is_empty() {
+            self.redactions.push(secret);
+        }
+        self
+    }
+
+    /// Whether every recorded response has been replayed
+    pub fn is_exhausted(&self) -> bool { self.lock().replayed.iter().all(|r| *r) }
+
+    fn lock(&self) -> MutexGuard<'_, State> { self.state.lock().unwrap_or_else(|e| e.into_inner()) }
+
+    fn scrub(&self, s: &str) -> String {
+        self.redactions
+            .iter()
+            .fold(s.to_owned(), |s, secret| s.replace(secret.as_str(), REDACTED))
+    }
+
+    // Appends the interaction and rewrites the cassette
+    fn store(&self, req: &HttpRequest, resp: &HttpResponse) -> Result<()> {
+        let interaction = Interaction {
+            request: RecordedRequest {
+                method: req.method.to_string(),
+                url: self.scrub(req.url.as_str()),
+                headers: self.scrub_headers(&req.headers),
+                body: self.scrub_body(req.body.as_deref().unwrap_or_default(), false),
+            },
+            response: RecordedResponse {
+                status: resp.status.as_u16(),
+                headers: self.scrub_headers(&resp.headers),
+                body: self.scrub_body(&resp.body, is_token_url(req.url.path())),
+            },
+        };
+
+        let mut state = self.lock();
+        state.interactions.push(interaction);
+        state.replayed.push(true);
+        let file = CassetteFile { interactions: state.interactions.clone() };
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_vec_pretty(&file)?)?;
+        Ok(())
+    }
+
+    fn scrub_headers(&self, headers: &HeaderMap) -> BTreeMap<String, String> {
+        let mut scrubbed = BTreeMap::new();
+        for (name, value) in headers {
+            let value = if is_secret_header(name, value) {
+                REDACTED.to_owned()
+            } else {
+                self.scrub(&String::from_utf8_lossy(value.as_bytes()))
+            };
+            scrubbed
+                .entry(name.as_str().to_owned())
+                .and_modify(|v: &mut String| {
+                    v.push_str(", ");
+                    v.push_str(&value);
+                })
+                .or_insert(value);
+        }
+        scrubbed
+    }
+
+    fn scrub_body(&self, body: &[u8], is_token: bool) -> RecordedBody {
+        if body.is_empty() {
+            return RecordedBody::default();
+        }
+        if is_token {
+            // Either a JSON `AccessToken` or the bare token
+            let body = match serde_json::from_slice::<Value>(body) {
+                Ok(Value::Object(mut token)) => {
+                    token.insert("token".into(), REDACTED.into());
+                    Value::Object(token).to_string()
+                }
+                _ => REDACTED.to_owned(),
+            };
+            return RecordedBody { body: Some(self.scrub(&body)), body_base64: None };
+        }
+        match std::str::from_utf8(body) {
+            Ok(text) => RecordedBody { body: Some(self.scrub(text)), body_base64: None },
+            Err(_) => RecordedBody { body: None, body_base64: Some(STANDARD.encode(body)) },
+        }
+    }
+
+    // Finds the next recorded response for the request
+    fn replay_response(&self, req: &HttpRequest) -> Result<HttpResponse> {
+        let url = self.scrub(req.url.as_str());
+        let method = req.method.as_str();
+
+        let mut state = self.lock();
+        let State { interactions, replayed } = &mut *state;
+        let Some((i, interaction)) = interactions.iter().enumerate().find(|(i, interaction)| {
+            !replayed[*i] && interaction.request.method == method && interaction.request.url == url
+        }) else {
+            return Err(TransportError::new(
+                ErrorKind::Other,
+                format!("no recorded response for {method} {url} in {}", self.path.display()),
+            )
+            .into());
+        };
+        replayed[i] = true;
+
+        let resp = &interaction.response;
+        let status = StatusCode::from_u16(resp.status)
+            .map_err(|e| TransportError::new(ErrorKind::Decode, e))?;
+        let mut headers = HeaderMap::new();
+        for (name, value) in &resp.headers {
+            let name = HeaderName::try_from(name.as_str())
+                .map_err(|e| TransportError::new(ErrorKind::Decode, e))?;
+            headers.insert(name, HeaderValue::try_from(value.as_str())?);
+        }
+        let body = match (&resp.body.body, &resp.body.body_base64) {
+            (Some(text), _) => text.as_bytes().to_vec(),
+            (None, Some(encoded)) => STANDARD
+                .decode(encoded)
+                .map_err(|e| TransportError::new(ErrorKind::Decode, e))?,
+            (None, None) => Vec::new(),
+        };
+        Ok(HttpResponse::new(status, headers, body))
+    }
+}
+
+impl Transport for Cassette {
+    fn send(&self, req: HttpRequest) -> Result<HttpResponse> {
+        match &self.recorder {
+            Some(transport) => {
+                let resp = transport.send(req.clone())?;
+                self.store(&req, &resp)?;
+                Ok(resp)
+            }
+            None => self.replay_response(&req),
+        }
+    }
+
+    #[cfg(feature = "async")]
+    fn send_async(&self, req: HttpRequest) -> TransportFuture<'_> {
+        Box::pin(async move {
+            match &self.recorder {
+                Some(transport) => {
+                    let resp = transport.send_async(req.clone()).await?;
+                    self.store(&req, &resp)?;
+                    Ok(resp)
+                }
+                None => self.replay_response(&req),
+            }
+        })
+    }
+}
+
+fn is_secret_header(name: &HeaderName, value: &HeaderValue) -> bool {
+    value.is_sensitive() || [AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE, SET_COOKIE].contains(name)
+}
+
+// Responses from the Identity API's token endpoint contain an access token
+fn is_token_url(path: &str) -> bool { path.trim_end_matches('/').ends_with("/token") }
+
+#[cfg(test)]
+mod cassette_tests {
+    use reqwest::Method;
+
+    use super::*;
+
+    // A cassette with nothing recorded
+    fn empty() -> Cassette {
+        Cassette {
+            path: PathBuf::new(),
+            recorder: None,
+            redactions: Vec::new(),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    #[test]
+    fn scrubs_secrets() {
+        let cassette = Cassette::record("unused.json", empty()).redact("tnt-123");
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer abc123"));
+        headers.insert("x-tenant", HeaderValue::from_static("tnt-123"));
+        let scrubbed = cassette.scrub_headers(&headers);
+        assert_eq!(scrubbed["authorization"], REDACTED);
+        assert_eq!(scrubbed["x-tenant"], REDACTED);
+
+        let body = cassette.scrub_body(br#"{"token":"jwt","tenant":"tnt-123"}"#, true);
+        let token: Value = serde_json::from_str(&body.body.unwrap()).unwrap();
+        assert_eq!(token, serde_json::json!({"token": REDACTED, "tenant": REDACTED}));
+        assert_eq!(cassette.scrub_body(b"jwt", true).body.unwrap(), REDACTED);
+
+        let body = cassette.scrub_body(&[0xFF, 0xFE], false);
+        assert_eq!(body.body_base64.unwrap(), "//4=");
+    }
+
+    #[test]
+    fn replays_in_order() {
+        let cassette = empty();
+        let url: reqwest::Url = "https://metadata.example/v1/config/".parse().unwrap();
+        for body in ["first", "second"] {
+            cassette.lock().interactions.push(Interaction {
+                request: RecordedRequest {
+                    method: "GET".into(),
+                    url: url.to_string(),
+                    headers: BTreeMap::new(),
+                    body: RecordedBody::default(),
+                },
+                response: RecordedResponse {
+                    status: 200,
+                    headers: BTreeMap::new(),
+                    body: RecordedBody { body: Some(body.into()), body_base64: None },
+                },
+            });
+            cassette.lock().replayed.push(false);
+        }
+
+        let req = HttpRequest::new(Method::GET, url, HeaderMap::new(), None);
+        assert_eq!(cassette.send(req.clone()).unwrap().body, b"first");
+        assert!(!cassette.is_exhausted());
+        assert_eq!(cassette.send(req.clone()).unwrap().body, b"second");
+        assert!(cassette.is_exhausted());
+        assert!(cassette.send(req).is_err());
+    }
+}
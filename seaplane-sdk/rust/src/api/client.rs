@@ -0,0 +1,385 @@
+//! A single client which shares one HTTP connection pool, the endpoint URLs, and authorization
+//! between requests to all of the Seaplane APIs
+
+use std::sync::Arc;
+
+use reqwest::Url;
+
+#[cfg(feature = "compute_api_v2")]
+use crate::api::compute::v2::FormationsRequestBuilder;
+#[cfg(feature = "identity_api_v1")]
+use crate::api::identity::v1::{Credentials, TokenRequest, TokenRequestBuilder};
+#[cfg(feature = "locks_api_v1")]
+use crate::api::locks::v1::LocksRequestBuilder;
+#[cfg(feature = "metadata_api_v1")]
+use crate::api::metadata::v1::MetadataRequestBuilder;
+#[cfg(feature = "restrict_api_v1")]
+use crate::api::restrict::v1::RestrictRequestBuilder;
+use crate::{
+    api::{
+        Auth, ClientConfig, HttpClient, HttpOptions, Observer, RateLimiter, RequestBuilder,
+        RetryPolicy, Secret, SharedObserver, Transport,
+    },
+    error::{Result, SeaplaneError},
+};
+
+/// A builder struct for creating a [`SeaplaneClient`]
+#[derive(Debug, Default)]
+pub struct SeaplaneClientBuilder {
+    token: Option<Secret>,
+    #[cfg(feature = "identity_api_v1")]
+    api_key: Option<Secret>,
+    #[cfg(feature = "identity_api_v1")]
+    credentials: Option<Credentials>,
+    compute_url: Option<String>,
+    metadata_url: Option<String>,
+    locks_url: Option<String>,
+    restrict_url: Option<String>,
+    identity_url: Option<String>,
+    config: ClientConfig,
+    retry: RetryPolicy,
+    limiter: Option<RateLimiter>,
+    transport: Option<Arc<dyn Transport>>,
+    observer: Option<SharedObserver>,
+}
+
+impl SeaplaneClientBuilder {
+    /// Create a new `Default` builder
+    pub fn new() -> Self { Self::default() }
+
+    /// Set a static token used in Bearer Authorization of all requests
+    ///
+    /// **NOTE:** This (or an API key or [`Credentials`]) is required
+    #[must_use]
+    pub fn token<S: Into<String>>(mut self, token: S) -> Self {
+        self.token = Some(Secret::new(token));
+        self
+    }
+
+    /// Set the API key which will be exchanged for (and used to refresh) the access token used in
+    /// Bearer Authorization of all requests. The token is fetched using the client's own
+    /// connection pool and Identity URL.
+    ///
+    /// **NOTE:** This (or a token or [`Credentials`]) is required
+    #[cfg(feature = "identity_api_v1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "identity_api_v1")))]
+    #[must_use]
+    pub fn api_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.api_key = Some(Secret::new(key));
+        self
+    }
+
+    /// Set the [`Credentials`] which provide the token used in Bearer Authorization of all
+    /// requests
+    ///
+    /// **NOTE:** This (or a token or an API key) is required
+    #[cfg(feature = "identity_api_v1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "identity_api_v1")))]
+    #[must_use]
+    pub fn credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Set the URL of the Compute API (default: the
+    /// [`COMPUTE_API_URL_ENV`](crate::api::compute::COMPUTE_API_URL_ENV) environment variable if
+    /// set, otherwise [`COMPUTE_API_URL`](crate::api::compute::COMPUTE_API_URL))
+    #[must_use]
+    pub fn compute_url<S: Into<String>>(mut self, url: S) -> Self {
+        self.compute_url = Some(url.into());
+        self
+    }
+
+    /// Set the URL of the Metadata API (default: the
+    /// [`METADATA_API_URL_ENV`](crate::api::metadata::METADATA_API_URL_ENV) environment variable
+    /// if set, otherwise [`METADATA_API_URL`](crate::api::metadata::METADATA_API_URL)).
+    ///
+    /// The Locks and Restrict APIs are served from the same endpoint, so this URL is also used
+    /// for them unless overridden with [`SeaplaneClientBuilder::locks_url`] or
+    /// [`SeaplaneClientBuilder::restrict_url`].
+    #[must_use]
+    pub fn metadata_url<S: Into<String>>(mut self, url: S) -> Self {
+        self.metadata_url = Some(url.into());
+        self
+    }
+
+    /// Set the URL of the Locks API (default: the Metadata API URL)
+    #[must_use]
+    pub fn locks_url<S: Into<String>>(mut self, url: S) -> Self {
+        self.locks_url = Some(url.into());
+        self
+    }
+
+    /// Set the URL of the Restrict API (default: the Metadata API URL)
+    #[must_use]
+    pub fn restrict_url<S: Into<String>>(mut self, url: S) -> Self {
+        self.restrict_url = Some(url.into());
+        self
+    }
+
+    /// Set the URL of the Identity API (default: the
+    /// [`IDENTITY_API_URL_ENV`](crate::api::identity::IDENTITY_API_URL_ENV) environment variable
+    /// if set, otherwise [`IDENTITY_API_URL`](crate::api::identity::IDENTITY_API_URL))
+    #[must_use]
+    pub fn identity_url<S: Into<String>>(mut self, url: S) -> Self {
+        self.identity_url = Some(url.into());
+        self
+    }
+
+    /// Allow non-HTTPS endpoints (default: `false`)
+    #[cfg(any(feature = "allow_insecure_urls", feature = "danger_zone"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "allow_insecure_urls", feature = "danger_zone"))))]
+    #[must_use]
+    pub fn allow_http(mut self, yes: bool) -> Self {
+        self.config.allow_http = yes;
+        self
+    }
+
+    /// Allow invalid TLS certificates (default: `false`)
+    #[cfg(any(feature = "allow_invalid_certs", feature = "danger_zone"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "allow_invalid_certs", feature = "danger_zone"))))]
+    #[must_use]
+    pub fn allow_invalid_certs(mut self, yes: bool) -> Self {
+        self.config.allow_invalid_certs = yes;
+        self
+    }
+
+    /// Set the [`HttpOptions`] such as timeouts, proxy and TLS certificates used for all
+    /// connections (default: [`HttpOptions::default`])
+    #[must_use]
+    pub fn http_options(mut self, options: HttpOptions) -> Self {
+        self.config.options = options;
+        self
+    }
+
+    /// Set the [`RetryPolicy`] used to retry idempotent requests which failed for transient
+    /// reasons, including the requests to the Identity API for access tokens (default:
+    /// [`RetryPolicy::default`])
+    #[must_use]
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Throttle the requests to all APIs on the client side using the [`RateLimiter`] (default: no
+    /// client-side limit). The limit applies to the combined rate of all requests built from the
+    /// client and its clones, including the requests to the Identity API for access tokens.
+    #[must_use]
+    pub fn rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Send the requests to all APIs through the [`Transport`] rather than an HTTP client
+    /// constructed from this builder's settings, in which case those settings (such as
+    /// [`HttpOptions`]) are ignored
+    #[must_use]
+    pub fn transport<T: Transport + 'static>(mut self, transport: T) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Notify the [`Observer`] of the outcome of every call to any API, including the calls made to
+    /// the Identity API to fetch access tokens (default: no observer)
+    #[must_use]
+    pub fn observer<O: Observer + 'static>(mut self, observer: O) -> Self {
+        self.observer = Some(SharedObserver::new(observer));
+        self
+    }
+
+    /// Build a `SeaplaneClient` from the given parameters
+    pub fn build(self) -> Result<SeaplaneClient> {
+        let parse = |url: Option<String>| url.map(|u| u.parse::<Url>()).transpose();
+        let http: Arc<dyn Transport> = match self.transport {
+            Some(transport) => transport,
+            None => Arc::new(HttpClient::with_config(self.config)?),
+        };
+        let metadata_url = parse(self.metadata_url)?;
+        let identity_url = parse(self.identity_url)?;
+
+        #[cfg(feature = "identity_api_v1")]
+        let credentials = match (self.credentials, &self.api_key) {
+            (Some(creds), _) => Some(creds),
+            (None, Some(key)) => {
+                let builder = identity_builder(
+                    &http,
+                    self.retry,
+                    &self.limiter,
+                    &self.observer,
+                    &identity_url,
+                );
+                Some(Credentials::new(builder.api_key(key.expose()).build()?))
+            }
+            (None, None) => None,
+        };
+
+        // An explicit token takes precedence over credentials
+        let auth = match self.token {
+            Some(token) => Auth::Token(token),
+            #[cfg(feature = "identity_api_v1")]
+            None if credentials.is_some() => Auth::Credentials(credentials.unwrap()),
+            None => return Err(SeaplaneError::MissingRequestAuthToken),
+        };
+
+        Ok(SeaplaneClient {
+            http,
+            auth,
+            retry: self.retry,
+            limiter: self.limiter,
+            observer: self.observer,
+            #[cfg(feature = "identity_api_v1")]
+            api_key: self.api_key,
+            compute_url: parse(self.compute_url)?,
+            locks_url: parse(self.locks_url)?.or_else(|| metadata_url.clone()),
+            restrict_url: parse(self.restrict_url)?.or_else(|| metadata_url.clone()),
+            metadata_url,
+            identity_url,
+        })
+    }
+}
+
+/// A client for all of the Seaplane APIs which owns a single HTTP connection pool.
+///
+/// The request builders handed out by a `SeaplaneClient` are preconfigured with its authorization
+/// and endpoint URLs, and all requests built from them share its connections. Cloning a
+/// `SeaplaneClient` is cheap and the clones share the same connection pool.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use seaplane::api::SeaplaneClient;
+/// let client = SeaplaneClient::builder().api_key("abc123").build().unwrap();
+///
+/// let resp = client.formations().build().unwrap().get_all().unwrap();
+/// dbg!(resp);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SeaplaneClient {
+    http: Arc<dyn Transport>,
+    auth: Auth,
+    retry: RetryPolicy,
+    limiter: Option<RateLimiter>,
+    observer: Option<SharedObserver>,
+    #[cfg(feature = "identity_api_v1")]
+    api_key: Option<Secret>,
+    #[cfg_attr(not(feature = "compute_api_v2"), allow(dead_code))]
+    compute_url: Option<Url>,
+    #[cfg_attr(not(feature = "metadata_api_v1"), allow(dead_code))]
+    metadata_url: Option<Url>,
+    #[cfg_attr(not(feature = "locks_api_v1"), allow(dead_code))]
+    locks_url: Option<Url>,
+    #[cfg_attr(not(feature = "restrict_api_v1"), allow(dead_code))]
+    restrict_url: Option<Url>,
+    #[cfg_attr(not(feature = "identity_api_v1"), allow(dead_code))]
+    identity_url: Option<Url>,
+}
+
+impl SeaplaneClient {
+    /// Create a new client builder
+    pub fn builder() -> SeaplaneClientBuilder { SeaplaneClientBuilder::new() }
+
+    /// Returns a builder for requests against the `/formations` APIs
+    #[cfg(feature = "compute_api_v2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "compute_api_v2")))]
+    pub fn formations(&self) -> FormationsRequestBuilder {
+        let builder = FormationsRequestBuilder::new().builder;
+        self.configure(builder, &self.compute_url).into()
+    }
+
+    /// Returns a builder for requests against the `/config` APIs
+    #[cfg(feature = "metadata_api_v1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "metadata_api_v1")))]
+    pub fn metadata(&self) -> MetadataRequestBuilder {
+        let builder = MetadataRequestBuilder::new().builder;
+        self.configure(builder, &self.metadata_url).into()
+    }
+
+    /// Returns a builder for requests against the `/locks` APIs
+    #[cfg(feature = "locks_api_v1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "locks_api_v1")))]
+    pub fn locks(&self) -> LocksRequestBuilder {
+        let builder = LocksRequestBuilder::new().builder;
+        self.configure(builder, &self.locks_url).into()
+    }
+
+    /// Returns a builder for requests against the `/restrict` APIs
+    #[cfg(feature = "restrict_api_v1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "restrict_api_v1")))]
+    pub fn restrict(&self) -> RestrictRequestBuilder {
+        let builder = RestrictRequestBuilder::new().builder;
+        self.configure(builder, &self.restrict_url).into()
+    }
+
+    /// Returns a builder for requests against the `/token` APIs, which uses the client's API key
+    /// if one was provided
+    #[cfg(feature = "identity_api_v1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "identity_api_v1")))]
+    pub fn identity(&self) -> TokenRequestBuilder {
+        let builder = identity_builder(
+            &self.http,
+            self.retry,
+            &self.limiter,
+            &self.observer,
+            &self.identity_url,
+        );
+        match &self.api_key {
+            Some(key) => builder.api_key(key.expose()),
+            None => builder,
+        }
+    }
+
+    #[cfg_attr(
+        not(any(
+            feature = "compute_api_v2",
+            feature = "locks_api_v1",
+            feature = "metadata_api_v1",
+            feature = "restrict_api_v1"
+        )),
+        allow(dead_code)
+    )]
+    fn configure<T>(&self, builder: RequestBuilder<T>, url: &Option<Url>) -> RequestBuilder<T> {
+        let builder = builder
+            .transport(Arc::clone(&self.http))
+            .auth(self.auth.clone())
+            .retry_policy(self.retry);
+        let builder = match &self.limiter {
+            Some(limiter) => builder.rate_limiter(limiter.clone()),
+            None => builder,
+        };
+        let builder = match &self.observer {
+            Some(observer) => builder.shared_observer(observer.clone()),
+            None => builder,
+        };
+        match url {
+            Some(url) => builder.api_url(url.as_str()),
+            None => builder,
+        }
+    }
+}
+
+// A builder for requests against the `/token` APIs sharing the client's transport, retry policy,
+// rate limiter and observer
+#[cfg(feature = "identity_api_v1")]
+fn identity_builder(
+    http: &Arc<dyn Transport>,
+    retry: RetryPolicy,
+    limiter: &Option<RateLimiter>,
+    observer: &Option<SharedObserver>,
+    url: &Option<Url>,
+) -> TokenRequestBuilder {
+    let builder = TokenRequest::builder()
+        .shared_transport(Arc::clone(http))
+        .retry_policy(retry);
+    let builder = match limiter {
+        Some(limiter) => builder.rate_limiter(limiter.clone()),
+        None => builder,
+    };
+    let builder = match observer {
+        Some(observer) => builder.shared_observer(observer.clone()),
+        None => builder,
+    };
+    match url {
+        Some(url) => builder.api_url(url.as_str()),
+        None => builder,
+    }
+}
@@ -7,3 +7,7 @@ pub mod v2;
 /// The compute API handles all things compute such as building `FormationConfiguration`s to
 /// `Flight`s to the underlying Containers.
 pub static COMPUTE_API_URL: &str = "https://compute.cplane.cloud/";
+
+/// The environment variable which, when set, overrides [`COMPUTE_API_URL`] as the base URL of
+/// the Compute API
+pub static COMPUTE_API_URL_ENV: &str = "SEAPLANE_COMPUTE_URL";
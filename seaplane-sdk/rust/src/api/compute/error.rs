@@ -1,6 +1,9 @@
+#[cfg(feature = "manifest")]
+use std::fmt;
+
 use thiserror::Error as ThisError;
 
-use crate::rexports::seaplane_oid::error::Error as OidError;
+use crate::{error::ErrorKind, rexports::seaplane_oid::error::Error as OidError};
 
 #[derive(ThisError, Debug, Copy, Clone, PartialEq, Eq)]
 pub enum FormationValidation {
@@ -30,6 +33,30 @@ pub enum FormationValidation {
     MissingFormationId,
     #[error("a gateway flight was not specified or could not be implied because the number of flights is > 1")]
     NoGatewayFlight,
+    #[error("illegal environment variable name; must only contain ASCII letters, digits, or underscores ('_') and may not start with a digit")]
+    InvalidEnvVarName,
+    #[error("an environment variable cannot be set both as a plain value and from a secret")]
+    DuplicateEnvVar,
+    #[error("the name of the secret an environment variable is set from cannot be empty")]
+    EmptySecretName,
+    #[error("port 0 cannot be exposed")]
+    InvalidPort,
+    #[error("the same port and protocol cannot be exposed more than once")]
+    DuplicatePort,
+    #[error("CPU and memory requests must be greater than zero")]
+    InvalidResources,
+    #[error(
+        "the maximum number of replicas must be greater than zero and no less than the minimum"
+    )]
+    InvalidReplicas,
+    #[error("health check path must start with a slash ('/')")]
+    InvalidHealthCheckPath,
+    #[error("health check port must be an exposed HTTP or HTTPS port")]
+    InvalidHealthCheckPort,
+    #[error("the placement requirements of the Formation are in conflict and invalid")]
+    ConflictingRequirements,
+    #[error("the same Formation name cannot be used more than once")]
+    DuplicateFormationName,
 }
 
 #[derive(ThisError, Debug, Clone, PartialEq, Eq)]
@@ -38,4 +65,62 @@ pub enum ComputeError {
     FormationValidation(#[from] FormationValidation),
     #[error("Object ID error: {0}")]
     Oid(#[from] OidError),
+    #[error("no Formation named '{0}' exists")]
+    FormationNotFound(String),
+    #[error("Flights are unhealthy: {}", .0.join(", "))]
+    FlightsUnhealthy(Vec<String>),
+    #[error("timed out waiting for Flights to become healthy: {}", .0.join(", "))]
+    HealthTimeout(Vec<String>),
+    #[error(
+        "the link to the next page of Formations ({0}) is not on the same origin as the endpoint"
+    )]
+    ForeignPageLink(String),
+    #[error("the link to the next page of Formations ({0}) leads back to a page already fetched")]
+    PageLinkCycle(String),
+    #[cfg(feature = "manifest")]
+    #[error("invalid manifest: {0}")]
+    Manifest(ManifestError),
+}
+
+impl ComputeError {
+    /// The broad category of the error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ComputeError::FormationNotFound(_) => ErrorKind::NotFound,
+            ComputeError::FlightsUnhealthy(_) => ErrorKind::Other,
+            ComputeError::HealthTimeout(_) => ErrorKind::Timeout,
+            ComputeError::ForeignPageLink(_) => ErrorKind::Other,
+            ComputeError::PageLinkCycle(_) => ErrorKind::Other,
+            _ => ErrorKind::InvalidRequest,
+        }
+    }
+}
+
+/// An error in a Formation manifest, along with where in the manifest it was found
+#[cfg(feature = "manifest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "manifest")))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestError {
+    /// The 1-based line the error was found on, if known
+    pub line: Option<usize>,
+    /// The 1-based column the error was found at, if known
+    pub column: Option<usize>,
+    /// What is wrong with the manifest
+    pub message: String,
+}
+
+#[cfg(feature = "manifest")]
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(f, "line {line}, column {column}: {}", self.message)
+            }
+            (Some(line), None) => write!(f, "line {line}: {}", self.message),
+            _ => f.write_str(&self.message),
+        }
+    }
 }
+
+#[cfg(feature = "manifest")]
+impl std::error::Error for ManifestError {}
@@ -1,23 +1,46 @@
 //! The `/formations` endpoint APIs which allows working with [`Formation`]s,
 //! [`Flight`]s, and the underlying containers
 
+#[cfg(feature = "manifest")]
+mod manifest;
 mod models;
+mod paging;
+mod plan;
 pub mod response;
 mod validate;
+mod wait;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "manifest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "manifest")))]
+pub use manifest::*;
 pub use models::*;
+pub use paging::*;
+pub use plan::*;
+use reqwest::{
+    header::{HeaderValue, IF_MATCH},
+    Url,
+};
 pub use response::*;
+use uuid::Uuid;
 pub use validate::*;
+use wait::{Health, HealthWatch};
 
+#[cfg(feature = "identity_api_v1")]
+use crate::api::identity::v1::Credentials;
 use crate::{
     api::{
         compute::{
             error::{ComputeError, FormationValidation},
-            COMPUTE_API_URL,
+            COMPUTE_API_URL, COMPUTE_API_URL_ENV,
         },
-        error::map_api_error,
-        ApiRequest, RequestBuilder,
+        ApiCall, ApiRequest, HttpOptions, Observer, RateLimit, RateLimiter, RequestBuilder,
+        Response, RetryPolicy, Service, Transport,
     },
-    error::Result,
+    error::{ErrorKind, Result, SeaplaneError},
 };
 
 const COMPUTE_API_ROUTE: &str = "v2beta/formations";
@@ -26,7 +49,7 @@ const COMPUTE_API_ROUTE: &str = "v2beta/formations";
 /// request against the `/formations` APIs
 #[derive(Debug)]
 pub struct FormationsRequestBuilder {
-    builder: RequestBuilder<FormationId>,
+    pub(crate) builder: RequestBuilder<FormationId>,
 }
 
 impl From<RequestBuilder<FormationId>> for FormationsRequestBuilder {
@@ -38,17 +61,36 @@ impl Default for FormationsRequestBuilder {
 }
 
 impl FormationsRequestBuilder {
-    pub fn new() -> Self { RequestBuilder::new(COMPUTE_API_URL, COMPUTE_API_ROUTE).into() }
+    pub fn new() -> Self {
+        RequestBuilder::new(
+            Service::Compute,
+            COMPUTE_API_URL,
+            COMPUTE_API_URL_ENV,
+            COMPUTE_API_ROUTE,
+        )
+        .into()
+    }
 
     /// Builds a FormationsRequest from the given parameters
     pub fn build(self) -> Result<FormationsRequest> { Ok(self.builder.build()?.into()) }
 
     /// Set the token used in Bearer Authorization
     ///
-    /// **NOTE:** This is required for all endpoints
+    /// **NOTE:** This (or [`FormationsRequestBuilder::credentials`]) is required for all endpoints
     #[must_use]
     pub fn token<U: Into<String>>(self, token: U) -> Self { self.builder.token(token).into() }
 
+    /// Set the [`Credentials`] which provide the token used in Bearer Authorization, fetching and
+    /// refreshing it as required
+    ///
+    /// **NOTE:** This (or [`FormationsRequestBuilder::token`]) is required for all endpoints
+    #[cfg(feature = "identity_api_v1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "identity_api_v1")))]
+    #[must_use]
+    pub fn credentials(self, credentials: Credentials) -> Self {
+        self.builder.credentials(credentials).into()
+    }
+
     /// Allow non-HTTPS endpoints for this request (default: `false`)
     #[cfg(any(feature = "allow_insecure_urls", feature = "danger_zone"))]
     #[cfg_attr(docsrs, doc(cfg(any(feature = "allow_insecure_urls", feature = "danger_zone"))))]
@@ -61,6 +103,49 @@ impl FormationsRequestBuilder {
         self.builder.allow_invalid_certs(yes).into()
     }
 
+    /// Set the [`HttpOptions`] such as timeouts, proxy and TLS certificates used for the
+    /// connection (default: [`HttpOptions::default`])
+    #[must_use]
+    pub fn http_options(self, options: HttpOptions) -> Self {
+        self.builder.http_options(options).into()
+    }
+
+    /// Set the [`RetryPolicy`] used to retry idempotent requests which failed for transient
+    /// reasons (default: [`RetryPolicy::default`])
+    #[must_use]
+    pub fn retry_policy(self, policy: RetryPolicy) -> Self {
+        self.builder.retry_policy(policy).into()
+    }
+
+    /// Throttle requests on the client side using the [`RateLimiter`], which may be shared with
+    /// other requests (default: no client-side limit)
+    #[must_use]
+    pub fn rate_limiter(self, limiter: RateLimiter) -> Self {
+        self.builder.rate_limiter(limiter).into()
+    }
+
+    /// Notify the [`Observer`] of the outcome of every call, such as to record metrics (default: no
+    /// observer)
+    #[must_use]
+    pub fn observer<O: Observer + 'static>(self, observer: O) -> Self {
+        self.builder.observer(observer).into()
+    }
+
+    /// Send requests through the [`Transport`] rather than an HTTP client constructed from this
+    /// builder's settings, in which case those settings (such as [`HttpOptions`]) are ignored
+    #[must_use]
+    pub fn transport<T: Transport + 'static>(self, transport: T) -> Self {
+        self.builder.transport(Arc::new(transport)).into()
+    }
+
+    /// Set the base URL of the Compute API, such as `https://compute.example.com/`.
+    ///
+    /// Defaults to the value of the [`COMPUTE_API_URL_ENV`] environment variable if set, and
+    /// otherwise to [`COMPUTE_API_URL`]. The URL is parsed when the request is built, returning an
+    /// error if it is invalid.
+    #[must_use]
+    pub fn api_url<U: Into<String>>(self, url: U) -> Self { self.builder.api_url(url).into() }
+
     // Used in testing and development to manually set the URL
     #[doc(hidden)]
     pub fn base_url<U: AsRef<str>>(self, url: U) -> Self { self.builder.base_url(url).into() }
@@ -71,6 +156,17 @@ impl FormationsRequestBuilder {
     /// per Formation instance
     #[must_use]
     pub fn formation_id(self, oid: FormationId) -> Self { self.builder.target(oid).into() }
+
+    /// Send the key in the `Idempotency-Key` header of [`FormationsRequest::create`], so that the
+    /// server creates the Formation at most once no matter how many times the request is sent.
+    /// This also allows `create` to be retried according to the [`RetryPolicy`] (default: no key).
+    ///
+    /// The key should identify a single intent to create a Formation, such as a random UUID
+    /// generated once and reused whenever creating the same Formation is retried.
+    #[must_use]
+    pub fn idempotency_key<S: Into<String>>(self, key: S) -> Self {
+        self.builder.idempotency_key(key.into()).into()
+    }
 }
 
 /// For making requests against the `/formations` APIs.
@@ -87,6 +183,9 @@ impl FormationsRequest {
     /// Create a new request builder
     pub fn builder() -> FormationsRequestBuilder { FormationsRequestBuilder::new() }
 
+    /// The rate limit reported by the server in the most recent response to this request, if any
+    pub fn rate_limit(&self) -> Option<RateLimit> { self.request.rate_limit() }
+
     /// Create a new Formation and returns the IDs of the created Formation.
     ///
     /// Uses `POST /formations`
@@ -115,16 +214,190 @@ impl FormationsRequest {
     /// dbg!(resp);
     /// ```
     pub fn create(&self, formation: &Formation) -> Result<CreateFormationResponse> {
-        let req = self
+        self.create_with_response(formation)
+            .map(Response::into_body)
+    }
+
+    /// The async variant of [`FormationsRequest::create`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn create_async(&self, formation: &Formation) -> Result<CreateFormationResponse> {
+        self.create_with_response_async(formation)
+            .await
+            .map(Response::into_body)
+    }
+
+    /// Like [`FormationsRequest::create`], but also returns the status and headers of the response
+    pub fn create_with_response(
+        &self,
+        formation: &Formation,
+    ) -> Result<Response<CreateFormationResponse>> {
+        let resp = self.request.send(self.create_call(formation)?)?;
+        Response::json(resp)
+    }
+
+    /// The async variant of [`FormationsRequest::create_with_response`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn create_with_response_async(
+        &self,
+        formation: &Formation,
+    ) -> Result<Response<CreateFormationResponse>> {
+        let resp = self
             .request
-            .client
-            .post(self.request.endpoint_url.clone())
-            .bearer_auth(&self.request.token)
-            .json(formation);
-        let resp = req.send()?;
-        map_api_error(resp)?
-            .json::<CreateFormationResponse>()
-            .map_err(Into::into)
+            .send_async(self.create_call(formation)?)
+            .await?;
+        Response::json(resp)
+    }
+
+    fn create_call(&self, formation: &Formation) -> Result<ApiCall> {
+        let call = ApiCall::post(self.request.endpoint_url.clone())
+            .operation("create")
+            .json(formation)?;
+        match &self.request.idempotency_key {
+            Some(key) => call.idempotency_key(key),
+            None => Ok(call),
+        }
+    }
+
+    /// Creates a new Formation, or finds the one created by an earlier attempt if creating it
+    /// failed in a way which leaves it unknown whether it was created.
+    ///
+    /// The Formation is created with the request's idempotency key (or a newly generated one if
+    /// none was set, see [`FormationsRequestBuilder::idempotency_key`]). If the request timed out
+    /// or the server failed to respond, the Formations are listed with
+    /// [`FormationsRequest::iter_all`] and the one with the same name is returned if its
+    /// configuration is the same as `formation` (see [`FormationDiff`]). If there is none, creating
+    /// it is attempted once more with the same key.
+    ///
+    /// **NOTE:** If the name is already taken by a Formation with a different configuration, the
+    /// error of the create is returned, whose [`kind`](crate::error::SeaplaneError::kind) is
+    /// [`ErrorKind::Conflict`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use seaplane::api::compute::v2::{FormationsRequest, Formation, Flight};
+    /// let req = FormationsRequest::builder()
+    ///     .token("abc123")
+    ///     .idempotency_key("a8e4b2b4-6e2f-4b8e-9d0a-51b4fbc7c5a7")
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let formation = Formation::builder()
+    ///     .name("example-formation")
+    ///     .add_flight(Flight::new("myflight", "my/image:latest"))
+    ///     .build()
+    ///     .unwrap();
+    /// let resp = req.create_or_find(&formation).unwrap();
+    /// dbg!(resp.oid);
+    /// ```
+    pub fn create_or_find(&self, formation: &Formation) -> Result<CreateFormationResponse> {
+        self.create_or_find_with(formation, self.create_or_find_call(formation)?)
+    }
+
+    /// The async variant of [`FormationsRequest::create_or_find`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn create_or_find_async(
+        &self,
+        formation: &Formation,
+    ) -> Result<CreateFormationResponse> {
+        self.create_or_find_with_async(formation, self.create_or_find_call(formation)?)
+            .await
+    }
+
+    // Creates `formation` with `call`, which must carry an idempotency key, as described in
+    // `create_or_find`
+    fn create_or_find_with(
+        &self,
+        formation: &Formation,
+        call: ApiCall,
+    ) -> Result<CreateFormationResponse> {
+        match self.request.send(call.clone()) {
+            Ok(resp) => return resp.json(),
+            Err(e) if !is_ambiguous(&e) => return Err(e),
+            Err(_) => (),
+        }
+        if let Some(existing) = self.find_created(formation)? {
+            return Ok(existing);
+        }
+        match self.request.send(call) {
+            Ok(resp) => resp.json(),
+            Err(e) if e.kind() == ErrorKind::Conflict => self.find_created(formation)?.ok_or(e),
+            Err(e) => Err(e),
+        }
+    }
+
+    #[cfg(feature = "async")]
+    async fn create_or_find_with_async(
+        &self,
+        formation: &Formation,
+        call: ApiCall,
+    ) -> Result<CreateFormationResponse> {
+        match self.request.send_async(call.clone()).await {
+            Ok(resp) => return resp.json(),
+            Err(e) if !is_ambiguous(&e) => return Err(e),
+            Err(_) => (),
+        }
+        if let Some(existing) = self.find_created_async(formation).await? {
+            return Ok(existing);
+        }
+        match self.request.send_async(call).await {
+            Ok(resp) => resp.json(),
+            Err(e) if e.kind() == ErrorKind::Conflict => {
+                self.find_created_async(formation).await?.ok_or(e)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // The Formation of the same name as `formation`, if any and if its configuration is the same,
+    // meaning that it is the one created by an earlier attempt
+    fn find_created(&self, formation: &Formation) -> Result<Option<Formation>> {
+        Ok(self
+            .find_by_name(&formation.name)?
+            .filter(|existing| FormationDiff::new(existing, formation).is_empty()))
+    }
+
+    #[cfg(feature = "async")]
+    async fn find_created_async(&self, formation: &Formation) -> Result<Option<Formation>> {
+        Ok(self
+            .find_by_name_async(&formation.name)
+            .await?
+            .filter(|existing| FormationDiff::new(existing, formation).is_empty()))
+    }
+
+    // The Formation named `name`, if any
+    fn find_by_name(&self, name: &str) -> Result<Option<Formation>> {
+        for formation in self.iter_all() {
+            let formation = formation?;
+            if formation.name == name {
+                return Ok(Some(formation));
+            }
+        }
+        Ok(None)
+    }
+
+    #[cfg(feature = "async")]
+    async fn find_by_name_async(&self, name: &str) -> Result<Option<Formation>> {
+        let mut formations = self.iter_all();
+        while let Some(formation) = formations.next_async().await {
+            let formation = formation?;
+            if formation.name == name {
+                return Ok(Some(formation));
+            }
+        }
+        Ok(None)
+    }
+
+    // The create call, always with an idempotency key so that it may be sent again
+    fn create_or_find_call(&self, formation: &Formation) -> Result<ApiCall> {
+        let call = self.create_call(formation)?;
+        match &self.request.idempotency_key {
+            Some(_) => Ok(call),
+            None => call.idempotency_key(&Uuid::new_v4().to_string()),
+        }
     }
 
     /// Deletes a formation
@@ -144,30 +417,131 @@ impl FormationsRequest {
     /// assert!(req.delete().is_ok());
     /// ```
     pub fn delete(&self) -> Result<DeleteFormationResponse> {
-        use FormationValidation::*;
-        if self.request.target.is_none() {
-            Err(ComputeError::FormationValidation(MissingFormationId))?;
-        }
-        let url = self
-            .request
-            .endpoint_url
-            .join(&format!("formations/{}", self.oid()))?;
+        self.delete_with_response().map(Response::into_body)
+    }
+
+    /// The async variant of [`FormationsRequest::delete`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn delete_async(&self) -> Result<DeleteFormationResponse> {
+        self.delete_with_response_async()
+            .await
+            .map(Response::into_body)
+    }
+
+    /// Like [`FormationsRequest::delete`], but also returns the status and headers of the response
+    pub fn delete_with_response(&self) -> Result<Response<DeleteFormationResponse>> {
+        let resp = self.request.send(self.delete_call()?)?;
+        Ok(Response::empty(resp))
+    }
+
+    /// The async variant of [`FormationsRequest::delete_with_response`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn delete_with_response_async(&self) -> Result<Response<DeleteFormationResponse>> {
+        let resp = self.request.send_async(self.delete_call()?).await?;
+        Ok(Response::empty(resp))
+    }
+
+    fn delete_call(&self) -> Result<ApiCall> {
+        Ok(ApiCall::delete(self.formation_url()?).operation("delete"))
+    }
+
+    /// Replaces the configuration of the target Formation with `formation`, including its Flights
+    /// and gateway Flight, while keeping its [`FormationId`] and URL. Returns the updated
+    /// Formation.
+    ///
+    /// When `if_match` is the `ETag` of the Formation as last fetched (see
+    /// [`FormationsRequest::get_with_response`]) the update is only made if the Formation has not
+    /// changed since, and otherwise fails with an error whose
+    /// [`kind`](crate::error::SeaplaneError::kind) is [`ErrorKind::PreconditionFailed`]. This
+    /// keeps concurrent updates from overwriting each other. With `None` the Formation is replaced
+    /// unconditionally.
+    ///
+    /// Uses `PUT /formations/ID`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use seaplane::api::compute::v2::{FormationsRequest, Flight};
+    /// let req = FormationsRequest::builder()
+    ///     .token("abc123_token")
+    ///     .formation_id("frm-agc6amh7z527vijkv2cutplwaa".parse().unwrap())
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let current = req.get_with_response().unwrap();
+    /// let etag = current.etag().map(str::to_owned);
+    /// let mut formation = current.into_body();
+    /// formation.flights[0] = Flight::new("web", "foo.com/web:v2");
+    ///
+    /// let resp = req.update(&formation, etag.as_deref()).unwrap();
+    /// dbg!(resp);
+    /// ```
+    pub fn update(
+        &self,
+        formation: &Formation,
+        if_match: Option<&str>,
+    ) -> Result<UpdateFormationResponse> {
+        self.update_with_response(formation, if_match)
+            .map(Response::into_body)
+    }
+
+    /// The async variant of [`FormationsRequest::update`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn update_async(
+        &self,
+        formation: &Formation,
+        if_match: Option<&str>,
+    ) -> Result<UpdateFormationResponse> {
+        self.update_with_response_async(formation, if_match)
+            .await
+            .map(Response::into_body)
+    }
+
+    /// Like [`FormationsRequest::update`], but also returns the status and headers of the
+    /// response, including the new `ETag` of the Formation
+    pub fn update_with_response(
+        &self,
+        formation: &Formation,
+        if_match: Option<&str>,
+    ) -> Result<Response<UpdateFormationResponse>> {
+        let resp = self.request.send(self.update_call(formation, if_match)?)?;
+        Response::json(resp)
+    }
+
+    /// The async variant of [`FormationsRequest::update_with_response`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn update_with_response_async(
+        &self,
+        formation: &Formation,
+        if_match: Option<&str>,
+    ) -> Result<Response<UpdateFormationResponse>> {
         let resp = self
             .request
-            .client
-            .delete(url)
-            .bearer_auth(&self.request.token)
-            .send()?;
+            .send_async(self.update_call(formation, if_match)?)
+            .await?;
+        Response::json(resp)
+    }
 
-        map_api_error(resp)?;
-        Ok(())
+    fn update_call(&self, formation: &Formation, if_match: Option<&str>) -> Result<ApiCall> {
+        let call = ApiCall::put(self.formation_url()?)
+            .operation("update")
+            .json(formation)?;
+        Ok(match if_match {
+            Some(etag) => call.header(IF_MATCH, HeaderValue::try_from(etag)?),
+            None => call,
+        })
     }
 
-    // @TODO: a paging iterator may be more appropriate here in the future
-    /// Returns a list of all the Formations you have access to
+    /// Returns the first page of the Formations you have access to
     ///
     /// Uses `GET /formations`
     ///
+    /// To get the Formations of all pages use [`FormationsRequest::iter_all`] instead.
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -181,17 +555,44 @@ impl FormationsRequest {
     /// dbg!(resp);
     /// ```
     pub fn get_all(&self) -> Result<GetFormationsResponse> {
-        let client = reqwest::blocking::Client::new();
-        let resp = client
-            .get(self.request.endpoint_url.clone())
-            .bearer_auth(&self.request.token)
-            .send()?;
-
-        map_api_error(resp)?
-            .json::<GetFormationsResponse>()
-            .map_err(Into::into)
+        self.request
+            .send(ApiCall::get(self.request.endpoint_url.clone()).operation("get_all"))?
+            .json()
     }
 
+    /// The async variant of [`FormationsRequest::get_all`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn get_all_async(&self) -> Result<GetFormationsResponse> {
+        self.request
+            .send_async(ApiCall::get(self.request.endpoint_url.clone()).operation("get_all"))
+            .await?
+            .json()
+    }
+
+    /// Returns an iterator over all the Formations you have access to, which fetches the pages of
+    /// Formations as they are needed
+    ///
+    /// Uses `GET /formations`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use seaplane::api::compute::v2::{FormationsRequest};
+    /// let req = FormationsRequest::builder()
+    ///     .token("abc123_token")
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let names = req
+    ///     .iter_all()
+    ///     .map(|f| f.map(|f| f.name))
+    ///     .collect::<Result<Vec<_>, _>>()
+    ///     .unwrap();
+    /// dbg!(names);
+    /// ```
+    pub fn iter_all(&self) -> FormationsIter<'_> { FormationsIter::new(self) }
+
     /// Returns a single Formation's metadata
     ///
     /// Uses `GET /formations/ID`
@@ -210,22 +611,514 @@ impl FormationsRequest {
     /// dbg!(resp);
     /// ```
     pub fn get(&self) -> Result<GetFormationResponse> {
-        if self.request.target.is_none() {
-            Err(ComputeError::FormationValidation(FormationValidation::MissingFormationId))?
-        }
-        let url = self
+        self.get_with_response().map(Response::into_body)
+    }
+
+    /// The async variant of [`FormationsRequest::get`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn get_async(&self) -> Result<GetFormationResponse> {
+        self.get_with_response_async()
+            .await
+            .map(Response::into_body)
+    }
+
+    /// Like [`FormationsRequest::get`], but also returns the status and headers of the response
+    pub fn get_with_response(&self) -> Result<Response<GetFormationResponse>> {
+        let resp = self
             .request
-            .endpoint_url
-            .join(&format!("formations/{}", self.oid()))?;
+            .send(ApiCall::get(self.formation_url()?).operation("get"))?;
+        Response::json(resp)
+    }
+
+    /// The async variant of [`FormationsRequest::get_with_response`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn get_with_response_async(&self) -> Result<Response<GetFormationResponse>> {
         let resp = self
             .request
-            .client
-            .get(url)
-            .bearer_auth(&self.request.token)
-            .send()?;
+            .send_async(ApiCall::get(self.formation_url()?).operation("get"))
+            .await?;
+        Response::json(resp)
+    }
+
+    /// Returns the Formation with the given human friendly `name`.
+    ///
+    /// Formations are only addressed by their [`FormationId`] in the API, so the name is resolved
+    /// by listing the Formations (see [`FormationsRequest::iter_all`]). When no Formation has the
+    /// name a [`ComputeError::FormationNotFound`] is returned, whose
+    /// [`kind`](crate::error::SeaplaneError::kind) is [`ErrorKind::NotFound`].
+    ///
+    /// Uses `GET /formations`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use seaplane::api::compute::v2::{FormationsRequest};
+    /// let req = FormationsRequest::builder()
+    ///     .token("abc123_token")
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let resp = req.get_by_name("example-formation").unwrap();
+    /// dbg!(resp.oid);
+    /// ```
+    pub fn get_by_name(&self, name: &str) -> Result<GetFormationResponse> {
+        validate_formation_name(name).map_err(ComputeError::FormationValidation)?;
+        self.find_by_name(name)?
+            .ok_or_else(|| ComputeError::FormationNotFound(name.to_owned()).into())
+    }
+
+    /// The async variant of [`FormationsRequest::get_by_name`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn get_by_name_async(&self, name: &str) -> Result<GetFormationResponse> {
+        validate_formation_name(name).map_err(ComputeError::FormationValidation)?;
+        self.find_by_name_async(name)
+            .await?
+            .ok_or_else(|| ComputeError::FormationNotFound(name.to_owned()).into())
+    }
+
+    /// Deletes the Formation with the given human friendly `name`, which is resolved as in
+    /// [`FormationsRequest::get_by_name`]
+    ///
+    /// Uses `GET /formations` and then `DELETE /formations/ID`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use seaplane::api::compute::v2::{FormationsRequest};
+    /// let req = FormationsRequest::builder()
+    ///     .token("abc123_token")
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert!(req.delete_by_name("example-formation").is_ok());
+    /// ```
+    pub fn delete_by_name(&self, name: &str) -> Result<DeleteFormationResponse> {
+        let formation = self.get_by_name(name)?;
+        self.request.send(self.delete_by_name_call(formation)?)?;
+        Ok(())
+    }
+
+    /// The async variant of [`FormationsRequest::delete_by_name`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn delete_by_name_async(&self, name: &str) -> Result<DeleteFormationResponse> {
+        let formation = self.get_by_name_async(name).await?;
+        self.request
+            .send_async(self.delete_by_name_call(formation)?)
+            .await?;
+        Ok(())
+    }
+
+    fn delete_by_name_call(&self, formation: Formation) -> Result<ApiCall> {
+        let Some(oid) = formation.oid else {
+            return Err(ComputeError::FormationNotFound(formation.name).into());
+        };
+        Ok(ApiCall::delete(self.url_of(&oid)?).operation("delete_by_name"))
+    }
+
+    /// Returns the Flights of the target Formation
+    ///
+    /// Uses `GET /formations/ID/flights`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use seaplane::api::compute::v2::{FormationsRequest};
+    /// let req = FormationsRequest::builder()
+    ///     .token("abc123_token")
+    ///     .formation_id("frm-agc6amh7z527vijkv2cutplwaa".parse().unwrap())
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// for flight in req.get_flights().unwrap() {
+    ///     println!("{} is {}", flight.name, flight.status);
+    /// }
+    /// ```
+    pub fn get_flights(&self) -> Result<GetFlightsResponse> {
+        self.request.send(self.get_flights_call()?)?.json()
+    }
+
+    /// The async variant of [`FormationsRequest::get_flights`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn get_flights_async(&self) -> Result<GetFlightsResponse> {
+        self.request
+            .send_async(self.get_flights_call()?)
+            .await?
+            .json()
+    }
+
+    fn get_flights_call(&self) -> Result<ApiCall> {
+        Ok(ApiCall::get(self.flights_url("")?).operation("get_flights"))
+    }
+
+    /// Returns a single Flight of the target Formation, along with its status and the container
+    /// instances running it
+    ///
+    /// Uses `GET /formations/ID/flights/ID`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use seaplane::api::compute::v2::{FormationsRequest};
+    /// let req = FormationsRequest::builder()
+    ///     .token("abc123_token")
+    ///     .formation_id("frm-agc6amh7z527vijkv2cutplwaa".parse().unwrap())
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let resp = req
+    ///     .get_flight(&"flt-6f9asfo8ql0ar3mihb0ruv14i0".parse().unwrap())
+    ///     .unwrap();
+    /// println!("{} is running {} instances", resp.flight.name, resp.instances.len());
+    /// ```
+    pub fn get_flight(&self, flight: &FlightId) -> Result<GetFlightResponse> {
+        self.request.send(self.get_flight_call(flight)?)?.json()
+    }
+
+    /// The async variant of [`FormationsRequest::get_flight`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn get_flight_async(&self, flight: &FlightId) -> Result<GetFlightResponse> {
+        self.request
+            .send_async(self.get_flight_call(flight)?)
+            .await?
+            .json()
+    }
+
+    fn get_flight_call(&self, flight: &FlightId) -> Result<ApiCall> {
+        Ok(ApiCall::get(self.flights_url(&format!("/{flight}"))?).operation("get_flight"))
+    }
+
+    /// Restarts all container instances of a single Flight of the target Formation, leaving the
+    /// other Flights untouched. The Flight is [`FlightStatus::Starting`] until the new instances
+    /// are healthy, which can be waited for with [`FormationsRequest::wait_until_healthy`].
+    ///
+    /// Uses `POST /formations/ID/flights/ID/restart`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use seaplane::api::compute::v2::{FormationsRequest};
+    /// let req = FormationsRequest::builder()
+    ///     .token("abc123_token")
+    ///     .formation_id("frm-agc6amh7z527vijkv2cutplwaa".parse().unwrap())
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert!(req
+    ///     .restart_flight(&"flt-6f9asfo8ql0ar3mihb0ruv14i0".parse().unwrap())
+    ///     .is_ok());
+    /// ```
+    pub fn restart_flight(&self, flight: &FlightId) -> Result<RestartFlightResponse> {
+        self.request.send(self.restart_flight_call(flight)?)?;
+        Ok(())
+    }
+
+    /// The async variant of [`FormationsRequest::restart_flight`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn restart_flight_async(&self, flight: &FlightId) -> Result<RestartFlightResponse> {
+        self.request
+            .send_async(self.restart_flight_call(flight)?)
+            .await?;
+        Ok(())
+    }
+
+    fn restart_flight_call(&self, flight: &FlightId) -> Result<ApiCall> {
+        Ok(ApiCall::post(self.flights_url(&format!("/{flight}/restart"))?)
+            .operation("restart_flight"))
+    }
+
+    /// Computes the changes which turn the deployed Formations into the `desired` ones, without
+    /// making them. See [`Plan::new`] for how the changes are computed.
+    ///
+    /// **NOTE:** Every deployed Formation which is not desired is planned to be deleted.
+    ///
+    /// Each Formation which is planned to be updated is fetched once more to record its `ETag`
+    /// (see [`Change::Update`]), so that applying the plan fails rather than overwrites it if it
+    /// changes in the meantime.
+    ///
+    /// Uses `GET /formations` and `GET /formations/ID`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use seaplane::api::compute::v2::{FormationsRequest, Formation, Flight};
+    /// let req = FormationsRequest::builder()
+    ///     .token("abc123_token")
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let desired = Formation::builder()
+    ///     .name("example-formation")
+    ///     .add_flight(Flight::new("web", "foo.com/web:v2"))
+    ///     .build()
+    ///     .unwrap();
+    /// let plan = req.plan(&[desired]).unwrap();
+    /// print!("{plan}");
+    /// ```
+    pub fn plan(&self, desired: &[Formation]) -> Result<Plan> {
+        let deployed = self.iter_all().collect::<Result<Vec<_>>>()?;
+        let mut plan = Plan::new(deployed, desired.iter().cloned())?;
+        for change in &mut plan.changes {
+            let Some(call) = self.plan_get_call(change)? else { continue };
+            let current: Response<Formation> = Response::json(self.request.send(call)?)?;
+            let etag = current.etag().map(str::to_owned);
+            change.set_deployed(current.into_body(), etag);
+        }
+        plan.remove_noop_updates();
+        Ok(plan)
+    }
+
+    /// The async variant of [`FormationsRequest::plan`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn plan_async(&self, desired: &[Formation]) -> Result<Plan> {
+        let mut deployed = Vec::new();
+        let mut formations = self.iter_all();
+        while let Some(formation) = formations.next_async().await {
+            deployed.push(formation?);
+        }
+        let mut plan = Plan::new(deployed, desired.iter().cloned())?;
+        for change in &mut plan.changes {
+            let Some(call) = self.plan_get_call(change)? else { continue };
+            let current: Response<Formation> =
+                Response::json(self.request.send_async(call).await?)?;
+            let etag = current.etag().map(str::to_owned);
+            change.set_deployed(current.into_body(), etag);
+        }
+        plan.remove_noop_updates();
+        Ok(plan)
+    }
+
+    // The call fetching the deployed Formation of an update, or `None` for other changes
+    fn plan_get_call(&self, change: &Change) -> Result<Option<ApiCall>> {
+        match change {
+            Change::Update { deployed, .. } => {
+                Ok(Some(ApiCall::get(self.deployed_url(deployed)?).operation("plan_get")))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Turns the deployed Formations into the `desired` ones by computing a [`Plan`] as in
+    /// [`FormationsRequest::plan`] and applying it as in [`FormationsRequest::apply_plan`].
+    /// Returns the plan, which with `dry_run` is only computed and not applied.
+    ///
+    /// **NOTE:** Every deployed Formation which is not desired is deleted.
+    ///
+    /// Uses `GET /formations` and `GET /formations/ID`, and then `POST /formations`,
+    /// `PUT /formations/ID` and `DELETE /formations/ID` as needed
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use seaplane::api::compute::v2::{FormationsRequest, Formation, Flight};
+    /// let req = FormationsRequest::builder()
+    ///     .token("abc123_token")
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let desired = Formation::builder()
+    ///     .name("example-formation")
+    ///     .add_flight(Flight::new("web", "foo.com/web:v2"))
+    ///     .build()
+    ///     .unwrap();
+    /// // Review the changes first
+    /// print!("{}", req.apply(&[desired.clone()], true).unwrap());
+    /// req.apply(&[desired], false).unwrap();
+    /// ```
+    pub fn apply(&self, desired: &[Formation], dry_run: bool) -> Result<Plan> {
+        let plan = self.plan(desired)?;
+        if !dry_run {
+            self.apply_plan(&plan)?;
+        }
+        Ok(plan)
+    }
+
+    /// The async variant of [`FormationsRequest::apply`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn apply_async(&self, desired: &[Formation], dry_run: bool) -> Result<Plan> {
+        let plan = self.plan_async(desired).await?;
+        if !dry_run {
+            self.apply_plan_async(&plan).await?;
+        }
+        Ok(plan)
+    }
 
-        map_api_error(resp)?
-            .json::<GetFormationResponse>()
+    /// Makes the changes of a [`Plan`] in order, such as one reviewed after
+    /// [`FormationsRequest::plan`].
+    ///
+    /// Applying stops at the first change which fails, leaving the changes before it made. Planning
+    /// again then yields the changes which remain.
+    ///
+    /// Formations are created each with an idempotency key of its own, so that a create which
+    /// failed for transient reasons is retried. When the request has an idempotency key (see
+    /// [`FormationsRequestBuilder::idempotency_key`]) the key of each Formation is derived from it
+    /// and the name of the Formation, so applying the same plan again reuses the keys. A create
+    /// fails with an error whose [`kind`](crate::error::SeaplaneError::kind) is
+    /// [`ErrorKind::Conflict`] if a Formation of the same name has been created since the plan was
+    /// made, and an update with the `ETag` of the deployed Formation fails with an error whose kind
+    /// is [`ErrorKind::PreconditionFailed`] if the Formation has changed since. In either case,
+    /// plan again.
+    ///
+    /// Uses `POST /formations`, `PUT /formations/ID` and `DELETE /formations/ID` as needed
+    pub fn apply_plan(&self, plan: &Plan) -> Result<()> {
+        for change in &plan.changes {
+            self.request.send(self.apply_call(change)?)?;
+        }
+        Ok(())
+    }
+
+    /// The async variant of [`FormationsRequest::apply_plan`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn apply_plan_async(&self, plan: &Plan) -> Result<()> {
+        for change in &plan.changes {
+            self.request.send_async(self.apply_call(change)?).await?;
+        }
+        Ok(())
+    }
+
+    fn apply_call(&self, change: &Change) -> Result<ApiCall> {
+        Ok(match change {
+            Change::Create(desired) => {
+                let key = match &self.request.idempotency_key {
+                    Some(key) => format!("{key}-{}", desired.name),
+                    None => Uuid::new_v4().to_string(),
+                };
+                ApiCall::post(self.request.endpoint_url.clone())
+                    .operation("apply_create")
+                    .json(desired)?
+                    .idempotency_key(&key)?
+            }
+            Change::Update { deployed, desired, etag, .. } => {
+                let call = ApiCall::put(self.deployed_url(deployed)?)
+                    .operation("apply_update")
+                    .json(desired)?;
+                match etag {
+                    Some(etag) => call.header(IF_MATCH, HeaderValue::try_from(etag)?),
+                    None => call,
+                }
+            }
+            Change::Delete(deployed) => {
+                ApiCall::delete(self.deployed_url(deployed)?).operation("apply_delete")
+            }
+        })
+    }
+
+    // Internal; the URL of a Formation listed as deployed
+    fn deployed_url(&self, formation: &Formation) -> Result<Url> {
+        match &formation.oid {
+            Some(oid) => self.url_of(oid),
+            None => Err(ComputeError::FormationNotFound(formation.name.clone()).into()),
+        }
+    }
+
+    /// Waits until all Flights of the target Formation are healthy, such as after it was created
+    /// or updated, and returns the healthy Formation.
+    ///
+    /// The Formation is fetched every `poll_interval`, and `on_progress` is called with each
+    /// Flight the first time it is seen and whenever its [`FlightStatus`] changes. Waiting fails
+    /// with a [`ComputeError::FlightsUnhealthy`] naming the unhealthy Flights once no Flight is
+    /// still starting, or with a [`ComputeError::HealthTimeout`] naming the Flights which are not
+    /// healthy when `timeout` elapses. Errors fetching the Formation are returned as they occur.
+    ///
+    /// Uses `GET /formations/ID`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use seaplane::api::compute::v2::{FormationsRequest, Flight};
+    /// let req = FormationsRequest::builder()
+    ///     .token("abc123_token")
+    ///     .formation_id("frm-agc6amh7z527vijkv2cutplwaa".parse().unwrap())
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let resp = req
+    ///     .wait_until_healthy(Duration::from_secs(300), Duration::from_secs(5), |f: &Flight| {
+    ///         println!("{} is {}", f.name, f.status)
+    ///     })
+    ///     .unwrap();
+    /// dbg!(resp);
+    /// ```
+    pub fn wait_until_healthy<F: FnMut(&Flight)>(
+        &self,
+        timeout: Duration,
+        poll_interval: Duration,
+        mut on_progress: F,
+    ) -> Result<GetFormationResponse> {
+        let deadline = Instant::now() + timeout;
+        let mut watch = HealthWatch::default();
+        loop {
+            let formation = self.get()?;
+            match watch.poll(&formation, &mut on_progress) {
+                Health::Healthy => return Ok(formation),
+                Health::Unhealthy(flights) => Err(ComputeError::FlightsUnhealthy(flights))?,
+                Health::Starting => (),
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                Err(wait::timeout_error(&formation))?
+            }
+            std::thread::sleep(poll_interval.min(deadline - now));
+        }
+    }
+
+    /// The async variant of [`FormationsRequest::wait_until_healthy`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn wait_until_healthy_async<F: FnMut(&Flight)>(
+        &self,
+        timeout: Duration,
+        poll_interval: Duration,
+        mut on_progress: F,
+    ) -> Result<GetFormationResponse> {
+        let deadline = Instant::now() + timeout;
+        let mut watch = HealthWatch::default();
+        loop {
+            let formation = self.get_async().await?;
+            match watch.poll(&formation, &mut on_progress) {
+                Health::Healthy => return Ok(formation),
+                Health::Unhealthy(flights) => Err(ComputeError::FlightsUnhealthy(flights))?,
+                Health::Starting => (),
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                Err(wait::timeout_error(&formation))?
+            }
+            tokio::time::sleep(poll_interval.min(deadline - now)).await;
+        }
+    }
+
+    // Internal; the URL of the target formation, or an error if no target was set
+    fn formation_url(&self) -> Result<Url> {
+        if self.request.target.is_none() {
+            Err(ComputeError::FormationValidation(FormationValidation::MissingFormationId))?
+        }
+        self.url_of(self.oid())
+    }
+
+    // Internal; the URL of the Flights of the target formation followed by `path`, or an error if
+    // no target was set
+    fn flights_url(&self, path: &str) -> Result<Url> {
+        let mut url = self.formation_url()?;
+        url.set_path(&format!("{}/flights{path}", url.path()));
+        Ok(url)
+    }
+
+    // Internal; the URL of the formation `oid`
+    fn url_of(&self, oid: &FormationId) -> Result<Url> {
+        self.request
+            .endpoint_url
+            .join(&format!("formations/{oid}"))
             .map_err(Into::into)
     }
 
@@ -237,3 +1130,8 @@ impl FormationsRequest {
     #[inline]
     fn oid(&self) -> &FormationId { self.request.target.as_ref().unwrap() }
 }
+
+// Whether a failed create may nonetheless have created the Formation
+fn is_ambiguous(err: &SeaplaneError) -> bool {
+    matches!(err.kind(), ErrorKind::Timeout | ErrorKind::Unavailable | ErrorKind::ServerError)
+}
@@ -0,0 +1,704 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt, fs,
+    path::Path,
+    result::Result as StdResult,
+};
+
+use serde::{
+    de::{self, Deserializer, Visitor},
+    Deserialize,
+};
+
+use crate::{
+    api::{
+        compute::{
+            error::{ComputeError, ManifestError},
+            v2::{
+                validate_env_var_name, validate_formation_name, Flight, Formation, HealthCheck,
+                Port, Resources,
+            },
+        },
+        shared::v1::{Provider, Region},
+    },
+    error::Result,
+    rexports::container_image_ref::ImageReference,
+};
+
+thread_local! {
+    // The variables interpolated into the manifest being parsed on this thread, or `None` to use
+    // the environment
+    static VARS: RefCell<Option<HashMap<String, String>>> = RefCell::new(None);
+}
+
+/// The file formats a [`Manifest`] can be written in
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ManifestFormat {
+    /// [TOML](https://toml.io)
+    Toml,
+    /// [YAML](https://yaml.org)
+    Yaml,
+    /// [JSON](https://www.json.org)
+    Json,
+}
+
+impl ManifestFormat {
+    /// The format implied by the extension of `path` (`.toml`, `.yaml`, `.yml` or `.json`), if
+    /// any
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "toml" => Some(ManifestFormat::Toml),
+            "yaml" | "yml" => Some(ManifestFormat::Yaml),
+            "json" => Some(ManifestFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// The [`Formation`]s declared in a manifest file, so that they can be kept in version control
+/// instead of being built in code.
+///
+/// A manifest is a list of `formations`, whose fields are named as in the Compute API. Every
+/// string may reference variables as `${NAME}`, or as `${NAME:-default}` to fall back to a
+/// default when `NAME` is unset, which are interpolated from the environment. A literal `$` is
+/// written as `$$`.
+///
+/// The same validation is performed as by
+/// [`FormationBuilder`](crate::api::compute::v2::FormationBuilder)
+/// and [`FlightBuilder`](crate::api::compute::v2::FlightBuilder), and errors are reported as a
+/// [`ComputeError::Manifest`] with the line and column they were found at.
+///
+/// # Examples
+///
+/// ```
+/// # use seaplane::api::compute::v2::{Manifest, ManifestFormat};
+/// let manifest = r#"
+/// [[formations]]
+/// name = "example-formation"
+/// regions-allowed = ["XE"]
+///
+/// [[formations.flights]]
+/// name = "web"
+/// image = "foo.com/web:${WEB_VERSION:-latest}"
+/// ports = [{ port = 8080, protocol = "http" }]
+/// health-check = { path = "/healthz" }
+/// "#;
+///
+/// let manifest = Manifest::parse(manifest, ManifestFormat::Toml).unwrap();
+/// assert_eq!(manifest.formations[0].flights[0].image_str(), "foo.com/web:latest");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Manifest {
+    /// The Formations declared in the manifest, in order
+    pub formations: Vec<Formation>,
+}
+
+impl Manifest {
+    /// Reads the manifest at `path`, whose format is determined by its extension (see
+    /// [`ManifestFormat::from_path`]), interpolating variables from the environment
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let format = ManifestFormat::from_path(path).ok_or_else(|| {
+            manifest_error(
+                None,
+                None,
+                format!("cannot determine the format of manifest '{}'", path.display()),
+            )
+        })?;
+        Self::parse(&fs::read_to_string(path)?, format)
+    }
+
+    /// Parses a manifest written in `format`, interpolating variables from the environment
+    pub fn parse(text: &str, format: ManifestFormat) -> Result<Self> {
+        Self::parse_in(text, format, None)
+    }
+
+    /// Parses a manifest written in `format`, interpolating variables from `vars` instead of the
+    /// environment
+    pub fn parse_with_vars(
+        text: &str,
+        format: ManifestFormat,
+        vars: &HashMap<String, String>,
+    ) -> Result<Self> {
+        Self::parse_in(text, format, Some(vars.clone()))
+    }
+
+    fn parse_in(
+        text: &str,
+        format: ManifestFormat,
+        vars: Option<HashMap<String, String>>,
+    ) -> Result<Self> {
+        // Resets the variables even if parsing panics
+        struct Reset;
+        impl Drop for Reset {
+            fn drop(&mut self) { VARS.with(|v| v.borrow_mut().take()); }
+        }
+
+        VARS.with(|v| *v.borrow_mut() = vars);
+        let _reset = Reset;
+        let raw: RawManifest = match format {
+            ManifestFormat::Toml => toml::from_str(text).map_err(|e| {
+                let (line, column) = match e.span() {
+                    Some(span) => line_column(text, span.start),
+                    None => (None, None),
+                };
+                manifest_error(line, column, e.message())
+            })?,
+            ManifestFormat::Yaml => serde_yaml::from_str(text).map_err(|e| {
+                let location = e.location();
+                manifest_error(
+                    location.as_ref().map(|l| l.line()),
+                    location.as_ref().map(|l| l.column()),
+                    yaml_message(&e),
+                )
+            })?,
+            ManifestFormat::Json => serde_json::from_str(text).map_err(|e| {
+                let known = |n| if e.line() == 0 { None } else { Some(n) };
+                manifest_error(known(e.line()), known(e.column()), json_message(&e))
+            })?,
+        };
+
+        Ok(Self { formations: raw.formations.into_iter().map(|f| f.0).collect() })
+    }
+}
+
+fn manifest_error(
+    line: Option<usize>,
+    column: Option<usize>,
+    message: impl Into<String>,
+) -> ComputeError {
+    ComputeError::Manifest(ManifestError { line, column, message: message.into() })
+}
+
+// The 1-based line and column of the byte `offset` in `text`
+fn line_column(text: &str, offset: usize) -> (Option<usize>, Option<usize>) {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (Some(before.matches('\n').count() + 1), Some(before[line_start..].chars().count() + 1))
+}
+
+// The message of a YAML error, without the location which is reported separately
+fn yaml_message(e: &serde_yaml::Error) -> String {
+    let message = e.to_string();
+    match message.rfind(" at line ") {
+        Some(i) if e.location().is_some() => message[..i].to_owned(),
+        _ => message,
+    }
+}
+
+// The message of a JSON error, without the location which is reported separately
+fn json_message(e: &serde_json::Error) -> String {
+    let message = e.to_string();
+    match message.rfind(" at line ") {
+        Some(i) if e.line() != 0 => message[..i].to_owned(),
+        _ => message,
+    }
+}
+
+/// Replaces the `${NAME}` and `${NAME:-default}` references in `raw` with the values of the
+/// variables, and `$$` with `$`
+fn interpolate(raw: &str) -> StdResult<String, String> {
+    let mut out = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            out.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('{') {
+            let end = after
+                .find('}')
+                .ok_or_else(|| format!("unterminated variable reference in '{raw}'"))?;
+            let (name, default) = match after[..end].split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (&after[..end], None),
+            };
+            validate_env_var_name(name)
+                .map_err(|_| format!("invalid variable name '{name}' in '{raw}'"))?;
+            match (lookup(name), default) {
+                (Some(value), _) => out.push_str(&value),
+                (None, Some(default)) => out.push_str(default),
+                (None, None) => return Err(format!("variable '{name}' is not set")),
+            }
+            rest = &after[end + 1..];
+        } else {
+            out.push('$');
+        }
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+// The value of the variable `name`
+fn lookup(name: &str) -> Option<String> {
+    VARS.with(|vars| match &*vars.borrow() {
+        Some(vars) => vars.get(name).cloned(),
+        None => std::env::var(name).ok(),
+    })
+}
+
+/// Deserializes a string and converts it with `convert`, within the deserialization of the
+/// string itself so that errors are reported at its position
+fn deserialize_str_with<'de, D, T, F>(deserializer: D, convert: F) -> StdResult<T, D::Error>
+where
+    D: Deserializer<'de>,
+    F: FnOnce(&str) -> StdResult<T, String>,
+{
+    struct StrVisitor<F>(F);
+
+    impl<'de, T, F: FnOnce(&str) -> StdResult<T, String>> Visitor<'de> for StrVisitor<F> {
+        type Value = T;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result { f.write_str("a string") }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> StdResult<T, E> {
+            (self.0)(v).map_err(E::custom)
+        }
+    }
+
+    deserializer.deserialize_str(StrVisitor(convert))
+}
+
+/// A string with its variables interpolated
+struct Text(String);
+
+impl<'de> Deserialize<'de> for Text {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> StdResult<Self, D::Error> {
+        deserialize_str_with(deserializer, |s| interpolate(s).map(Text))
+    }
+}
+
+/// The validated name of an environment variable
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct EnvVarName(String);
+
+impl<'de> Deserialize<'de> for EnvVarName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> StdResult<Self, D::Error> {
+        deserialize_str_with(deserializer, |name| {
+            validate_env_var_name(name).map_err(|e| e.to_string())?;
+            Ok(EnvVarName(name.to_owned()))
+        })
+    }
+}
+
+fn formation_name<'de, D: Deserializer<'de>>(deserializer: D) -> StdResult<String, D::Error> {
+    deserialize_str_with(deserializer, |s| {
+        let name = interpolate(s)?;
+        validate_formation_name(&name).map_err(|e| e.to_string())?;
+        Ok(name)
+    })
+}
+
+fn image_reference<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> StdResult<ImageReference, D::Error> {
+    deserialize_str_with(deserializer, |s| {
+        let image = interpolate(s)?;
+        image
+            .parse()
+            .map_err(|e| format!("invalid container image reference '{image}': {e}"))
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawManifest {
+    #[serde(default)]
+    formations: Vec<ManifestFormation>,
+}
+
+#[derive(Deserialize)]
+#[serde(try_from = "RawFormation")]
+struct ManifestFormation(Formation);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct RawFormation {
+    #[serde(deserialize_with = "formation_name")]
+    name: String,
+    flights: Vec<ManifestFlight>,
+    gateway_flight: Option<Text>,
+    #[serde(default)]
+    providers_allowed: BTreeSet<Provider>,
+    #[serde(default)]
+    providers_denied: BTreeSet<Provider>,
+    #[serde(default)]
+    regions_allowed: BTreeSet<Region>,
+    #[serde(default)]
+    regions_denied: BTreeSet<Region>,
+}
+
+impl TryFrom<RawFormation> for ManifestFormation {
+    type Error = String;
+
+    fn try_from(raw: RawFormation) -> StdResult<Self, Self::Error> {
+        let mut builder = Formation::builder().name(raw.name);
+        for ManifestFlight(flight) in raw.flights {
+            builder = builder.add_flight(flight);
+        }
+        if let Some(Text(gateway)) = raw.gateway_flight {
+            builder = builder.gateway_flight(gateway);
+        }
+        builder = raw
+            .providers_allowed
+            .into_iter()
+            .fold(builder, |b, p| b.add_allowed_provider(p));
+        builder = raw
+            .providers_denied
+            .into_iter()
+            .fold(builder, |b, p| b.add_denied_provider(p));
+        builder = raw
+            .regions_allowed
+            .into_iter()
+            .fold(builder, |b, r| b.add_allowed_region(r));
+        builder = raw
+            .regions_denied
+            .into_iter()
+            .fold(builder, |b, r| b.add_denied_region(r));
+
+        builder
+            .build()
+            .map(ManifestFormation)
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(try_from = "RawFlight")]
+struct ManifestFlight(Flight);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct RawFlight {
+    name: Text,
+    #[serde(deserialize_with = "image_reference")]
+    image: ImageReference,
+    #[serde(default)]
+    env: BTreeMap<EnvVarName, Text>,
+    #[serde(default)]
+    secrets: BTreeMap<EnvVarName, Text>,
+    #[serde(default)]
+    ports: Vec<Port>,
+    resources: Option<Resources>,
+    min_replicas: Option<u32>,
+    max_replicas: Option<u32>,
+    #[serde(default)]
+    command: Vec<Text>,
+    #[serde(default)]
+    args: Vec<Text>,
+    health_check: Option<RawHealthCheck>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawHealthCheck {
+    path: Text,
+    port: Option<u16>,
+}
+
+impl TryFrom<RawFlight> for ManifestFlight {
+    type Error = String;
+
+    fn try_from(raw: RawFlight) -> StdResult<Self, Self::Error> {
+        let mut builder = Flight::builder()
+            .name(raw.name.0)
+            .image_reference(raw.image)
+            .command(raw.command.into_iter().map(|t| t.0))
+            .args(raw.args.into_iter().map(|t| t.0));
+        for (EnvVarName(name), Text(value)) in raw.env {
+            builder = builder.env(name, value);
+        }
+        for (EnvVarName(name), Text(secret)) in raw.secrets {
+            builder = builder.secret(name, secret);
+        }
+        for port in raw.ports {
+            builder = builder.port(port.port, port.protocol);
+        }
+        if let Some(Resources { cpu_millis, memory_mib }) = raw.resources {
+            if let Some(cpu) = cpu_millis {
+                builder = builder.cpu_millis(cpu);
+            }
+            if let Some(memory) = memory_mib {
+                builder = builder.memory_mib(memory);
+            }
+        }
+        if let Some(min) = raw.min_replicas {
+            builder = builder.min_replicas(min);
+        }
+        if let Some(max) = raw.max_replicas {
+            builder = builder.max_replicas(max);
+        }
+        if let Some(check) = raw.health_check {
+            let mut health_check = HealthCheck::new(check.path.0);
+            health_check.port = check.port;
+            builder = builder.health_check(health_check);
+        }
+
+        builder
+            .build()
+            .map(ManifestFlight)
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod manifest_tests {
+    use super::*;
+    use crate::{
+        api::compute::{error::FormationValidation, v2::Protocol},
+        error::SeaplaneError,
+    };
+
+    const TOML: &str = r#"
+[[formations]]
+name = "web-app"
+gateway-flight = "web"
+regions-allowed = ["XE"]
+providers-denied = ["AWS"]
+
+[[formations.flights]]
+name = "web"
+image = "foo.com/web:${VERSION}"
+env = { GREETING = "$$HOME is ${HOME:-/root}" }
+secrets = { DB_PASSWORD = "db-password" }
+ports = [{ port = 8080, protocol = "http" }]
+min-replicas = 2
+health-check = { path = "/healthz" }
+
+[[formations.flights]]
+name = "worker"
+image = "foo.com/worker:${VERSION}"
+command = ["/bin/worker", "--queue", "${QUEUE:-jobs}"]
+
+[[formations]]
+name = "batch"
+
+[[formations.flights]]
+name = "cron"
+image = "foo.com/cron:latest"
+"#;
+
+    const YAML: &str = r#"
+formations:
+  - name: web-app
+    gateway-flight: web
+    regions-allowed: [XE]
+    providers-denied: [AWS]
+    flights:
+      - name: web
+        image: foo.com/web:${VERSION}
+        env:
+          GREETING: $$HOME is ${HOME:-/root}
+        secrets:
+          DB_PASSWORD: db-password
+        ports:
+          - port: 8080
+            protocol: http
+        min-replicas: 2
+        health-check:
+          path: /healthz
+      - name: worker
+        image: foo.com/worker:${VERSION}
+        command: [/bin/worker, --queue, "${QUEUE:-jobs}"]
+  - name: batch
+    flights:
+      - name: cron
+        image: foo.com/cron:latest
+"#;
+
+    const JSON: &str = r#"{
+  "formations": [
+    {
+      "name": "web-app",
+      "gateway-flight": "web",
+      "regions-allowed": ["XE"],
+      "providers-denied": ["AWS"],
+      "flights": [
+        {
+          "name": "web",
+          "image": "foo.com/web:${VERSION}",
+          "env": { "GREETING": "$$HOME is ${HOME:-/root}" },
+          "secrets": { "DB_PASSWORD": "db-password" },
+          "ports": [{ "port": 8080, "protocol": "http" }],
+          "min-replicas": 2,
+          "health-check": { "path": "/healthz" }
+        },
+        {
+          "name": "worker",
+          "image": "foo.com/worker:${VERSION}",
+          "command": ["/bin/worker", "--queue", "${QUEUE:-jobs}"]
+        }
+      ]
+    },
+    {
+      "name": "batch",
+      "flights": [{ "name": "cron", "image": "foo.com/cron:latest" }]
+    }
+  ]
+}"#;
+
+    fn vars() -> HashMap<String, String> {
+        HashMap::from([("VERSION".into(), "v1.2".into()), ("QUEUE".into(), "emails".into())])
+    }
+
+    // The line, column and message of the error parsing `text`
+    fn error(text: &str, format: ManifestFormat) -> (Option<usize>, Option<usize>, String) {
+        match Manifest::parse_with_vars(text, format, &vars()) {
+            Err(SeaplaneError::ComputeRequest(ComputeError::Manifest(e))) => {
+                (e.line, e.column, e.message)
+            }
+            res => panic!("expected a manifest error, got {res:?}"),
+        }
+    }
+
+    #[test]
+    fn all_formats_agree() {
+        let expected = vec![
+            Formation::builder()
+                .name("web-app")
+                .gateway_flight("web")
+                .add_allowed_region(Region::XE)
+                .add_denied_provider(Provider::AWS)
+                .add_flight(
+                    Flight::builder()
+                        .name("web")
+                        .image("foo.com/web:v1.2")
+                        .env("GREETING", "$HOME is /root")
+                        .secret("DB_PASSWORD", "db-password")
+                        .port(8080, Protocol::Http)
+                        .min_replicas(2)
+                        .health_check(HealthCheck::new("/healthz"))
+                        .build()
+                        .unwrap(),
+                )
+                .add_flight(
+                    Flight::builder()
+                        .name("worker")
+                        .image("foo.com/worker:v1.2")
+                        .command(["/bin/worker", "--queue", "emails"])
+                        .build()
+                        .unwrap(),
+                )
+                .build()
+                .unwrap(),
+            Formation::builder()
+                .name("batch")
+                .add_flight(Flight::new("cron", "foo.com/cron:latest"))
+                .build()
+                .unwrap(),
+        ];
+
+        for (text, format) in [
+            (TOML, ManifestFormat::Toml),
+            (YAML, ManifestFormat::Yaml),
+            (JSON, ManifestFormat::Json),
+        ] {
+            let manifest = Manifest::parse_with_vars(text, format, &vars()).unwrap();
+            assert_eq!(manifest.formations, expected, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn interpolation() {
+        VARS.with(|v| *v.borrow_mut() = Some(vars()));
+        assert_eq!(interpolate("${VERSION}-${QUEUE}").unwrap(), "v1.2-emails");
+        assert_eq!(interpolate("${UNSET:-a:-b}").unwrap(), "a:-b");
+        assert_eq!(interpolate("${VERSION:-}").unwrap(), "v1.2");
+        assert_eq!(interpolate("$$${VERSION}$$").unwrap(), "$v1.2$");
+        assert_eq!(interpolate("$5 and $").unwrap(), "$5 and $");
+        assert_eq!(interpolate("${UNSET}").unwrap_err(), "variable 'UNSET' is not set");
+        assert!(interpolate("${VERSION").is_err());
+        assert!(interpolate("${1X}").is_err());
+        VARS.with(|v| v.borrow_mut().take());
+    }
+
+    // Each manifest in turn, with `from` replaced by `to`, fails at the given line with a message
+    // containing `message`
+    fn check_errors(replace: [(&str, &str, usize); 3], message: &str) {
+        let manifests = [
+            (TOML, ManifestFormat::Toml),
+            (YAML, ManifestFormat::Yaml),
+            (JSON, ManifestFormat::Json),
+        ];
+        for ((text, format), (from, to, expected_line)) in manifests.into_iter().zip(replace) {
+            let (line, column, msg) = error(&text.replacen(from, to, 1), format);
+            assert_eq!(line, Some(expected_line), "{format:?}: {msg}");
+            assert!(column.is_some(), "{format:?}: {msg}");
+            assert!(msg.contains(message), "{format:?}: {msg}");
+        }
+    }
+
+    #[test]
+    fn errors_point_at_values() {
+        check_errors(
+            [
+                ("\"batch\"", "\"Batch\"", 23),
+                ("name: batch", "name: Batch", 23),
+                ("\"batch\"", "\"Batch\"", 26),
+            ],
+            &FormationValidation::NameInvalidChar.to_string(),
+        );
+        check_errors(
+            [
+                ("GREETING", "1GREETING", 11),
+                ("GREETING", "1GREETING", 11),
+                ("GREETING", "1GREETING", 12),
+            ],
+            &FormationValidation::InvalidEnvVarName.to_string(),
+        );
+        check_errors(
+            [
+                ("web:${VERSION}", "web:${MISSING}", 10),
+                ("web:${VERSION}", "web:${MISSING}", 9),
+                ("web:${VERSION}", "web:${MISSING}", 11),
+            ],
+            "variable 'MISSING' is not set",
+        );
+        check_errors(
+            [
+                ("web:${VERSION}", "web:${VERSION}!", 10),
+                ("web:${VERSION}", "web:${VERSION}!", 9),
+                ("web:${VERSION}", "web:${VERSION}!", 11),
+            ],
+            "invalid container image reference 'foo.com/web:v1.2!'",
+        );
+        check_errors(
+            [("XE", "XX", 5), ("XE", "XX", 5), ("XE", "XX", 6)],
+            "Matching variant not found",
+        );
+    }
+
+    #[test]
+    fn builder_errors_point_at_objects() {
+        // Checks across fields are made once the whole Flight or Formation has been read
+        check_errors(
+            [
+                ("gateway-flight = \"web\"", "gateway-flight = \"api\"", 2),
+                ("gateway-flight: web", "gateway-flight: api", 3),
+                ("\"gateway-flight\": \"web\"", "\"gateway-flight\": \"api\"", 25),
+            ],
+            &FormationValidation::InvalidGatewayFlight.to_string(),
+        );
+        check_errors(
+            [
+                ("path = \"/healthz\" }", "path = \"/healthz\", port = 9000 }", 8),
+                ("path: /healthz", "path: /healthz\n          port: 9000", 8),
+                ("\"/healthz\" }", "\"/healthz\", \"port\": 9000 }", 18),
+            ],
+            &FormationValidation::InvalidHealthCheckPort.to_string(),
+        );
+    }
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(ManifestFormat::from_path("a/formations.toml"), Some(ManifestFormat::Toml));
+        assert_eq!(ManifestFormat::from_path("formations.yml"), Some(ManifestFormat::Yaml));
+        assert_eq!(ManifestFormat::from_path("formations.yaml"), Some(ManifestFormat::Yaml));
+        assert_eq!(ManifestFormat::from_path("formations.json"), Some(ManifestFormat::Json));
+        assert_eq!(ManifestFormat::from_path("formations"), None);
+    }
+}
@@ -1,3 +1,9 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    result::Result as StdResult,
+};
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use strum::{Display, EnumString};
 use url::Url;
@@ -5,9 +11,12 @@ use url::Url;
 #[cfg(doc)]
 use crate::api::compute::v2::FormationsRequest;
 use crate::{
-    api::compute::{
-        error::{ComputeError, FormationValidation},
-        v2::validate_formation_name,
+    api::{
+        compute::{
+            error::{ComputeError, FormationValidation},
+            v2::{validate_env_var_name, validate_formation_name},
+        },
+        shared::v1::{Provider, Region},
     },
     error::Result,
     rexports::{
@@ -87,6 +96,10 @@ pub struct FormationBuilder {
     flights: Vec<Flight>,
     name: String,
     gateway_flight: Option<String>,
+    providers_allowed: BTreeSet<Provider>,
+    providers_denied: BTreeSet<Provider>,
+    regions_allowed: BTreeSet<Region>,
+    regions_denied: BTreeSet<Region>,
 }
 
 impl FormationBuilder {
@@ -115,6 +128,66 @@ impl FormationBuilder {
         self
     }
 
+    /// Add a [`Provider`] which is allowed for use when placing the Flights of this Formation.
+    ///
+    /// By default all [`Provider`]s are allowed.
+    ///
+    /// If this conflicts with `providers_denied` ([`FormationBuilder::add_denied_provider`])
+    /// (e.g. [`Provider::GCP`] is both allowed and denied) the configuration is invalid and will
+    /// be rejected.
+    ///
+    /// **NOTE:** This method can be called multiple times. All values will be utilized.
+    #[must_use]
+    pub fn add_allowed_provider<P: Into<Provider>>(mut self, provider: P) -> Self {
+        self.providers_allowed.insert(provider.into());
+        self
+    }
+
+    /// The inverse of [`FormationBuilder::add_allowed_provider`] which specifies a [`Provider`]
+    /// which is not allowed for use when placing the Flights of this Formation.
+    ///
+    /// By default no [`Provider`]s are denied.
+    ///
+    /// If this conflicts with `providers_allowed` ([`FormationBuilder::add_allowed_provider`])
+    /// the configuration is invalid and will be rejected.
+    ///
+    /// **NOTE:** This method can be called multiple times. All values will be utilized.
+    #[must_use]
+    pub fn add_denied_provider<P: Into<Provider>>(mut self, provider: P) -> Self {
+        self.providers_denied.insert(provider.into());
+        self
+    }
+
+    /// Add a [`Region`] which is allowed for use when placing the Flights of this Formation.
+    ///
+    /// By default all [`Region`]s are allowed.
+    ///
+    /// If this conflicts with `regions_denied` ([`FormationBuilder::add_denied_region`]) (e.g.
+    /// [`Region::XE`] is both allowed and denied) the configuration is invalid and will be
+    /// rejected.
+    ///
+    /// **NOTE:** This method can be called multiple times. All values will be utilized.
+    #[must_use]
+    pub fn add_allowed_region<R: Into<Region>>(mut self, region: R) -> Self {
+        self.regions_allowed.insert(region.into());
+        self
+    }
+
+    /// The inverse of [`FormationBuilder::add_allowed_region`] which specifies a [`Region`] which
+    /// is not allowed for use when placing the Flights of this Formation.
+    ///
+    /// By default no [`Region`]s are denied.
+    ///
+    /// If this conflicts with `regions_allowed` ([`FormationBuilder::add_allowed_region`]) the
+    /// configuration is invalid and will be rejected.
+    ///
+    /// **NOTE:** This method can be called multiple times. All values will be utilized.
+    #[must_use]
+    pub fn add_denied_region<R: Into<Region>>(mut self, region: R) -> Self {
+        self.regions_denied.insert(region.into());
+        self
+    }
+
     /// Removes all [`Flight`]s from this Formation Configuration
     pub fn clear_flights(&mut self) { self.flights.clear(); }
 
@@ -137,12 +210,29 @@ impl FormationBuilder {
 
         validate_formation_name(&self.name).map_err(ComputeError::FormationValidation)?;
 
+        if (self
+            .providers_allowed
+            .intersection(&self.providers_denied)
+            .count()
+            + self
+                .regions_allowed
+                .intersection(&self.regions_denied)
+                .count())
+            > 0
+        {
+            return Err(ComputeError::FormationValidation(ConflictingRequirements).into());
+        }
+
         Ok(Formation {
             name: self.name,
             oid: None,
             url: None,
             flights: self.flights,
             gateway_flight: self.gateway_flight,
+            providers_allowed: self.providers_allowed,
+            providers_denied: self.providers_denied,
+            regions_allowed: self.regions_allowed,
+            regions_denied: self.regions_denied,
         })
     }
 }
@@ -168,6 +258,22 @@ pub struct Formation {
     /// The Flight who will receive all the public HTTP(s) traffic that arrives on the public
     /// Formation URL
     pub gateway_flight: Option<String>,
+
+    /// The [`Provider`]s the Flights may be placed on, or all if empty
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub providers_allowed: BTreeSet<Provider>,
+
+    /// The [`Provider`]s the Flights may not be placed on
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub providers_denied: BTreeSet<Provider>,
+
+    /// The [`Region`]s the Flights may be placed in, or all if empty
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub regions_allowed: BTreeSet<Region>,
+
+    /// The [`Region`]s the Flights may not be placed in
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub regions_denied: BTreeSet<Region>,
 }
 
 impl Formation {
@@ -201,6 +307,7 @@ impl Formation {
 #[cfg(test)]
 mod formation_tests {
     use super::*;
+    use crate::error::SeaplaneError;
 
     #[test]
     fn deser() {
@@ -224,12 +331,15 @@ mod formation_tests {
                     .unwrap(),
             ),
             flights: vec![Flight {
-                name: "example-flight".into(),
                 oid: Some("flt-6f9asfo8ql0ar3mihb0ruv14i0".parse().unwrap()),
-                image: "foo.com/bar:latest".parse::<ImageReference>().unwrap(),
                 status: FlightStatus::Starting,
+                ..Flight::new("example-flight", "foo.com/bar:latest")
             }],
             gateway_flight: Some("example-flight".into()),
+            providers_allowed: BTreeSet::new(),
+            providers_denied: BTreeSet::new(),
+            regions_allowed: BTreeSet::new(),
+            regions_denied: BTreeSet::new(),
         };
 
         assert_eq!(model, serde_json::from_str(json).unwrap());
@@ -243,12 +353,15 @@ mod formation_tests {
             oid: Some("frm-sjt5inobm97i317b95uerqv080".parse().unwrap()),
             url: None,
             flights: vec![Flight {
-                name: "example-flight".into(),
                 oid: Some("flt-6f9asfo8ql0ar3mihb0ruv14i0".parse().unwrap()),
-                image: "foo.com/bar:latest".parse::<ImageReference>().unwrap(),
                 status: FlightStatus::Starting,
+                ..Flight::new("example-flight", "foo.com/bar:latest")
             }],
             gateway_flight: Some("example-flight".into()),
+            providers_allowed: BTreeSet::new(),
+            providers_denied: BTreeSet::new(),
+            regions_allowed: BTreeSet::new(),
+            regions_denied: BTreeSet::new(),
         };
 
         assert_eq!(json.to_string(), serde_json::to_string(&model).unwrap());
@@ -262,16 +375,147 @@ mod formation_tests {
             url: None,
             oid: None,
             flights: vec![Flight {
-                name: "example-flight".into(),
                 oid: None,
-                image: "foo.com/bar:latest".parse::<ImageReference>().unwrap(),
                 status: FlightStatus::Healthy,
+                ..Flight::new("example-flight", "foo.com/bar:latest")
             }],
             gateway_flight: Some("example-flight".into()),
+            providers_allowed: BTreeSet::new(),
+            providers_denied: BTreeSet::new(),
+            regions_allowed: BTreeSet::new(),
+            regions_denied: BTreeSet::new(),
         };
 
         assert_eq!(json.to_string(), serde_json::to_string(&model).unwrap());
     }
+
+    #[test]
+    fn placement() {
+        let json = r#"{"name":"example-formation","flights":[{"name":"example-flight","image":"foo.com/bar:latest"}],"gateway-flight":null,"providers-denied":["AWS"],"regions-allowed":["XE","XU"]}"#;
+        let model = Formation::builder()
+            .name("example-formation")
+            .add_flight(Flight::new("example-flight", "foo.com/bar:latest"))
+            .add_allowed_region(Region::XU)
+            .add_allowed_region(Region::XE)
+            .add_denied_provider(Provider::AWS)
+            .build()
+            .unwrap();
+
+        assert_eq!(json, serde_json::to_string(&model).unwrap());
+        assert_eq!(model, serde_json::from_str(json).unwrap());
+    }
+
+    #[test]
+    fn conflicting_placement() {
+        let builder = || {
+            Formation::builder()
+                .name("example-formation")
+                .add_flight(Flight::new("example-flight", "foo.com/bar:latest"))
+        };
+        let conflict = Err(SeaplaneError::ComputeRequest(ComputeError::FormationValidation(
+            FormationValidation::ConflictingRequirements,
+        )));
+
+        assert_eq!(
+            builder()
+                .add_allowed_region(Region::XE)
+                .add_denied_region(Region::XE)
+                .build(),
+            conflict
+        );
+        assert_eq!(
+            builder()
+                .add_allowed_provider(Provider::GCP)
+                .add_denied_provider(Provider::GCP)
+                .build(),
+            conflict
+        );
+        assert!(builder()
+            .add_allowed_region(Region::XE)
+            .add_denied_region(Region::XU)
+            .add_allowed_provider(Provider::GCP)
+            .add_denied_provider(Provider::AWS)
+            .build()
+            .is_ok());
+    }
+}
+
+/// The protocol of a port exposed by a [`Flight`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, EnumString, Display, Default)]
+#[strum(ascii_case_insensitive, serialize_all = "lowercase")]
+pub enum Protocol {
+    /// HTTP traffic
+    #[default]
+    Http,
+
+    /// HTTPS traffic
+    Https,
+
+    /// Raw TCP traffic
+    Tcp,
+
+    /// Raw UDP traffic
+    Udp,
+}
+
+impl Protocol {
+    /// Whether HTTP health checks can be made against ports of this protocol
+    pub fn is_http(&self) -> bool { matches!(self, Protocol::Http | Protocol::Https) }
+}
+
+impl_serde_str!(Protocol);
+
+/// A port exposed by the container of a [`Flight`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Port {
+    /// The port number the container listens on
+    pub port: u16,
+
+    /// The protocol of the traffic the port receives
+    #[serde(default)]
+    pub protocol: Protocol,
+}
+
+impl Port {
+    /// Create a new port which receives traffic of `protocol`
+    pub fn new(port: u16, protocol: Protocol) -> Self { Self { port, protocol } }
+}
+
+/// The CPU and memory requested for each container instance of a [`Flight`]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Resources {
+    /// The requested CPU in millicores, where 1000 is one full CPU
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_millis: Option<u32>,
+
+    /// The requested memory in MiB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_mib: Option<u32>,
+}
+
+/// An HTTP health check the runtime makes against a [`Flight`] to determine its
+/// [`FlightStatus`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthCheck {
+    /// The path requested, which must start with a slash (`/`)
+    pub path: String,
+
+    /// The exposed port the request is made to, or the first exposed HTTP(S) port if `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+}
+
+impl HealthCheck {
+    /// Create a new health check requesting `path` on the first exposed HTTP(S) port
+    pub fn new(path: impl Into<String>) -> Self { Self { path: path.into(), port: None } }
+
+    /// Make the health check request to `port` instead
+    #[must_use]
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
 }
 
 /// A builder to construct [`Flight`]s
@@ -279,6 +523,15 @@ mod formation_tests {
 pub struct FlightBuilder {
     name: Option<String>,
     image: Option<ImageReference>,
+    env: BTreeMap<String, String>,
+    secrets: BTreeMap<String, String>,
+    ports: Vec<Port>,
+    resources: Option<Resources>,
+    min_replicas: Option<u32>,
+    max_replicas: Option<u32>,
+    command: Vec<String>,
+    args: Vec<String>,
+    health_check: Option<HealthCheck>,
 }
 
 impl FlightBuilder {
@@ -321,6 +574,100 @@ impl FlightBuilder {
         self
     }
 
+    /// Set the environment variable `name` to `value` in the container. See
+    /// [`validate_env_var_name`] for the rules names must follow.
+    ///
+    /// **NOTE:** This method can be called multiple times. Setting the same variable again
+    /// replaces its value.
+    #[must_use]
+    pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set the environment variable `name` in the container to the value of the secret named
+    /// `secret`, so that the value itself never appears in the Formation configuration.
+    ///
+    /// **NOTE:** This method can be called multiple times. Setting the same variable again
+    /// replaces its secret.
+    #[must_use]
+    pub fn secret(mut self, name: impl Into<String>, secret: impl Into<String>) -> Self {
+        self.secrets.insert(name.into(), secret.into());
+        self
+    }
+
+    /// Expose `port` of the container, which receives traffic of `protocol`
+    ///
+    /// **NOTE:** This method can be called multiple times. All values will be utilized.
+    #[must_use]
+    pub fn port(mut self, port: u16, protocol: Protocol) -> Self {
+        self.ports.push(Port::new(port, protocol));
+        self
+    }
+
+    /// The CPU to request for each container instance, in millicores where 1000 is one full CPU
+    #[must_use]
+    pub fn cpu_millis(mut self, millicores: u32) -> Self {
+        self.resources
+            .get_or_insert_with(Default::default)
+            .cpu_millis = Some(millicores);
+        self
+    }
+
+    /// The memory to request for each container instance, in MiB
+    #[must_use]
+    pub fn memory_mib(mut self, mib: u32) -> Self {
+        self.resources
+            .get_or_insert_with(Default::default)
+            .memory_mib = Some(mib);
+        self
+    }
+
+    /// The minimum number of container instances to keep running (default: decided by the
+    /// runtime)
+    #[must_use]
+    pub fn min_replicas(mut self, min: u32) -> Self {
+        self.min_replicas = Some(min);
+        self
+    }
+
+    /// The maximum number of container instances to run (default: decided by the runtime)
+    #[must_use]
+    pub fn max_replicas(mut self, max: u32) -> Self {
+        self.max_replicas = Some(max);
+        self
+    }
+
+    /// Override the entrypoint of the container image
+    #[must_use]
+    pub fn command<I, S>(mut self, command: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.command = command.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Override the arguments passed to the entrypoint of the container image
+    #[must_use]
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// The HTTP health check which determines whether the [`Flight`] is healthy. The checked port
+    /// must be exposed with [`Protocol::Http`] or [`Protocol::Https`].
+    #[must_use]
+    pub fn health_check(mut self, check: HealthCheck) -> Self {
+        self.health_check = Some(check);
+        self
+    }
+
     /// Perform validation checks and construct a [`Flight`]
     pub fn build(self) -> Result<Flight> {
         use FormationValidation::*;
@@ -329,21 +676,80 @@ impl FlightBuilder {
         } else if self.image.is_none() {
             return Err(ComputeError::FormationValidation(MissingFlightImageReference).into());
         }
+        self.validate().map_err(ComputeError::FormationValidation)?;
 
         Ok(Flight {
             name: self.name.unwrap(),
             oid: None,
             image: self.image.unwrap(),
             status: FlightStatus::default(),
+            env: self.env,
+            secrets: self.secrets,
+            ports: self.ports,
+            resources: self.resources,
+            min_replicas: self.min_replicas,
+            max_replicas: self.max_replicas,
+            command: self.command,
+            args: self.args,
+            health_check: self.health_check,
         })
     }
+
+    // Checks the container settings, which the Compute API would otherwise reject
+    fn validate(&self) -> StdResult<(), FormationValidation> {
+        use FormationValidation::*;
+        for name in self.env.keys().chain(self.secrets.keys()) {
+            validate_env_var_name(name)?;
+        }
+        if self.secrets.keys().any(|name| self.env.contains_key(name)) {
+            return Err(DuplicateEnvVar);
+        }
+        if self.secrets.values().any(String::is_empty) {
+            return Err(EmptySecretName);
+        }
+
+        if self.ports.iter().any(|p| p.port == 0) {
+            return Err(InvalidPort);
+        }
+        if self.ports.iter().collect::<HashSet<_>>().len() != self.ports.len() {
+            return Err(DuplicatePort);
+        }
+
+        if let Some(r) = self.resources {
+            if r.cpu_millis == Some(0) || r.memory_mib == Some(0) {
+                return Err(InvalidResources);
+            }
+        }
+
+        match (self.min_replicas, self.max_replicas) {
+            (_, Some(0)) => return Err(InvalidReplicas),
+            (Some(min), Some(max)) if min > max => return Err(InvalidReplicas),
+            _ => (),
+        }
+
+        if let Some(check) = &self.health_check {
+            if !check.path.starts_with('/') {
+                return Err(InvalidHealthCheckPath);
+            }
+            // Without an explicit port the first HTTP(S) port is checked
+            if !self
+                .ports
+                .iter()
+                .any(|p| p.protocol.is_http() && check.port.map_or(true, |port| port == p.port))
+            {
+                return Err(InvalidHealthCheckPort);
+            }
+        }
+
+        Ok(())
+    }
 }
 
 /// Describes a single [`Flight`] within a Formation.
 ///
 /// Flights are logically a single container. However, Seaplane spins up many actual backing
-/// *container instances* around the globe (with your Formation's `regions_allowed` map) and load
-/// balances traffic between them.
+/// *container instances* around the globe (within the [`Region`]s and [`Provider`]s allowed by
+/// the placement requirements of your [`Formation`]) and load balances traffic between them.
 #[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
 #[non_exhaustive]
 #[serde(rename_all = "kebab-case")]
@@ -361,6 +767,43 @@ pub struct Flight {
     /// The status of this Flight
     #[serde(default, skip_serializing_if = "FlightStatus::is_starting")]
     pub status: FlightStatus,
+
+    /// The environment variables set in the container, by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+
+    /// The environment variables set in the container from secrets, as the names of the
+    /// variables mapped to the names of the secrets
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub secrets: BTreeMap<String, String>,
+
+    /// The ports exposed by the container
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ports: Vec<Port>,
+
+    /// The CPU and memory requested for each container instance
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<Resources>,
+
+    /// The minimum number of container instances
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_replicas: Option<u32>,
+
+    /// The maximum number of container instances
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_replicas: Option<u32>,
+
+    /// Overrides the entrypoint of the container image, if not empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub command: Vec<String>,
+
+    /// Overrides the arguments passed to the entrypoint of the container image, if not empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+
+    /// The HTTP health check which determines the status of this Flight
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check: Option<HealthCheck>,
 }
 
 impl Flight {
@@ -400,9 +843,48 @@ impl Flight {
     pub fn image(&self) -> &ImageReference { &self.image }
 }
 
+/// A single running container of a [`Flight`], one of the many the Flight is spread across
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+#[serde(rename_all = "kebab-case")]
+pub struct ContainerInstance {
+    /// The ID of the container instance
+    pub id: String,
+
+    /// The status of this container instance
+    #[serde(default)]
+    pub status: FlightStatus,
+
+    /// The [`Region`] the container instance runs in, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<Region>,
+
+    /// The [`Provider`] the container instance runs on, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<Provider>,
+
+    /// When the container instance was started, if it has been
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+/// A [`Flight`] along with its running container instances
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct FlightDetails {
+    /// The Flight itself
+    #[serde(flatten)]
+    pub flight: Flight,
+
+    /// The container instances currently running the Flight
+    #[serde(default)]
+    pub instances: Vec<ContainerInstance>,
+}
+
 #[cfg(test)]
 mod flight_tests {
     use super::*;
+    use crate::error::SeaplaneError;
 
     #[test]
     fn deser() {
@@ -412,10 +894,9 @@ mod flight_tests {
             "image":"foo.com/bar:latest"
         }"#;
         let model = Flight {
-            name: "example-flight".into(),
             oid: Some("flt-6f9asfo8ql0ar3mihb0ruv14i0".parse().unwrap()),
-            image: "foo.com/bar:latest".parse::<ImageReference>().unwrap(),
             status: FlightStatus::Starting,
+            ..Flight::new("example-flight", "foo.com/bar:latest")
         };
 
         assert_eq!(model, serde_json::from_str(json).unwrap());
@@ -425,10 +906,9 @@ mod flight_tests {
     fn ser() {
         let json = r#"{"name":"example-flight","oid":"flt-6f9asfo8ql0ar3mihb0ruv14i0","image":"foo.com/bar:latest","status":"healthy"}"#;
         let model = Flight {
-            name: "example-flight".into(),
             oid: Some("flt-6f9asfo8ql0ar3mihb0ruv14i0".parse().unwrap()),
-            image: "foo.com/bar:latest".parse::<ImageReference>().unwrap(),
             status: FlightStatus::Healthy,
+            ..Flight::new("example-flight", "foo.com/bar:latest")
         };
 
         assert_eq!(json, serde_json::to_string(&model).unwrap());
@@ -438,12 +918,125 @@ mod flight_tests {
     fn ser_no_oid() {
         let json = r#"{"name":"example-flight","image":"foo.com/bar:latest","status":"healthy"}"#;
         let model = Flight {
-            name: "example-flight".into(),
             oid: None,
-            image: "foo.com/bar:latest".parse::<ImageReference>().unwrap(),
             status: FlightStatus::Healthy,
+            ..Flight::new("example-flight", "foo.com/bar:latest")
         };
 
         assert_eq!(json, serde_json::to_string(&model).unwrap());
     }
+
+    #[test]
+    fn ser_container_settings() {
+        let json = r#"{"name":"web","image":"foo.com/web:latest","env":{"LOG_LEVEL":"info"},"secrets":{"DB_PASSWORD":"db-password"},"ports":[{"port":8080,"protocol":"http"},{"port":9000,"protocol":"tcp"}],"resources":{"cpu-millis":500,"memory-mib":256},"min-replicas":2,"max-replicas":10,"command":["/bin/web"],"args":["--verbose"],"health-check":{"path":"/healthz","port":8080}}"#;
+        let model = Flight::builder()
+            .name("web")
+            .image("foo.com/web:latest")
+            .env("LOG_LEVEL", "info")
+            .secret("DB_PASSWORD", "db-password")
+            .port(8080, Protocol::Http)
+            .port(9000, Protocol::Tcp)
+            .cpu_millis(500)
+            .memory_mib(256)
+            .min_replicas(2)
+            .max_replicas(10)
+            .command(["/bin/web"])
+            .args(["--verbose"])
+            .health_check(HealthCheck::new("/healthz").port(8080))
+            .build()
+            .unwrap();
+
+        assert_eq!(json, serde_json::to_string(&model).unwrap());
+        assert_eq!(model, serde_json::from_str(json).unwrap());
+    }
+
+    #[test]
+    fn validate_container_settings() {
+        use FormationValidation::*;
+
+        let check = |builder: FlightBuilder| match builder.name("web").image("foo.com/web").build()
+        {
+            Err(SeaplaneError::ComputeRequest(ComputeError::FormationValidation(e))) => Err(e),
+            Err(e) => panic!("unexpected error {e}"),
+            Ok(_) => Ok(()),
+        };
+
+        assert_eq!(check(Flight::builder().env("1ST", "a")), Err(InvalidEnvVarName));
+        assert_eq!(check(Flight::builder().secret("A-B", "a")), Err(InvalidEnvVarName));
+        assert_eq!(check(Flight::builder().env("", "a")), Err(InvalidEnvVarName));
+        assert_eq!(check(Flight::builder().env("A", "").secret("A", "a")), Err(DuplicateEnvVar));
+        assert_eq!(check(Flight::builder().secret("A", "")), Err(EmptySecretName));
+        assert_eq!(check(Flight::builder().port(0, Protocol::Tcp)), Err(InvalidPort));
+        assert_eq!(
+            check(
+                Flight::builder()
+                    .port(53, Protocol::Udp)
+                    .port(53, Protocol::Udp)
+            ),
+            Err(DuplicatePort)
+        );
+        assert_eq!(
+            check(
+                Flight::builder()
+                    .port(53, Protocol::Udp)
+                    .port(53, Protocol::Tcp)
+            ),
+            Ok(())
+        );
+        assert_eq!(check(Flight::builder().cpu_millis(0)), Err(InvalidResources));
+        assert_eq!(check(Flight::builder().memory_mib(0)), Err(InvalidResources));
+        assert_eq!(check(Flight::builder().max_replicas(0)), Err(InvalidReplicas));
+        assert_eq!(check(Flight::builder().min_replicas(3).max_replicas(2)), Err(InvalidReplicas));
+        assert_eq!(check(Flight::builder().min_replicas(0).max_replicas(2)), Ok(()));
+
+        let http = || {
+            Flight::builder()
+                .port(80, Protocol::Http)
+                .port(9000, Protocol::Tcp)
+        };
+        assert_eq!(
+            check(http().health_check(HealthCheck::new("healthz"))),
+            Err(InvalidHealthCheckPath)
+        );
+        assert_eq!(check(http().health_check(HealthCheck::new("/healthz"))), Ok(()));
+        assert_eq!(
+            check(http().health_check(HealthCheck::new("/healthz").port(9000))),
+            Err(InvalidHealthCheckPort)
+        );
+        assert_eq!(
+            check(Flight::builder().health_check(HealthCheck::new("/healthz"))),
+            Err(InvalidHealthCheckPort)
+        );
+    }
+
+    #[test]
+    fn deser_details() {
+        let json = r#"{
+            "name":"example-flight",
+            "oid":"flt-6f9asfo8ql0ar3mihb0ruv14i0",
+            "image":"foo.com/bar:latest",
+            "status":"healthy",
+            "instances":[{
+                "id":"inst-1",
+                "status":"healthy",
+                "region":"XE",
+                "provider":"GCP",
+                "started-at":"2023-03-01T12:00:00Z"
+            }]
+        }"#;
+        let details: FlightDetails = serde_json::from_str(json).unwrap();
+
+        assert_eq!(details.flight.name, "example-flight");
+        assert_eq!(details.flight.status, FlightStatus::Healthy);
+        assert_eq!(
+            details.instances,
+            [ContainerInstance {
+                id: "inst-1".into(),
+                status: FlightStatus::Healthy,
+                region: Some(Region::XE),
+                provider: Some(Provider::GCP),
+                started_at: Some("2023-03-01T12:00:00Z".parse().unwrap()),
+            }]
+        );
+    }
 }
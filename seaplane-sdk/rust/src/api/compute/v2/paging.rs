@@ -0,0 +1,176 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    iter::FusedIterator,
+};
+
+use reqwest::Url;
+
+use crate::{
+    api::{
+        compute::{
+            error::ComputeError,
+            v2::{Formation, FormationsRequest, GetFormationsResponse},
+        },
+        ApiCall,
+    },
+    error::Result,
+};
+
+/// An iterator over all Formations, which lazily fetches one page at a time by following the
+/// `next` link of each page.
+///
+/// Created with [`FormationsRequest::iter_all`]. Every item is a `Result` because fetching any
+/// page may fail, after which the iterator ends. The iterator also ends with an error if a `next`
+/// link points to a different origin than the endpoint, so that the token is never sent elsewhere,
+/// or back to a page which was already fetched, so that it is never followed forever.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use seaplane::api::compute::v2::FormationsRequest;
+/// let req = FormationsRequest::builder()
+///     .token("abc123_token")
+///     .build()
+///     .unwrap();
+///
+/// let mut formations = req.iter_all().page_size(50);
+/// for formation in &mut formations {
+///     println!("{}", formation.unwrap().name);
+/// }
+/// println!("{:?} formations in total", formations.total());
+/// ```
+#[derive(Debug)]
+pub struct FormationsIter<'a> {
+    request: &'a FormationsRequest,
+    page_size: Option<usize>,
+    next: NextPage,
+    // The URLs of the pages fetched so far
+    visited: HashSet<Url>,
+    // The Formations of the fetched page which have not been yielded yet
+    buffered: VecDeque<Formation>,
+    total: Option<usize>,
+}
+
+// Where the next page is fetched from
+#[derive(Debug)]
+enum NextPage {
+    First,
+    // The `next` link of the previous page
+    Link(Url),
+    Done,
+}
+
+impl<'a> FormationsIter<'a> {
+    pub(crate) fn new(request: &'a FormationsRequest) -> Self {
+        Self {
+            request,
+            page_size: None,
+            next: NextPage::First,
+            visited: HashSet::new(),
+            buffered: VecDeque::new(),
+            total: None,
+        }
+    }
+
+    /// Ask for pages of (at most) `size` Formations. The server may cap the size of pages, so this
+    /// is only a hint (default: the server's page size)
+    #[must_use]
+    pub fn page_size(mut self, size: usize) -> Self {
+        self.page_size = Some(size);
+        self
+    }
+
+    /// The total number of Formations in all pages, as reported with the most recently fetched
+    /// page, or `None` if no page has been fetched yet
+    pub fn total(&self) -> Option<usize> { self.total }
+
+    /// The async variant of [`Iterator::next`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn next_async(&mut self) -> Option<Result<Formation>> {
+        loop {
+            if let Some(formation) = self.buffered.pop_front() {
+                return Some(Ok(formation));
+            }
+            let call = self.next_call()?;
+            let page = match self.request.request.send_async(call).await {
+                Ok(resp) => resp.json(),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = self.receive(page) {
+                return Some(Err(e));
+            }
+        }
+    }
+
+    // The call fetching the next page, or `None` if all pages have been fetched
+    fn next_call(&mut self) -> Option<ApiCall> {
+        let url = match &self.next {
+            NextPage::First => self.page_url(self.request.request.endpoint_url.clone()),
+            NextPage::Link(link) => self.page_url(link.clone()),
+            NextPage::Done => return None,
+        };
+        self.visited.insert(url.clone());
+        Some(ApiCall::get(url).operation("get_all"))
+    }
+
+    // The URL of the page at `url`, with the limit of the page size
+    fn page_url(&self, mut url: Url) -> Url {
+        // The link may or may not keep the limit of the previous page, so it is set on every page
+        if let Some(size) = self.page_size {
+            let query: Vec<_> = url
+                .query_pairs()
+                .filter(|(name, _)| name != "limit")
+                .map(|(name, value)| (name.into_owned(), value.into_owned()))
+                .collect();
+            url.query_pairs_mut()
+                .clear()
+                .extend_pairs(query)
+                .append_pair("limit", &size.to_string());
+        }
+        url
+    }
+
+    // Buffers the Formations of a fetched page and remembers where the next one is
+    fn receive(&mut self, page: Result<GetFormationsResponse>) -> Result<()> {
+        self.next = NextPage::Done;
+        let page = page?;
+        self.total = Some(page.meta.total);
+        match page.meta.next {
+            // An empty page would otherwise be followed forever
+            Some(next) if !page.objects.is_empty() => {
+                let endpoint = &self.request.request.endpoint_url;
+                let link = endpoint.join(next.as_str())?;
+                if link.origin() != endpoint.origin() {
+                    return Err(ComputeError::ForeignPageLink(link.to_string()).into());
+                }
+                if self.visited.contains(&self.page_url(link.clone())) {
+                    return Err(ComputeError::PageLinkCycle(link.to_string()).into());
+                }
+                self.next = NextPage::Link(link);
+            }
+            _ => (),
+        }
+        self.buffered.extend(page.objects);
+        Ok(())
+    }
+}
+
+impl Iterator for FormationsIter<'_> {
+    type Item = Result<Formation>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(formation) = self.buffered.pop_front() {
+                return Some(Ok(formation));
+            }
+            let call = self.next_call()?;
+            let page = self.request.request.send(call).and_then(|resp| resp.json());
+            if let Err(e) = self.receive(page) {
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+impl FusedIterator for FormationsIter<'_> {}
@@ -0,0 +1,412 @@
+use std::{collections::HashSet, fmt};
+
+use crate::{
+    api::compute::{
+        error::{ComputeError, FormationValidation},
+        v2::{Flight, FlightStatus, Formation},
+    },
+    error::Result,
+    rexports::container_image_ref::ImageReference,
+};
+
+/// The changes which turn the deployed Formations into the desired ones, as computed by
+/// [`FormationsRequest::plan`](crate::api::compute::v2::FormationsRequest::plan).
+///
+/// Formations are matched by name. The `Display` implementation describes the changes one per
+/// line, such as to review them before they are applied.
+///
+/// # Examples
+///
+/// ```
+/// # use seaplane::api::compute::v2::{Flight, Formation, Plan};
+/// let deployed = Formation::builder()
+///     .name("example-formation")
+///     .add_flight(Flight::new("web", "foo.com/web:v1"))
+///     .build()
+///     .unwrap();
+/// let mut desired = deployed.clone();
+/// desired.flights[0] = Flight::new("web", "foo.com/web:v2");
+///
+/// let plan = Plan::new(vec![deployed], vec![desired]).unwrap();
+/// assert_eq!(
+///     plan.to_string(),
+///     "~ update Formation example-formation\n    \
+///      ~ change image of Flight web: foo.com/web:v1 -> foo.com/web:v2\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Plan {
+    /// The changes in the order they are applied: creations and updates in the order of the
+    /// desired Formations, followed by deletions
+    pub changes: Vec<Change>,
+}
+
+/// A change to a single Formation
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Change {
+    /// The desired Formation is not deployed and will be created
+    Create(Formation),
+
+    /// The deployed Formation differs from the desired one and will be replaced by it
+    Update {
+        /// The Formation as deployed
+        deployed: Formation,
+        /// The Formation as desired
+        desired: Formation,
+        /// What differs between the two
+        diff: FormationDiff,
+        /// The `ETag` of the deployed Formation, if known. When set the Formation is only replaced
+        /// if it has not changed since it was planned.
+        etag: Option<String>,
+    },
+
+    /// The deployed Formation is not desired and will be deleted
+    Delete(Formation),
+}
+
+/// What differs between a deployed Formation and the desired one with the same name.
+///
+/// Only the configuration is compared, not what is assigned by the Compute API such as Object IDs
+/// or the statuses of Flights.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FormationDiff {
+    /// The names of the Flights which are only desired
+    pub flights_added: Vec<String>,
+    /// The names of the Flights which are only deployed
+    pub flights_removed: Vec<String>,
+    /// The Flights whose container image differs
+    pub images_changed: Vec<ImageChange>,
+    /// The names of the Flights whose other settings (such as environment variables or ports)
+    /// differ
+    pub flights_reconfigured: Vec<String>,
+    /// The change of the gateway Flight, if it differs
+    pub gateway_flight: Option<GatewayChange>,
+    /// Whether the allowed or denied Regions or Providers differ
+    pub placement_changed: bool,
+}
+
+/// A change of the container image of a Flight
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageChange {
+    /// The name of the Flight
+    pub flight: String,
+    /// The image as deployed
+    pub from: ImageReference,
+    /// The image as desired
+    pub to: ImageReference,
+}
+
+/// A change of the gateway Flight of a Formation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GatewayChange {
+    /// The gateway Flight as deployed
+    pub from: Option<String>,
+    /// The gateway Flight as desired
+    pub to: Option<String>,
+}
+
+impl Plan {
+    /// Computes the changes which turn the `deployed` Formations into the `desired` ones.
+    ///
+    /// **NOTE:** Every deployed Formation which is not desired is deleted, so `desired` should
+    /// contain all Formations which are to be kept.
+    ///
+    /// Fails if two desired Formations have the same name.
+    pub fn new(
+        deployed: impl IntoIterator<Item = Formation>,
+        desired: impl IntoIterator<Item = Formation>,
+    ) -> Result<Self> {
+        let mut deployed: Vec<_> = deployed.into_iter().map(Some).collect();
+        let mut names = HashSet::new();
+        let mut changes = Vec::new();
+        for desired in desired {
+            if !names.insert(desired.name.clone()) {
+                return Err(ComputeError::FormationValidation(
+                    FormationValidation::DuplicateFormationName,
+                )
+                .into());
+            }
+            let current = deployed
+                .iter_mut()
+                .find(|f| f.as_ref().map_or(false, |f| f.name == desired.name))
+                .and_then(Option::take);
+            match current {
+                None => changes.push(Change::Create(desired)),
+                Some(deployed) => {
+                    let diff = FormationDiff::new(&deployed, &desired);
+                    if !diff.is_empty() {
+                        changes.push(Change::Update { deployed, desired, diff, etag: None });
+                    }
+                }
+            }
+        }
+        changes.extend(deployed.into_iter().flatten().map(Change::Delete));
+
+        Ok(Self { changes })
+    }
+
+    /// Whether the deployed Formations already are as desired
+    pub fn is_empty(&self) -> bool { self.changes.is_empty() }
+
+    // Drops the updates whose deployed Formation already is as desired, such as after it was
+    // fetched again
+    pub(crate) fn remove_noop_updates(&mut self) {
+        self.changes
+            .retain(|change| !matches!(change, Change::Update { diff, .. } if diff.is_empty()));
+    }
+}
+
+impl Change {
+    // Replaces the deployed Formation of an update with `current` as fetched along with its `ETag`
+    pub(crate) fn set_deployed(&mut self, current: Formation, current_etag: Option<String>) {
+        if let Change::Update { deployed, desired, diff, etag } = self {
+            *diff = FormationDiff::new(&current, desired);
+            *deployed = current;
+            *etag = current_etag;
+        }
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.changes.is_empty() {
+            return writeln!(f, "no changes");
+        }
+        for change in &self.changes {
+            match change {
+                Change::Create(formation) => {
+                    let flights: Vec<_> = formation.flights.iter().map(|f| f.name()).collect();
+                    writeln!(
+                        f,
+                        "+ create Formation {} (Flights: {})",
+                        formation.name,
+                        flights.join(", ")
+                    )?;
+                }
+                Change::Update { desired, diff, .. } => {
+                    writeln!(f, "~ update Formation {}", desired.name)?;
+                    write!(f, "{diff}")?;
+                }
+                Change::Delete(formation) => writeln!(f, "- delete Formation {}", formation.name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FormationDiff {
+    /// Compares the `deployed` and `desired` configurations of a Formation
+    pub fn new(deployed: &Formation, desired: &Formation) -> Self {
+        let mut diff = Self::default();
+        for flight in &desired.flights {
+            match deployed.flights.iter().find(|f| f.name == flight.name) {
+                None => diff.flights_added.push(flight.name.clone()),
+                Some(current) => {
+                    if current.image != flight.image {
+                        diff.images_changed.push(ImageChange {
+                            flight: flight.name.clone(),
+                            from: current.image.clone(),
+                            to: flight.image.clone(),
+                        });
+                    }
+                    if !same_settings(current, flight) {
+                        diff.flights_reconfigured.push(flight.name.clone());
+                    }
+                }
+            }
+        }
+        diff.flights_removed = deployed
+            .flights
+            .iter()
+            .filter(|f| !desired.flights.iter().any(|d| d.name == f.name))
+            .map(|f| f.name.clone())
+            .collect();
+
+        if deployed.gateway_flight != desired.gateway_flight {
+            diff.gateway_flight = Some(GatewayChange {
+                from: deployed.gateway_flight.clone(),
+                to: desired.gateway_flight.clone(),
+            });
+        }
+        diff.placement_changed = deployed.providers_allowed != desired.providers_allowed
+            || deployed.providers_denied != desired.providers_denied
+            || deployed.regions_allowed != desired.regions_allowed
+            || deployed.regions_denied != desired.regions_denied;
+
+        diff
+    }
+
+    /// Whether the configurations are the same
+    pub fn is_empty(&self) -> bool { self == &Self::default() }
+}
+
+impl fmt::Display for FormationDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for flight in &self.flights_added {
+            writeln!(f, "    + add Flight {flight}")?;
+        }
+        for flight in &self.flights_removed {
+            writeln!(f, "    - remove Flight {flight}")?;
+        }
+        for change in &self.images_changed {
+            writeln!(
+                f,
+                "    ~ change image of Flight {}: {} -> {}",
+                change.flight, change.from, change.to
+            )?;
+        }
+        for flight in &self.flights_reconfigured {
+            writeln!(f, "    ~ reconfigure Flight {flight}")?;
+        }
+        if let Some(change) = &self.gateway_flight {
+            let name = |flight: &Option<String>| flight.clone().unwrap_or_else(|| "none".into());
+            writeln!(
+                f,
+                "    ~ change gateway Flight: {} -> {}",
+                name(&change.from),
+                name(&change.to)
+            )?;
+        }
+        if self.placement_changed {
+            writeln!(f, "    ~ change placement requirements")?;
+        }
+        Ok(())
+    }
+}
+
+// Whether the Flights are the same other than in their images and what the Compute API assigns
+fn same_settings(deployed: &Flight, desired: &Flight) -> bool {
+    let unassigned = |flight: &Flight| {
+        let mut flight = flight.clone();
+        flight.oid = None;
+        flight.status = FlightStatus::default();
+        flight.image = desired.image.clone();
+        flight
+    };
+    unassigned(deployed) == unassigned(desired)
+}
+
+#[cfg(test)]
+mod plan_tests {
+    use super::*;
+    use crate::{
+        api::{compute::v2::Protocol, shared::v1::Region},
+        error::SeaplaneError,
+    };
+
+    fn formation(name: &str, flights: &[(&str, &str)]) -> Formation {
+        let mut builder = Formation::builder().name(name);
+        for (flight, image) in flights {
+            builder = builder.add_flight(Flight::new(*flight, image));
+        }
+        builder.build().unwrap()
+    }
+
+    // A Formation as returned by the Compute API, with the Object IDs and statuses it assigns
+    fn deployed(name: &str, flights: &[(&str, &str)]) -> Formation {
+        let mut formation = formation(name, flights);
+        formation.oid = Some("frm-sjt5inobm97i317b95uerqv080".parse().unwrap());
+        for flight in &mut formation.flights {
+            flight.oid = Some("flt-6f9asfo8ql0ar3mihb0ruv14i0".parse().unwrap());
+            flight.status = FlightStatus::Healthy;
+        }
+        formation
+    }
+
+    #[test]
+    fn create_update_delete() {
+        let mut desired_web = formation(
+            "web",
+            &[("api", "foo.com/api:v2"), ("db", "foo.com/db:v1"), ("cache", "foo.com/cache:v1")],
+        );
+        desired_web.gateway_flight = Some("api".into());
+        desired_web.regions_allowed.insert(Region::XE);
+        desired_web.flights[1] = Flight::builder()
+            .name("db")
+            .image("foo.com/db:v1")
+            .port(5432, Protocol::Tcp)
+            .build()
+            .unwrap();
+        let desired = vec![
+            formation("new", &[("cron", "foo.com/cron:latest"), ("worker", "foo.com/worker:v1")]),
+            desired_web.clone(),
+            formation("same", &[("web", "foo.com/web:v1")]),
+        ];
+        let deployed = vec![
+            deployed("old", &[("web", "foo.com/web:v1")]),
+            deployed(
+                "web",
+                &[
+                    ("api", "foo.com/api:v1"),
+                    ("db", "foo.com/db:v1"),
+                    ("queue", "foo.com/queue:v1"),
+                ],
+            ),
+            deployed("same", &[("web", "foo.com/web:v1")]),
+        ];
+
+        let plan = Plan::new(deployed.clone(), desired.clone()).unwrap();
+        assert_eq!(
+            plan.changes,
+            [
+                Change::Create(desired[0].clone()),
+                Change::Update {
+                    deployed: deployed[1].clone(),
+                    desired: desired_web,
+                    diff: FormationDiff {
+                        flights_added: vec!["cache".into()],
+                        flights_removed: vec!["queue".into()],
+                        images_changed: vec![ImageChange {
+                            flight: "api".into(),
+                            from: "foo.com/api:v1".parse().unwrap(),
+                            to: "foo.com/api:v2".parse().unwrap(),
+                        }],
+                        flights_reconfigured: vec!["db".into()],
+                        gateway_flight: Some(GatewayChange { from: None, to: Some("api".into()) }),
+                        placement_changed: true,
+                    },
+                    etag: None,
+                },
+                Change::Delete(deployed[0].clone()),
+            ]
+        );
+        assert_eq!(
+            plan.to_string(),
+            "+ create Formation new (Flights: cron, worker)
+~ update Formation web
+    + add Flight cache
+    - remove Flight queue
+    ~ change image of Flight api: foo.com/api:v1 -> foo.com/api:v2
+    ~ reconfigure Flight db
+    ~ change gateway Flight: none -> api
+    ~ change placement requirements
+- delete Formation old
+"
+        );
+    }
+
+    #[test]
+    fn no_changes() {
+        let plan = Plan::new(
+            vec![deployed("web", &[("web", "foo.com/web:v1")])],
+            vec![formation("web", &[("web", "foo.com/web:v1")])],
+        )
+        .unwrap();
+        assert!(plan.is_empty());
+        assert_eq!(plan.to_string(), "no changes\n");
+    }
+
+    #[test]
+    fn duplicate_names() {
+        let web = formation("web", &[("web", "foo.com/web:v1")]);
+        assert_eq!(
+            Plan::new(vec![], vec![web.clone(), web]),
+            Err(SeaplaneError::ComputeRequest(ComputeError::FormationValidation(
+                FormationValidation::DuplicateFormationName
+            )))
+        );
+    }
+}
@@ -27,4 +27,8 @@ pub struct PageMetadata {
 pub type GetFormationsResponse = PagedResponse<Formation>;
 pub type CreateFormationResponse = Formation;
 pub type GetFormationResponse = Formation;
+pub type UpdateFormationResponse = Formation;
 pub type DeleteFormationResponse = ();
+pub type GetFlightsResponse = Vec<Flight>;
+pub type GetFlightResponse = FlightDetails;
+pub type RestartFlightResponse = ();
@@ -2,7 +2,7 @@ use std::result::Result as StdResult;
 
 use crate::api::compute::error::FormationValidation;
 #[cfg(doc)]
-use crate::api::compute::v2::Formation;
+use crate::api::compute::v2::{Flight, Formation};
 
 /// Determine if a [`Formation`] name (slug) is valid or not.
 ///
@@ -45,3 +45,22 @@ pub fn validate_formation_name(name: impl AsRef<str>) -> StdResult<(), Formation
 
     Ok(())
 }
+
+/// Determine if the name of an environment variable set in a [`Flight`] is valid or not.
+///
+/// The name must follow the POSIX rules for portable environment variable names:
+///
+///  - 1 or more ASCII letters, digits, or underscores (A-Z, a-z, 0-9, and '_')
+///  - may not start with a digit
+pub fn validate_env_var_name(name: impl AsRef<str>) -> StdResult<(), FormationValidation> {
+    let name = name.as_ref().as_bytes();
+    match name.first() {
+        Some(c) if !c.is_ascii_digit() => (),
+        _ => return Err(FormationValidation::InvalidEnvVarName),
+    }
+    if !name.iter().all(|&c| c.is_ascii_alphanumeric() || c == b'_') {
+        return Err(FormationValidation::InvalidEnvVarName);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use crate::api::compute::{
+    error::ComputeError,
+    v2::{Flight, FlightStatus, Formation},
+};
+
+/// Tracks the health of the Flights of a Formation across polls
+#[derive(Debug, Default)]
+pub(crate) struct HealthWatch {
+    // The last status seen of each Flight, by name
+    seen: HashMap<String, FlightStatus>,
+}
+
+/// The health of a Formation as a whole
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Health {
+    /// All Flights are healthy
+    Healthy,
+    /// Some Flights are still starting
+    Starting,
+    /// No Flights are starting, but the contained Flights are unhealthy
+    Unhealthy(Vec<String>),
+}
+
+impl HealthWatch {
+    /// Checks the health of the Flights of a freshly fetched Formation, calling `on_progress` with
+    /// every Flight seen for the first time or whose status changed since the last poll
+    pub(crate) fn poll<F: FnMut(&Flight)>(
+        &mut self,
+        formation: &Formation,
+        on_progress: &mut F,
+    ) -> Health {
+        for flight in &formation.flights {
+            if self.seen.insert(flight.name.clone(), flight.status) != Some(flight.status) {
+                on_progress(flight);
+            }
+        }
+
+        if formation
+            .flights
+            .iter()
+            .any(|f| f.status == FlightStatus::Starting)
+        {
+            return Health::Starting;
+        }
+        match names_where(formation, |s| s == FlightStatus::Unhealthy) {
+            unhealthy if unhealthy.is_empty() => Health::Healthy,
+            unhealthy => Health::Unhealthy(unhealthy),
+        }
+    }
+}
+
+/// The error when the Flights of `formation` did not all become healthy in time
+pub(crate) fn timeout_error(formation: &Formation) -> ComputeError {
+    ComputeError::HealthTimeout(names_where(formation, |s| s != FlightStatus::Healthy))
+}
+
+// The names of the Flights whose status matches
+fn names_where(formation: &Formation, matches: impl Fn(FlightStatus) -> bool) -> Vec<String> {
+    formation
+        .flights
+        .iter()
+        .filter(|f| matches(f.status))
+        .map(|f| f.name.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod wait_tests {
+    use super::*;
+
+    fn formation(statuses: &[(&str, FlightStatus)]) -> Formation {
+        let mut builder = Formation::builder().name("example");
+        for (name, status) in statuses {
+            let mut flight = Flight::new(*name, "foo.com/web:latest");
+            flight.status = *status;
+            builder = builder.add_flight(flight);
+        }
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn reports_changes_only() {
+        use FlightStatus::*;
+
+        let mut watch = HealthWatch::default();
+        let mut reported = Vec::new();
+        let mut poll = |statuses: &[(&str, FlightStatus)]| {
+            watch.poll(&formation(statuses), &mut |f: &Flight| {
+                reported.push((f.name.clone(), f.status))
+            })
+        };
+
+        assert_eq!(poll(&[("a", Starting), ("b", Starting)]), Health::Starting);
+        assert_eq!(poll(&[("a", Healthy), ("b", Starting)]), Health::Starting);
+        assert_eq!(poll(&[("a", Healthy), ("b", Unhealthy)]), Health::Unhealthy(vec!["b".into()]));
+        assert_eq!(poll(&[("a", Healthy), ("b", Healthy)]), Health::Healthy);
+        assert_eq!(
+            reported,
+            [
+                ("a".into(), Starting),
+                ("b".into(), Starting),
+                ("a".into(), Healthy),
+                ("b".into(), Unhealthy),
+                ("b".into(), Healthy),
+            ]
+        );
+    }
+
+    #[test]
+    fn timeout_names_unhealthy_flights() {
+        use FlightStatus::*;
+
+        let f = formation(&[("a", Healthy), ("b", Starting), ("c", Unhealthy)]);
+        assert_eq!(timeout_error(&f), ComputeError::HealthTimeout(vec!["b".into(), "c".into()]));
+    }
+}
@@ -2,11 +2,14 @@
 
 use std::{error::Error, fmt, result::Result as StdResult};
 
-use reqwest::blocking::Response;
-use serde::{de, Deserialize};
+use reqwest::{blocking::Response, header::HeaderMap, StatusCode};
+use serde::{de, de::DeserializeOwned, Deserialize};
 use serde_json::{Map, Value};
 
-use crate::error::Result;
+use crate::{
+    api::RateLimit,
+    error::{ErrorKind, Result, SeaplaneError},
+};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ApiResponse {
@@ -45,6 +48,29 @@ impl<'de> serde::Deserialize<'de> for ApiResponse {
     }
 }
 
+impl ApiResponse {
+    /// Deserializes the additional, service specific members of the problem (those other than
+    /// `type`, `title`, `detail` and `status`) into `T`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use seaplane::api::ApiResponse;
+    /// #[derive(serde::Deserialize)]
+    /// struct Conflict {
+    ///     holder: String,
+    /// }
+    ///
+    /// let resp: ApiResponse =
+    ///     serde_json::from_str(r#"{"title": "Conflict", "holder": "client-a"}"#).unwrap();
+    /// let conflict: Conflict = resp.meta_as().unwrap();
+    /// assert_eq!(conflict.holder, "client-a");
+    /// ```
+    pub fn meta_as<T: DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_value(Value::Object(self.meta.clone())).map_err(Into::into)
+    }
+}
+
 impl fmt::Display for ApiResponse {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "{}", self.title)?;
@@ -72,14 +98,53 @@ mod api_response_error_tests {
     }
 }
 
+/// The headers which may carry the ID the server assigned to a request, in order of preference
+const REQUEST_ID_HEADERS: [&str; 3] = ["x-request-id", "request-id", "x-correlation-id"];
+
+/// The ID the server assigned to a request, from the first of [`REQUEST_ID_HEADERS`] present in
+/// the `headers` of its response
+pub(crate) fn request_id(headers: &HeaderMap) -> Option<&str> {
+    REQUEST_ID_HEADERS
+        .iter()
+        .find_map(|name| headers.get(*name).and_then(|v| v.to_str().ok()))
+}
+
 /// Maps a response error for all of the coordination services that use a JSON response type
 pub fn map_api_error(resp: Response) -> Result<Response> {
     if let Err(source) = resp.error_for_status_ref() {
-        return Err(ApiError { response: resp.json::<ApiResponse>()?, source }.into());
+        let status = resp.status();
+        let headers = resp.headers().clone();
+        let body = resp.bytes()?;
+        return Err(status_error(status, headers, &body, Some(source)));
     }
     Ok(resp)
 }
 
+/// Maps an error status into an [`ApiError`] if the body is an RFC 7807 problem, or otherwise into
+/// an [`HttpError`] which preserves the raw response. The `source` is only known for responses
+/// received by `reqwest`.
+pub(crate) fn status_error(
+    status: StatusCode,
+    headers: HeaderMap,
+    body: &[u8],
+    source: Option<reqwest::Error>,
+) -> SeaplaneError {
+    match serde_json::from_slice(body) {
+        Ok(response) => ApiError {
+            response,
+            status,
+            rate_limit: RateLimit::from_headers(&headers),
+            headers,
+            source,
+        }
+        .into(),
+        Err(_) => {
+            HttpError { status, headers, body: String::from_utf8_lossy(body).into_owned(), source }
+                .into()
+        }
+    }
+}
+
 /// A generic Server Side error response derived from errors following [RFC 7807][rfc_7807]
 ///
 /// [rfc_7807]: https://www.rfc-editor.org/rfc/rfc7807
@@ -87,19 +152,33 @@ pub fn map_api_error(resp: Response) -> Result<Response> {
 #[non_exhaustive]
 pub struct ApiError {
     pub response: ApiResponse,
-    pub source: reqwest::Error,
+    /// The HTTP status of the response
+    pub status: StatusCode,
+    /// The rate limit reported in the headers of the response
+    pub rate_limit: Option<RateLimit>,
+    /// All headers of the response
+    pub headers: HeaderMap,
+    /// The error reported by `reqwest`, which is `None` for responses from a custom
+    /// [`Transport`](crate::api::Transport)
+    pub source: Option<reqwest::Error>,
 }
 
 impl ApiError {
+    /// The ID the server assigned to the request, from the `X-Request-ID` header (or its
+    /// `Request-ID` or `X-Correlation-ID` equivalents), to include in a support ticket
+    pub fn request_id(&self) -> Option<&str> { request_id(&self.headers) }
+
+    /// Classifies the error by its HTTP status, see [`ErrorKind`]
+    pub fn kind(&self) -> ErrorKind { ErrorKind::from_status(self.status) }
+
+    /// Whether the error is transient, meaning the same request may succeed if retried later
+    pub fn is_retryable(&self) -> bool { self.kind().is_retryable() }
+
     /// A convenience method for seeing if the error came from an HTTP 404 NOT FOUND
-    pub fn is_http_not_found(&self) -> bool {
-        self.source.status() == Some(reqwest::StatusCode::NOT_FOUND)
-    }
+    pub fn is_http_not_found(&self) -> bool { self.status == reqwest::StatusCode::NOT_FOUND }
 
     /// A convenience method for seeing if the error came from an HTTP 401 UNAUTHORIZED
-    pub fn is_http_unauthorized(&self) -> bool {
-        self.source.status() == Some(reqwest::StatusCode::UNAUTHORIZED)
-    }
+    pub fn is_http_unauthorized(&self) -> bool { self.status == reqwest::StatusCode::UNAUTHORIZED }
 }
 
 impl PartialEq for ApiError {
@@ -111,5 +190,133 @@ impl fmt::Display for ApiError {
 }
 
 impl Error for ApiError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> { Some(&self.source) }
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            Some(e) => Some(e),
+            None => None,
+        }
+    }
+}
+
+/// A Server Side error response whose body is not an [RFC 7807][rfc_7807] problem, such as an HTML
+/// page or an empty body returned by a load balancer or gateway
+///
+/// [rfc_7807]: https://www.rfc-editor.org/rfc/rfc7807
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct HttpError {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    /// The raw body of the response (with any invalid UTF-8 replaced)
+    pub body: String,
+    /// The error reported by `reqwest`, which is `None` for responses from a custom
+    /// [`Transport`](crate::api::Transport)
+    pub source: Option<reqwest::Error>,
+}
+
+impl HttpError {
+    /// The ID the server assigned to the request, from the `X-Request-ID` header (or its
+    /// `Request-ID` or `X-Correlation-ID` equivalents), to include in a support ticket
+    pub fn request_id(&self) -> Option<&str> { request_id(&self.headers) }
+
+    /// Classifies the error by its HTTP status, see [`ErrorKind`]
+    pub fn kind(&self) -> ErrorKind { ErrorKind::from_status(self.status) }
+
+    /// The rate limit reported in the headers of the response
+    pub fn rate_limit(&self) -> Option<RateLimit> { RateLimit::from_headers(&self.headers) }
+
+    /// Whether the error is transient, meaning the same request may succeed if retried later
+    pub fn is_retryable(&self) -> bool { self.kind().is_retryable() }
+}
+
+impl PartialEq for HttpError {
+    fn eq(&self, rhs: &Self) -> bool { self.status == rhs.status && self.body == rhs.body }
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Keep errors from gateways which respond with entire HTML pages readable
+        const MAX_BODY_LEN: usize = 512;
+
+        write!(f, "HTTP {}", self.status)?;
+        let body = self.body.trim();
+        if !body.is_empty() {
+            match body.char_indices().nth(MAX_BODY_LEN) {
+                Some((end, _)) => write!(f, ": {}...", &body[..end])?,
+                None => write!(f, ": {body}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Error for HttpError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            Some(e) => Some(e),
+            None => None,
+        }
+    }
+}
+
+/// An error reported by a custom [`Transport`](crate::api::Transport) which failed to get a
+/// response, such as when the connection was refused
+#[derive(Debug)]
+pub struct TransportError {
+    kind: ErrorKind,
+    source: Box<dyn Error + Send + Sync>,
+}
+
+impl TransportError {
+    /// Create a new error of `kind`, which determines whether the request is retried (see
+    /// [`ErrorKind::is_retryable`])
+    pub fn new<E>(kind: ErrorKind, source: E) -> Self
+    where
+        E: Into<Box<dyn Error + Send + Sync>>,
+    {
+        Self { kind, source: source.into() }
+    }
+
+    /// The kind of the error
+    pub fn kind(&self) -> ErrorKind { self.kind }
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}", self.source) }
+}
+
+impl Error for TransportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> { Some(&*self.source) }
+}
+
+#[cfg(test)]
+mod http_error_tests {
+    use super::*;
+
+    #[test]
+    fn display_truncates_body() {
+        let e = HttpError {
+            status: StatusCode::BAD_GATEWAY,
+            headers: HeaderMap::new(),
+            body: "x".repeat(1000),
+            source: None,
+        };
+        assert_eq!(e.to_string(), format!("HTTP 502 Bad Gateway: {}...", "x".repeat(512)));
+    }
+
+    #[test]
+    fn status_error_keeps_request_id() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", "abc".parse().unwrap());
+
+        let body = br#"{"title": "Conflict", "status": 409}"#;
+        match status_error(StatusCode::CONFLICT, headers.clone(), body, None) {
+            SeaplaneError::ApiResponse(e) => assert_eq!(e.request_id(), Some("abc")),
+            e => panic!("expected an API error, got {e:?}"),
+        }
+        match status_error(StatusCode::BAD_GATEWAY, headers, b"<html></html>", None) {
+            SeaplaneError::HttpResponse(e) => assert_eq!(e.request_id(), Some("abc")),
+            e => panic!("expected an HTTP error, got {e:?}"),
+        }
+    }
 }
@@ -3,5 +3,9 @@
 /// Identity contains endpoints for things such as Authentication
 pub static IDENTITY_API_URL: &str = "https://flightdeck.cplane.cloud/";
 
+/// The environment variable which, when set, overrides [`IDENTITY_API_URL`] as the base URL of
+/// the Identity API
+pub static IDENTITY_API_URL_ENV: &str = "SEAPLANE_IDENTITY_URL";
+
 #[cfg(feature = "identity_api_v1")]
 pub mod v1;
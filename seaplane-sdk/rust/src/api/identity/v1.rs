@@ -1,14 +1,19 @@
 //! The API endpoints related to Tokens and Authentication
 
-use reqwest::{
-    blocking,
-    header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_LENGTH, CONTENT_TYPE},
-    Url,
-};
+mod credentials;
+
+use std::sync::Arc;
+
+pub use credentials::*;
+use reqwest::header::{HeaderValue, ACCEPT, CONTENT_LENGTH};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    api::{identity::IDENTITY_API_URL, map_api_error},
+    api::{
+        identity::{IDENTITY_API_URL, IDENTITY_API_URL_ENV},
+        ApiCall, ApiRequest, Auth, HttpOptions, Observer, RateLimiter, RequestBuilder, RetryPolicy,
+        Secret, Service, SharedObserver, Transport,
+    },
     error::{Result, SeaplaneError},
 };
 
@@ -26,31 +31,37 @@ pub struct AccessToken {
     pub subdomain: String,
 }
 
-#[derive(Default, Debug)]
+#[derive(Debug)]
 pub struct TokenRequestBuilder {
     // Required for Bearer Auth
-    api_key: Option<String>,
-    // Used for testing
-    #[doc(hidden)]
-    base_url: Option<Url>,
-    // Used to allow HTTP endpoints
-    #[cfg(any(feature = "allow_insecure_urls", feature = "danger_zone"))]
-    allow_http: bool,
-    // Used to allow invalid TLS certs
-    #[cfg(any(feature = "allow_invalid_certs", feature = "danger_zone"))]
-    allow_invalid_certs: bool,
+    api_key: Option<Secret>,
+    builder: RequestBuilder<()>,
+}
+
+impl Default for TokenRequestBuilder {
+    fn default() -> Self { Self::new() }
 }
 
 impl TokenRequestBuilder {
     /// Create a new `Default` builder
-    pub fn new() -> Self { Self::default() }
+    pub fn new() -> Self {
+        Self {
+            api_key: None,
+            builder: RequestBuilder::new(
+                Service::Identity,
+                IDENTITY_API_URL,
+                IDENTITY_API_URL_ENV,
+                TOKEN_API_BASE_PATH,
+            ),
+        }
+    }
 
     /// Set the API Key used in Bearer Authorization
     ///
     /// **NOTE:** This is required
     #[must_use]
     pub fn api_key<S: Into<String>>(mut self, key: S) -> Self {
-        self.api_key = Some(key.into());
+        self.api_key = Some(Secret::new(key));
         self
     }
 
@@ -58,7 +69,7 @@ impl TokenRequestBuilder {
     #[cfg(any(feature = "allow_insecure_urls", feature = "danger_zone"))]
     #[cfg_attr(docsrs, doc(cfg(any(feature = "allow_insecure_urls", feature = "danger_zone"))))]
     pub fn allow_http(mut self, yes: bool) -> Self {
-        self.allow_http = yes;
+        self.builder = self.builder.allow_http(yes);
         self
     }
 
@@ -66,75 +77,89 @@ impl TokenRequestBuilder {
     #[cfg(any(feature = "allow_invalid_certs", feature = "danger_zone"))]
     #[cfg_attr(docsrs, doc(cfg(any(feature = "allow_invalid_certs", feature = "danger_zone"))))]
     pub fn allow_invalid_certs(mut self, yes: bool) -> Self {
-        self.allow_invalid_certs = yes;
+        self.builder = self.builder.allow_invalid_certs(yes);
         self
     }
 
-    /// Build a TokenRequest from the given parameters
-    pub fn build(self) -> Result<TokenRequest> {
-        if self.api_key.is_none() {
-            return Err(SeaplaneError::MissingRequestApiKey);
-        }
+    /// Set the [`HttpOptions`] such as timeouts, proxy and TLS certificates used for the
+    /// connection (default: [`HttpOptions::default`])
+    #[must_use]
+    pub fn http_options(mut self, options: HttpOptions) -> Self {
+        self.builder = self.builder.http_options(options);
+        self
+    }
 
-        let mut headers = HeaderMap::new();
-        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-        headers.insert(CONTENT_LENGTH, HeaderValue::from_static("0"));
-
-        #[cfg_attr(
-            not(any(
-                feature = "api_tests",
-                feature = "allow_insecure_urls",
-                feature = "danger_zone"
-            )),
-            allow(unused_mut)
-        )]
-        let mut builder = blocking::Client::builder()
-            .default_headers(headers)
-            .https_only(true);
-
-        cfg_if::cfg_if! {
-            if #[cfg(feature = "api_tests")] {
-                builder = builder.https_only(false);
-            } else if #[cfg(any(feature = "allow_insecure_urls", feature = "danger_zone"))] {
-                builder = builder.https_only(!self.allow_http);
-            }
-        }
-        #[cfg(any(feature = "allow_invalid_certs", feature = "danger_zone"))]
-        {
-            builder = builder.danger_accept_invalid_certs(self.allow_invalid_certs);
-        }
+    /// Set the [`RetryPolicy`] used to retry token requests which failed for transient reasons
+    /// (default: [`RetryPolicy::default`]). Fetching a token has no side effects, so unlike other
+    /// `POST` requests it is always retried.
+    #[must_use]
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.builder = self.builder.retry_policy(policy);
+        self
+    }
 
-        let url = if let Some(url) = self.base_url {
-            url.join(TOKEN_API_BASE_PATH)?
-        } else {
-            let mut url: Url = IDENTITY_API_URL.parse()?;
-            url.set_path(TOKEN_API_BASE_PATH);
-            url
-        };
+    /// Throttle requests on the client side using the [`RateLimiter`], which may be shared with
+    /// other requests (default: no client-side limit)
+    #[must_use]
+    pub fn rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.builder = self.builder.rate_limiter(limiter);
+        self
+    }
 
-        Ok(TokenRequest {
-            api_key: self.api_key.unwrap(),
-            client: builder.build()?,
-            endpoint_url: url,
-        })
+    /// Send requests through the [`Transport`] rather than constructing a new HTTP client, in
+    /// which case any client settings on this builder are ignored
+    #[must_use]
+    pub fn transport<T: Transport + 'static>(self, transport: T) -> Self {
+        self.shared_transport(Arc::new(transport))
     }
 
-    // Used in testing and development to manually set the URL
-    #[doc(hidden)]
-    pub fn base_url<S: AsRef<str>>(mut self, url: S) -> Self {
-        self.base_url = Some(url.as_ref().parse().unwrap());
+    /// Send requests through a transport shared with other requests
+    pub(crate) fn shared_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.builder = self.builder.transport(transport);
         self
     }
+
+    /// Notify the [`Observer`] of the outcome of every call, such as to record metrics (default: no
+    /// observer)
+    #[must_use]
+    pub fn observer<O: Observer + 'static>(self, observer: O) -> Self {
+        self.shared_observer(SharedObserver::new(observer))
+    }
+
+    /// Notify an observer shared with other requests
+    pub(crate) fn shared_observer(mut self, observer: SharedObserver) -> Self {
+        self.builder = self.builder.shared_observer(observer);
+        self
+    }
+
+    /// Set the base URL of the Identity API, such as `https://flightdeck.example.com/`.
+    ///
+    /// Defaults to the value of the [`IDENTITY_API_URL_ENV`] environment variable if set, and
+    /// otherwise to [`IDENTITY_API_URL`]. The URL is parsed when the request is built, returning
+    /// an error if it is invalid.
+    #[must_use]
+    pub fn api_url<S: Into<String>>(mut self, url: S) -> Self {
+        self.builder = self.builder.api_url(url);
+        self
+    }
+
+    /// Build a TokenRequest from the given parameters
+    pub fn build(self) -> Result<TokenRequest> {
+        let api_key = self.api_key.ok_or(SeaplaneError::MissingRequestApiKey)?;
+
+        Ok(TokenRequest { request: self.builder.auth(Auth::Token(api_key)).build()? })
+    }
+
+    // Used in testing and development to manually set the URL
+    #[doc(hidden)]
+    pub fn base_url<S: AsRef<str>>(self, url: S) -> Self { self.api_url(url.as_ref()) }
 }
 
 /// For making requests against the `/v1/token` APIs.
 #[derive(Debug)]
 pub struct TokenRequest {
-    api_key: String,
-    #[doc(hidden)]
-    client: reqwest::blocking::Client,
-    #[doc(hidden)]
-    endpoint_url: Url,
+    // Authorized with the API key
+    request: ApiRequest<()>,
 }
 
 impl TokenRequest {
@@ -153,12 +178,21 @@ impl TokenRequest {
     /// dbg!(resp);
     /// ```
     pub fn access_token(&self) -> Result<String> {
-        let resp = self
-            .client
-            .post(self.endpoint_url.clone())
-            .bearer_auth(&self.api_key)
-            .send()?;
-        map_api_error(resp)?.text().map_err(Into::into)
+        Ok(self
+            .request
+            .send_with_token(self.access_token_call())?
+            .text())
+    }
+
+    /// The async variant of [`TokenRequest::access_token`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn access_token_async(&self) -> Result<String> {
+        Ok(self
+            .request
+            .send_with_token_async(self.access_token_call())
+            .await?
+            .text())
     }
 
     /// Returns a JSON response of an `AccessToken` which contains the short lived JWT used to
@@ -175,14 +209,33 @@ impl TokenRequest {
     /// dbg!(resp);
     /// ```
     pub fn access_token_json(&self) -> Result<AccessToken> {
-        let resp = self
-            .client
-            .post(self.endpoint_url.clone())
-            .bearer_auth(&self.api_key)
+        self.request
+            .send_with_token(self.access_token_json_call())?
+            .json()
+    }
+
+    /// The async variant of [`TokenRequest::access_token_json`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn access_token_json_async(&self) -> Result<AccessToken> {
+        self.request
+            .send_with_token_async(self.access_token_json_call())
+            .await?
+            .json()
+    }
+
+    fn access_token_call(&self) -> ApiCall {
+        let mut call = ApiCall::post(self.request.endpoint_url.clone())
+            .header(CONTENT_LENGTH, HeaderValue::from_static("0"))
+            .operation("access_token");
+        // Fetching a token has no side effects, so it can safely be retried
+        call.idempotent = true;
+        call
+    }
+
+    fn access_token_json_call(&self) -> ApiCall {
+        self.access_token_call()
             .header(ACCEPT, HeaderValue::from_static("application/json"))
-            .send()?;
-        map_api_error(resp)?
-            .json::<AccessToken>()
-            .map_err(Into::into)
+            .operation("access_token_json")
     }
 }
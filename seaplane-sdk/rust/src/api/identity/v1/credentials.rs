@@ -0,0 +1,218 @@
+//! A provider of access tokens which can be shared between all request builders
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use base64::{
+    alphabet::URL_SAFE,
+    engine::{
+        general_purpose::{GeneralPurpose, GeneralPurposeConfig},
+        DecodePaddingMode, Engine,
+    },
+};
+use serde::Deserialize;
+
+use crate::{
+    api::identity::v1::{AccessToken, TokenRequest},
+    error::Result,
+};
+
+/// The default amount of time prior to an access token's expiration at which it will be
+/// refreshed
+pub const DEFAULT_REFRESH_MARGIN: Duration = Duration::from_secs(30);
+
+/// Fetches, caches, and refreshes the short lived [`AccessToken`] derived from an API key.
+///
+/// A `Credentials` can be handed to any of the `*RequestBuilder`s in place of a static token.
+/// Cloning a `Credentials` is cheap and all clones share the same cached token, so a single
+/// instance can be used for every request made by an application.
+///
+/// The token is fetched on first use and re-used until it is within the refresh margin (default
+/// [`DEFAULT_REFRESH_MARGIN`]) of the expiration encoded in the JWT, at which point a new token is
+/// fetched. If a request is still rejected with an HTTP 401 UNAUTHORIZED a new token is fetched
+/// and the request is retried once.
+///
+/// # Examples
+///
+/// ```no_run
+/// use seaplane::api::{compute::v2::FormationsRequest, identity::v1::Credentials};
+///
+/// let creds = Credentials::from_api_key("abc123").unwrap();
+///
+/// let req = FormationsRequest::builder()
+///     .credentials(creds.clone())
+///     .build()
+///     .unwrap();
+///
+/// let resp = req.get_all().unwrap();
+/// dbg!(resp);
+/// ```
+#[derive(Clone)]
+pub struct Credentials {
+    token_request: Arc<TokenRequest>,
+    cached: Arc<Mutex<Option<CachedToken>>>,
+    // Held by the caller fetching a new token, so that the others wait on its request rather than
+    // on the cache, which is never locked across a request
+    refreshing: Arc<Mutex<()>>,
+    #[cfg(feature = "async")]
+    refreshing_async: Arc<tokio::sync::Mutex<()>>,
+    refresh_margin: Duration,
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Deliberately omits the API key and token
+        f.debug_struct("Credentials")
+            .field("refresh_margin", &self.refresh_margin)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Clone)]
+struct CachedToken {
+    token: AccessToken,
+    expires_at: Option<SystemTime>,
+}
+
+impl Credentials {
+    /// Create a new `Credentials` which fetches access tokens using the given [`TokenRequest`]
+    pub fn new(token_request: TokenRequest) -> Self {
+        Self {
+            token_request: Arc::new(token_request),
+            cached: Arc::new(Mutex::new(None)),
+            refreshing: Arc::new(Mutex::new(())),
+            #[cfg(feature = "async")]
+            refreshing_async: Arc::new(tokio::sync::Mutex::new(())),
+            refresh_margin: DEFAULT_REFRESH_MARGIN,
+        }
+    }
+
+    /// Create a new `Credentials` which fetches access tokens from the default Identity API using
+    /// the given API key
+    pub fn from_api_key<S: Into<String>>(api_key: S) -> Result<Self> {
+        Ok(Self::new(TokenRequest::builder().api_key(api_key).build()?))
+    }
+
+    /// The amount of time prior to the access token's expiration at which it will be refreshed
+    /// (default: [`DEFAULT_REFRESH_MARGIN`])
+    #[must_use]
+    pub fn refresh_margin(mut self, margin: Duration) -> Self {
+        self.refresh_margin = margin;
+        self
+    }
+
+    /// Seeds the cache with a previously acquired [`AccessToken`], which will be used until it
+    /// expires or is rejected
+    #[must_use]
+    pub fn with_access_token(self, token: AccessToken) -> Self {
+        *self.lock() = Some(CachedToken::new(token));
+        self
+    }
+
+    /// Returns the cached [`AccessToken`], fetching a new one if there is no cached token or the
+    /// cached token is about to expire
+    pub fn access_token(&self) -> Result<AccessToken> {
+        if let Some(token) = self.valid_token(&self.lock()) {
+            return Ok(token);
+        }
+        // Concurrent callers wait on the refresh lock, and use the token fetched by whichever
+        // caller held it before them
+        let _refreshing = self.refreshing.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(token) = self.valid_token(&self.lock()) {
+            return Ok(token);
+        }
+        let token = self.token_request.access_token_json()?;
+        *self.lock() = Some(CachedToken::new(token.clone()));
+        Ok(token)
+    }
+
+    /// The async variant of [`Credentials::access_token`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn access_token_async(&self) -> Result<AccessToken> {
+        if let Some(token) = self.valid_token(&self.lock()) {
+            return Ok(token);
+        }
+        // As in `access_token`, but the refresh lock can be held across an `.await`
+        let _refreshing = self.refreshing_async.lock().await;
+        if let Some(token) = self.valid_token(&self.lock()) {
+            return Ok(token);
+        }
+        let token = self.token_request.access_token_json_async().await?;
+        *self.lock() = Some(CachedToken::new(token.clone()));
+        Ok(token)
+    }
+
+    /// Discards the cached token if it is `rejected`, so that the next call fetches a new one.
+    ///
+    /// A token that was already replaced (for example by a concurrent request) is left in place.
+    pub fn invalidate(&self, rejected: &AccessToken) {
+        let mut cached = self.lock();
+        if cached.as_ref().map(|c| c.token.token == rejected.token) == Some(true) {
+            *cached = None;
+        }
+    }
+
+    fn valid_token(&self, cached: &Option<CachedToken>) -> Option<AccessToken> {
+        cached
+            .as_ref()
+            .filter(|c| {
+                c.expires_at
+                    .map_or(true, |exp| SystemTime::now() + self.refresh_margin < exp)
+            })
+            .map(|c| c.token.clone())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<CachedToken>> {
+        // A panic while holding the lock cannot leave the cache in an inconsistent state
+        self.cached.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl CachedToken {
+    fn new(token: AccessToken) -> Self {
+        let expires_at = jwt_expiration(&token.token);
+        Self { token, expires_at }
+    }
+}
+
+/// Decodes the `exp` claim of a JWT without validating the signature, returning `None` if the
+/// token could not be decoded or has no expiration.
+fn jwt_expiration(jwt: &str) -> Option<SystemTime> {
+    #[derive(Deserialize)]
+    struct Claims {
+        exp: u64,
+    }
+
+    let engine = GeneralPurpose::new(
+        &URL_SAFE,
+        GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+    );
+    let payload = engine.decode(jwt.split('.').nth(1)?).ok()?;
+    let claims: Claims = serde_json::from_slice(&payload).ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(claims.exp))
+}
+
+#[cfg(test)]
+mod credentials_tests {
+    use super::*;
+
+    #[test]
+    fn jwt_exp() {
+        // {"alg":"HS256","typ":"JWT"}.{"sub":"tnt","exp":1700000000}
+        let jwt =
+            "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.eyJzdWIiOiJ0bnQiLCJleHAiOjE3MDAwMDAwMDB9.c2ln";
+        assert_eq!(jwt_expiration(jwt), Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)));
+    }
+
+    #[test]
+    fn jwt_no_exp() {
+        // {"alg":"HS256","typ":"JWT"}.{"sub":"tnt"}
+        let jwt = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.eyJzdWIiOiJ0bnQifQ.c2ln";
+        assert_eq!(jwt_expiration(jwt), None);
+        assert_eq!(jwt_expiration("not-a-jwt"), None);
+    }
+}
@@ -0,0 +1,224 @@
+//! Client-side throttling of the requests made to the APIs
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::api::RateLimit;
+
+/// A token bucket which limits the rate at which requests are sent.
+///
+/// The bucket holds up to `burst` tokens (default: the number of requests per second, but at least
+/// one) and is refilled at a constant rate. Every attempt at sending a request, including retries,
+/// takes a token and waits until one is available.
+///
+/// Cloning a `RateLimiter` is cheap, and all clones share the same bucket. Setting the same limiter
+/// on several requests (or on a [`SeaplaneClient`]) therefore keeps the combined rate of all of
+/// them, across any number of threads or tasks, under the limit.
+///
+/// When a response reports that the tenant's rate limit is exhausted (see [`RateLimit`]) the
+/// limiter additionally holds back all requests until the server's rate limit window resets.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use seaplane::api::{metadata::v1::MetadataRequest, RateLimiter};
+/// let limiter = RateLimiter::per_second(50).burst(10);
+///
+/// let workers: Vec<_> = (0..8)
+///     .map(|i| {
+///         let limiter = limiter.clone();
+///         std::thread::spawn(move || {
+///             let req = MetadataRequest::builder()
+///                 .token("abc123")
+///                 .encoded_key(format!("a2V5LQ{i}"))
+///                 .rate_limiter(limiter)
+///                 .build()
+///                 .unwrap();
+///             req.put_value_unencoded("value").unwrap();
+///         })
+///     })
+///     .collect();
+/// ```
+///
+/// [`SeaplaneClient`]: crate::api::SeaplaneClient
+#[derive(Clone)]
+pub struct RateLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+struct Bucket {
+    // Tokens added per second
+    rate: f64,
+    capacity: f64,
+    // May be negative when tokens have been reserved by waiting requests
+    tokens: f64,
+    refilled_at: Instant,
+    // Set when the server reported the rate limit as exhausted
+    paused_until: Option<Instant>,
+}
+
+impl fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bucket = self.lock();
+        f.debug_struct("RateLimiter")
+            .field("rate", &bucket.rate)
+            .field("burst", &bucket.capacity)
+            .finish()
+    }
+}
+
+impl RateLimiter {
+    /// Allow up to `requests` requests per second. A value of `0` is treated as `1`.
+    pub fn per_second(requests: u32) -> Self { Self::with_rate(f64::from(requests.max(1))) }
+
+    /// Allow up to `requests` requests per minute. A value of `0` is treated as `1`.
+    pub fn per_minute(requests: u32) -> Self { Self::with_rate(f64::from(requests.max(1)) / 60.0) }
+
+    fn with_rate(rate: f64) -> Self {
+        let capacity = rate.max(1.0);
+        Self {
+            bucket: Arc::new(Mutex::new(Bucket {
+                rate,
+                capacity,
+                tokens: capacity,
+                refilled_at: Instant::now(),
+                paused_until: None,
+            })),
+        }
+    }
+
+    /// The maximum number of requests which may be sent at once after a period of inactivity. A
+    /// value of `0` is treated as `1`.
+    #[must_use]
+    pub fn burst(self, requests: u32) -> Self {
+        {
+            let mut bucket = self.lock();
+            bucket.capacity = f64::from(requests.max(1));
+            bucket.tokens = bucket.capacity;
+        }
+        self
+    }
+
+    /// Takes a token, blocking the current thread until one is available
+    pub(crate) fn acquire(&self) {
+        let wait = self.reserve(Instant::now());
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+
+    /// The async variant of [`RateLimiter::acquire`]
+    #[cfg(feature = "async")]
+    pub(crate) async fn acquire_async(&self) {
+        let wait = self.reserve(Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Holds back all requests until the server's rate limit window resets if `limit` reports that
+    /// it is exhausted
+    pub(crate) fn observe(&self, limit: &RateLimit) {
+        if let (true, Some(reset)) = (limit.is_exhausted(), limit.reset) {
+            let until = Instant::now() + reset;
+            let mut bucket = self.lock();
+            bucket.paused_until = Some(bucket.paused_until.map_or(until, |p| p.max(until)));
+        }
+    }
+
+    // Takes a token (possibly going into debt) and returns how long to wait before using it
+    fn reserve(&self, now: Instant) -> Duration {
+        let mut bucket = self.lock();
+        // No tokens are taken before a pause ends, so those reserved during it are spread out
+        // after it rather than all used as soon as it ends
+        let start = bucket.paused_until.map_or(now, |until| until.max(now));
+        let elapsed = start.saturating_duration_since(bucket.refilled_at);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * bucket.rate).min(bucket.capacity);
+        bucket.refilled_at = bucket.refilled_at.max(start);
+        bucket.tokens -= 1.0;
+
+        let debt = if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / bucket.rate)
+        };
+        bucket.refilled_at.saturating_duration_since(now) + debt
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Bucket> {
+        // The bucket is always left in a consistent state, so a poisoned lock is still usable
+        self.bucket.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod rate_limiter_tests {
+    use super::*;
+
+    #[test]
+    fn burst_then_steady_rate() {
+        let limiter = RateLimiter::per_second(10).burst(2);
+        let now = Instant::now();
+        assert_eq!(limiter.reserve(now), Duration::ZERO);
+        assert_eq!(limiter.reserve(now), Duration::ZERO);
+        assert_eq!(limiter.reserve(now), Duration::from_millis(100));
+        assert_eq!(limiter.reserve(now), Duration::from_millis(200));
+
+        // After a while the bucket is full again, but never holds more than the burst
+        let later = now + Duration::from_secs(60);
+        assert_eq!(limiter.reserve(later), Duration::ZERO);
+        assert_eq!(limiter.reserve(later), Duration::ZERO);
+        assert_eq!(limiter.reserve(later), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn clones_share_the_bucket() {
+        let limiter = RateLimiter::per_minute(1);
+        let other = limiter.clone();
+        let now = Instant::now();
+        assert_eq!(limiter.reserve(now), Duration::ZERO);
+        let wait = std::thread::spawn(move || other.reserve(now))
+            .join()
+            .unwrap();
+        assert_eq!(wait, Duration::from_secs(60));
+    }
+
+    #[test]
+    fn exhausted_server_limit_pauses() {
+        let limiter = RateLimiter::per_second(100);
+        limiter.observe(&RateLimit {
+            remaining: Some(1),
+            reset: Some(Duration::from_secs(30)),
+            ..Default::default()
+        });
+        assert_eq!(limiter.reserve(Instant::now()), Duration::ZERO);
+
+        limiter.observe(&RateLimit {
+            remaining: Some(0),
+            reset: Some(Duration::from_secs(30)),
+            ..Default::default()
+        });
+        let wait = limiter.reserve(Instant::now());
+        assert!(wait > Duration::from_secs(29) && wait <= Duration::from_secs(30));
+    }
+
+    #[test]
+    fn reservations_during_pause_are_spread_out() {
+        let limiter = RateLimiter::per_second(10).burst(1);
+        let now = Instant::now();
+        limiter.lock().paused_until = Some(now + Duration::from_secs(1));
+
+        let waits: Vec<_> = (0..3).map(|_| limiter.reserve(now)).collect();
+        assert_eq!(
+            waits,
+            [Duration::from_millis(1000), Duration::from_millis(1100), Duration::from_millis(1200)]
+        );
+
+        // Once the pause is over the bucket refills as usual
+        let later = now + Duration::from_secs(60);
+        assert_eq!(limiter.reserve(later), Duration::ZERO);
+    }
+}
@@ -4,3 +4,8 @@ pub mod v1;
 
 /// The base URL for our Locks API endpoints
 pub static LOCKS_API_URL: &str = "https://metadata.cplane.cloud/";
+
+/// The environment variable which, when set, overrides [`LOCKS_API_URL`]. The Locks API is
+/// served by the Metadata service, so this is the same variable as
+/// [`METADATA_API_URL_ENV`](crate::api::metadata::METADATA_API_URL_ENV).
+pub static LOCKS_API_URL_ENV: &str = "SEAPLANE_METADATA_URL";
@@ -1,16 +1,20 @@
 //! The `/locks` endpoint APIs which allows working with [`HeldLock`]s
 mod models;
 
+use std::sync::Arc;
+
 use reqwest::Url;
 use serde::Deserialize;
 
 pub use self::models::*;
+#[cfg(feature = "identity_api_v1")]
+use crate::api::identity::v1::Credentials;
 use crate::{
     api::{
-        locks::{error::LocksError, LOCKS_API_URL},
-        map_api_error,
+        locks::{error::LocksError, LOCKS_API_URL, LOCKS_API_URL_ENV},
         shared::v1::RangeQueryContext,
-        ApiRequest, RequestBuilder,
+        ApiCall, ApiRequest, HttpOptions, Observer, RateLimit, RateLimiter, RequestBuilder,
+        Response, RetryPolicy, Service, Transport,
     },
     base64::add_base64_path_segment,
     error::Result,
@@ -22,7 +26,7 @@ static LOCKS_API_BASE_PATH: &str = "v1/locks/";
 /// request against the `/locks` APIs
 #[derive(Debug)]
 pub struct LocksRequestBuilder {
-    builder: RequestBuilder<RequestTarget>,
+    pub(crate) builder: RequestBuilder<RequestTarget>,
 }
 
 /// For making requests against the `/locks` APIs.
@@ -40,7 +44,10 @@ impl Default for LocksRequestBuilder {
 }
 impl LocksRequestBuilder {
     /// Create a new LocksRequestBuilder
-    pub fn new() -> Self { RequestBuilder::new(LOCKS_API_URL, LOCKS_API_BASE_PATH).into() }
+    pub fn new() -> Self {
+        RequestBuilder::new(Service::Locks, LOCKS_API_URL, LOCKS_API_URL_ENV, LOCKS_API_BASE_PATH)
+            .into()
+    }
 
     /// Build a LocksRequest from the given parameters
     pub fn build(self) -> Result<LocksRequest> { Ok(self.builder.build()?.into()) }
@@ -57,12 +64,66 @@ impl LocksRequestBuilder {
         self.builder.allow_invalid_certs(yes).into()
     }
 
+    /// Set the [`HttpOptions`] such as timeouts, proxy and TLS certificates used for the
+    /// connection (default: [`HttpOptions::default`])
+    #[must_use]
+    pub fn http_options(self, options: HttpOptions) -> Self {
+        self.builder.http_options(options).into()
+    }
+
+    /// Set the [`RetryPolicy`] used to retry idempotent requests which failed for transient
+    /// reasons (default: [`RetryPolicy::default`])
+    #[must_use]
+    pub fn retry_policy(self, policy: RetryPolicy) -> Self {
+        self.builder.retry_policy(policy).into()
+    }
+
+    /// Throttle requests on the client side using the [`RateLimiter`], which may be shared with
+    /// other requests (default: no client-side limit)
+    #[must_use]
+    pub fn rate_limiter(self, limiter: RateLimiter) -> Self {
+        self.builder.rate_limiter(limiter).into()
+    }
+
+    /// Notify the [`Observer`] of the outcome of every call, such as to record metrics (default: no
+    /// observer)
+    #[must_use]
+    pub fn observer<O: Observer + 'static>(self, observer: O) -> Self {
+        self.builder.observer(observer).into()
+    }
+
+    /// Send requests through the [`Transport`] rather than an HTTP client constructed from this
+    /// builder's settings, in which case those settings (such as [`HttpOptions`]) are ignored
+    #[must_use]
+    pub fn transport<T: Transport + 'static>(self, transport: T) -> Self {
+        self.builder.transport(Arc::new(transport)).into()
+    }
+
     /// Set the token used in Bearer Authorization
     ///
-    /// **NOTE:** This is required for all endpoints
+    /// **NOTE:** This (or [`LocksRequestBuilder::credentials`]) is required for all endpoints
     #[must_use]
     pub fn token<U: Into<String>>(self, token: U) -> Self { self.builder.token(token).into() }
 
+    /// Set the [`Credentials`] which provide the token used in Bearer Authorization, fetching and
+    /// refreshing it as required
+    ///
+    /// **NOTE:** This (or [`LocksRequestBuilder::token`]) is required for all endpoints
+    #[cfg(feature = "identity_api_v1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "identity_api_v1")))]
+    #[must_use]
+    pub fn credentials(self, credentials: Credentials) -> Self {
+        self.builder.credentials(credentials).into()
+    }
+
+    /// Set the base URL of the Locks API, such as `https://metadata.example.com/`.
+    ///
+    /// Defaults to the value of the [`LOCKS_API_URL_ENV`] environment variable if set, and
+    /// otherwise to [`LOCKS_API_URL`]. The URL is parsed when the request is built, returning an
+    /// error if it is invalid.
+    #[must_use]
+    pub fn api_url<U: Into<String>>(self, url: U) -> Self { self.builder.api_url(url).into() }
+
     // Used in testing and development to manually set the URL
     #[doc(hidden)]
     pub fn base_url<U: AsRef<str>>(self, url: U) -> Self { self.builder.base_url(url).into() }
@@ -113,6 +174,9 @@ impl LocksRequest {
     /// Create a new request builder
     pub fn builder() -> LocksRequestBuilder { LocksRequestBuilder::new() }
 
+    /// The rate limit reported by the server in the most recent response to this request, if any
+    pub fn rate_limit(&self) -> Option<RateLimit> { self.request.rate_limit() }
+
     // Internal method creating the URL for all single lock endpoints
     fn single_lock_url(&self) -> Result<Url> {
         match &self.request.target {
@@ -194,26 +258,45 @@ impl LocksRequest {
     /// dbg!(resp);
     /// ```
     pub fn acquire(&self, ttl: u32, client_id: &str) -> Result<HeldLock> {
-        let mut url = self.single_lock_url()?;
-        url.set_query(Some(&format!("ttl={ttl}&client-id={client_id}")));
-        let resp = self
-            .request
-            .client
-            .post(url)
-            .bearer_auth(&self.request.token)
-            .send()?;
-
-        #[derive(Deserialize)]
-        struct AcquireResponse {
-            id: LockId,
-            sequencer: u32,
-        }
+        self.acquire_with_response(ttl, client_id)
+            .map(Response::into_body)
+    }
+
+    /// The async variant of [`LocksRequest::acquire`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn acquire_async(&self, ttl: u32, client_id: &str) -> Result<HeldLock> {
+        self.acquire_with_response_async(ttl, client_id)
+            .await
+            .map(Response::into_body)
+    }
+
+    /// Like [`LocksRequest::acquire`], but also returns the status and headers of the response
+    pub fn acquire_with_response(&self, ttl: u32, client_id: &str) -> Result<Response<HeldLock>> {
+        let call = self.acquire_call(ttl, client_id)?;
+        let name = self.lock_name()?;
+        let resp = Response::json::<AcquireResponse>(self.request.send(call)?)?;
+        Ok(resp.map(|AcquireResponse { id, sequencer }| HeldLock { name, id, sequencer }))
+    }
 
+    /// The async variant of [`LocksRequest::acquire_with_response`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn acquire_with_response_async(
+        &self,
+        ttl: u32,
+        client_id: &str,
+    ) -> Result<Response<HeldLock>> {
+        let call = self.acquire_call(ttl, client_id)?;
         let name = self.lock_name()?;
-        map_api_error(resp)?
-            .json::<AcquireResponse>()
-            .map(|AcquireResponse { id, sequencer }| HeldLock { name, id, sequencer })
-            .map_err(Into::into)
+        let resp = Response::json::<AcquireResponse>(self.request.send_async(call).await?)?;
+        Ok(resp.map(|AcquireResponse { id, sequencer }| HeldLock { name, id, sequencer }))
+    }
+
+    fn acquire_call(&self, ttl: u32, client_id: &str) -> Result<ApiCall> {
+        let mut url = self.single_lock_url()?;
+        url.set_query(Some(&format!("ttl={ttl}&client-id={client_id}")));
+        Ok(ApiCall::post(url).operation("acquire"))
     }
 
     /// Attempts to release the given lock.
@@ -242,20 +325,38 @@ impl LocksRequest {
     /// let resp = release_req.release().unwrap();
     /// dbg!(resp)
     /// ```
-    pub fn release(&self) -> Result<()> {
-        let url = self.held_lock_url()?;
+    pub fn release(&self) -> Result<()> { self.release_with_response().map(Response::into_body) }
+
+    /// The async variant of [`LocksRequest::release`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn release_async(&self) -> Result<()> {
+        self.release_with_response_async()
+            .await
+            .map(Response::into_body)
+    }
 
+    /// Like [`LocksRequest::release`], but also returns the status and headers of the response
+    pub fn release_with_response(&self) -> Result<Response<()>> {
+        let url = self.held_lock_url()?;
+        // TODO: for now we drop the "success" message to control it ourselves
         let resp = self
             .request
-            .client
-            .delete(url)
-            .bearer_auth(&self.request.token)
-            .send()?;
+            .send(ApiCall::delete(url).operation("release"))?;
+        Ok(Response::empty(resp))
+    }
 
-        map_api_error(resp)?
-            .text()
-            .map(|_| ()) // TODO: for now we drop the "success" message to control it ourselves
-            .map_err(Into::into)
+    /// The async variant of [`LocksRequest::release_with_response`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn release_with_response_async(&self) -> Result<Response<()>> {
+        let url = self.held_lock_url()?;
+        // TODO: for now we drop the "success" message to control it ourselves
+        let resp = self
+            .request
+            .send_async(ApiCall::delete(url).operation("release"))
+            .await?;
+        Ok(Response::empty(resp))
     }
 
     /// Attempts to renew the given lock, setting the TTL to the given `ttl`
@@ -284,21 +385,38 @@ impl LocksRequest {
     /// dbg!(resp)
     /// ```
     pub fn renew(&self, ttl: u32) -> Result<()> {
-        let mut url = self.held_lock_url()?;
+        self.renew_with_response(ttl).map(Response::into_body)
+    }
 
-        url.query_pairs_mut().append_pair("ttl", &ttl.to_string());
+    /// The async variant of [`LocksRequest::renew`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn renew_async(&self, ttl: u32) -> Result<()> {
+        self.renew_with_response_async(ttl)
+            .await
+            .map(Response::into_body)
+    }
 
-        let resp = self
-            .request
-            .client
-            .patch(url)
-            .bearer_auth(&self.request.token)
-            .send()?;
+    /// Like [`LocksRequest::renew`], but also returns the status and headers of the response
+    pub fn renew_with_response(&self, ttl: u32) -> Result<Response<()>> {
+        // TODO: for now we drop the "success" message to control it ourselves
+        let resp = self.request.send(self.renew_call(ttl)?)?;
+        Ok(Response::empty(resp))
+    }
+
+    /// The async variant of [`LocksRequest::renew_with_response`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn renew_with_response_async(&self, ttl: u32) -> Result<Response<()>> {
+        // TODO: for now we drop the "success" message to control it ourselves
+        let resp = self.request.send_async(self.renew_call(ttl)?).await?;
+        Ok(Response::empty(resp))
+    }
 
-        map_api_error(resp)?
-            .text()
-            .map(|_| ()) // TODO: for now we drop the "success" message to control it ourselves
-            .map_err(Into::into)
+    fn renew_call(&self, ttl: u32) -> Result<ApiCall> {
+        let mut url = self.held_lock_url()?;
+        url.query_pairs_mut().append_pair("ttl", &ttl.to_string());
+        Ok(ApiCall::patch(url).operation("renew"))
     }
 
     /// Gets information about a single lock.
@@ -319,16 +437,38 @@ impl LocksRequest {
     /// dbg!(resp);
     /// ```
     pub fn get_lock_info(&self) -> Result<LockInfo> {
-        let url = self.single_lock_url()?;
+        self.get_lock_info_with_response().map(Response::into_body)
+    }
+
+    /// The async variant of [`LocksRequest::get_lock_info`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn get_lock_info_async(&self) -> Result<LockInfo> {
+        self.get_lock_info_with_response_async()
+            .await
+            .map(Response::into_body)
+    }
 
+    /// Like [`LocksRequest::get_lock_info`], but also returns the status and headers of the
+    /// response
+    pub fn get_lock_info_with_response(&self) -> Result<Response<LockInfo>> {
+        let url = self.single_lock_url()?;
         let resp = self
             .request
-            .client
-            .get(url)
-            .bearer_auth(&self.request.token)
-            .send()?;
+            .send(ApiCall::get(url).operation("get_lock_info"))?;
+        Response::json(resp)
+    }
 
-        map_api_error(resp)?.json::<LockInfo>().map_err(Into::into)
+    /// The async variant of [`LocksRequest::get_lock_info_with_response`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn get_lock_info_with_response_async(&self) -> Result<Response<LockInfo>> {
+        let url = self.single_lock_url()?;
+        let resp = self
+            .request
+            .send_async(ApiCall::get(url).operation("get_lock_info"))
+            .await?;
+        Response::json(resp)
     }
 
     /// Returns a single page of lock information for the given directory, beginning with the `from`
@@ -373,24 +513,21 @@ impl LocksRequest {
     /// }
     /// ```
     pub fn get_page(&self) -> Result<LockInfoRange> {
-        match &self.request.target {
-            None | Some(RequestTarget::SingleLock(_) | RequestTarget::HeldLock(_)) => {
-                Err(LocksError::IncorrectLocksRequestTarget)?
-            }
-            Some(RequestTarget::Range(_)) => {
-                let url = self.range_url()?;
-
-                let resp = self
-                    .request
-                    .client
-                    .get(url)
-                    .bearer_auth(&self.request.token)
-                    .send()?;
-                map_api_error(resp)?
-                    .json::<LockInfoRange>()
-                    .map_err(Into::into)
-            }
-        }
+        let url = self.range_url()?;
+        self.request
+            .send(ApiCall::get(url).operation("get_page"))?
+            .json()
+    }
+
+    /// The async variant of [`LocksRequest::get_page`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn get_page_async(&self) -> Result<LockInfoRange> {
+        let url = self.range_url()?;
+        self.request
+            .send_async(ApiCall::get(url).operation("get_page"))
+            .await?
+            .json()
     }
 
     /// Returns all held lock information for the given directory, from the `from` key onwards. May
@@ -424,18 +561,49 @@ impl LocksRequest {
         loop {
             let mut lir = self.get_page()?;
             pages.append(&mut lir.locks);
-            if let Some(next_key) = lir.next {
-                // TODO: Regrettable duplication here suggests that there should be a
-                // ConfigKeyRequest and a ConfigRangeRequest
-                if let Some(RequestTarget::Range(ref mut context)) = self.request.target {
-                    context.set_from(next_key);
-                } else {
-                    Err(LocksError::IncorrectLocksRequestTarget)?
-                }
-            } else {
+            if !self.set_next_page(lir.next)? {
                 break;
             }
         }
         Ok(pages)
     }
+
+    /// The async variant of [`LocksRequest::get_all_pages`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn get_all_pages_async(&mut self) -> Result<Vec<LockInfo>> {
+        let mut pages = Vec::new();
+        loop {
+            let mut lir = self.get_page_async().await?;
+            pages.append(&mut lir.locks);
+            if !self.set_next_page(lir.next)? {
+                break;
+            }
+        }
+        Ok(pages)
+    }
+
+    // Internal method moving the range target to start from `next_key`, returning `false` if
+    // there is no next page
+    fn set_next_page(&mut self, next_key: Option<LockName>) -> Result<bool> {
+        if let Some(next_key) = next_key {
+            // TODO: Regrettable duplication here suggests that there should be a
+            // ConfigKeyRequest and a ConfigRangeRequest
+            if let Some(RequestTarget::Range(ref mut context)) = self.request.target {
+                context.set_from(next_key);
+            } else {
+                Err(LocksError::IncorrectLocksRequestTarget)?
+            }
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+}
+
+// The body of a successful acquire, which does not include the lock name
+#[derive(Deserialize)]
+struct AcquireResponse {
+    id: LockId,
+    sequencer: u32,
 }
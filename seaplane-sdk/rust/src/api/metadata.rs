@@ -4,3 +4,7 @@ pub mod v1;
 
 /// The base URL for our Metadata endpoints
 pub static METADATA_API_URL: &str = "https://metadata.cplane.cloud/";
+
+/// The environment variable which, when set, overrides [`METADATA_API_URL`] as the base URL of
+/// the Metadata API
+pub static METADATA_API_URL_ENV: &str = "SEAPLANE_METADATA_URL";
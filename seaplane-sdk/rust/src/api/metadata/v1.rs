@@ -1,18 +1,22 @@
 //! The `/config` endpoint APIs which allows working with [`KeyValue`]s
 mod models;
 
+use std::sync::Arc;
+
 use reqwest::{
-    header::{self, CONTENT_TYPE},
+    header::{HeaderValue, CONTENT_TYPE},
     Url,
 };
 
 pub use self::models::*;
+#[cfg(feature = "identity_api_v1")]
+use crate::api::identity::v1::Credentials;
 use crate::{
     api::{
-        map_api_error,
-        metadata::{error::MetadataError, METADATA_API_URL},
+        metadata::{error::MetadataError, METADATA_API_URL, METADATA_API_URL_ENV},
         shared::v1::RangeQueryContext,
-        ApiRequest, RequestBuilder,
+        ApiCall, ApiRequest, HttpOptions, Observer, RateLimit, RateLimiter, RequestBuilder,
+        Response, RetryPolicy, Service, Transport,
     },
     base64::add_base64_path_segment,
     error::Result,
@@ -24,7 +28,7 @@ const METADATA_API_BASE_PATH: &str = "v1/config/";
 /// request against the `/config` APIs
 #[derive(Debug)]
 pub struct MetadataRequestBuilder {
-    builder: RequestBuilder<RequestTarget>,
+    pub(crate) builder: RequestBuilder<RequestTarget>,
 }
 
 impl From<RequestBuilder<RequestTarget>> for MetadataRequestBuilder {
@@ -37,17 +41,36 @@ impl Default for MetadataRequestBuilder {
 
 impl MetadataRequestBuilder {
     /// Create a new MetadataRequestBuilder
-    pub fn new() -> Self { RequestBuilder::new(METADATA_API_URL, METADATA_API_BASE_PATH).into() }
+    pub fn new() -> Self {
+        RequestBuilder::new(
+            Service::Metadata,
+            METADATA_API_URL,
+            METADATA_API_URL_ENV,
+            METADATA_API_BASE_PATH,
+        )
+        .into()
+    }
 
     /// Build an MetadataRequest from the given parameters
     pub fn build(self) -> Result<MetadataRequest> { Ok(self.builder.build()?.into()) }
 
     /// Set the token used in Bearer Authorization
     ///
-    /// **NOTE:** This is required for all endpoints
+    /// **NOTE:** This (or [`MetadataRequestBuilder::credentials`]) is required for all endpoints
     #[must_use]
     pub fn token<U: Into<String>>(self, token: U) -> Self { self.builder.token(token).into() }
 
+    /// Set the [`Credentials`] which provide the token used in Bearer Authorization, fetching and
+    /// refreshing it as required
+    ///
+    /// **NOTE:** This (or [`MetadataRequestBuilder::token`]) is required for all endpoints
+    #[cfg(feature = "identity_api_v1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "identity_api_v1")))]
+    #[must_use]
+    pub fn credentials(self, credentials: Credentials) -> Self {
+        self.builder.credentials(credentials).into()
+    }
+
     /// Allow non-HTTPS endpoints for this request (default: `false`)
     #[cfg(any(feature = "allow_insecure_urls", feature = "danger_zone"))]
     #[cfg_attr(docsrs, doc(cfg(any(feature = "allow_insecure_urls", feature = "danger_zone"))))]
@@ -60,6 +83,49 @@ impl MetadataRequestBuilder {
         self.builder.allow_invalid_certs(yes).into()
     }
 
+    /// Set the [`HttpOptions`] such as timeouts, proxy and TLS certificates used for the
+    /// connection (default: [`HttpOptions::default`])
+    #[must_use]
+    pub fn http_options(self, options: HttpOptions) -> Self {
+        self.builder.http_options(options).into()
+    }
+
+    /// Set the [`RetryPolicy`] used to retry idempotent requests which failed for transient
+    /// reasons (default: [`RetryPolicy::default`])
+    #[must_use]
+    pub fn retry_policy(self, policy: RetryPolicy) -> Self {
+        self.builder.retry_policy(policy).into()
+    }
+
+    /// Throttle requests on the client side using the [`RateLimiter`], which may be shared with
+    /// other requests (default: no client-side limit)
+    #[must_use]
+    pub fn rate_limiter(self, limiter: RateLimiter) -> Self {
+        self.builder.rate_limiter(limiter).into()
+    }
+
+    /// Notify the [`Observer`] of the outcome of every call, such as to record metrics (default: no
+    /// observer)
+    #[must_use]
+    pub fn observer<O: Observer + 'static>(self, observer: O) -> Self {
+        self.builder.observer(observer).into()
+    }
+
+    /// Send requests through the [`Transport`] rather than an HTTP client constructed from this
+    /// builder's settings, in which case those settings (such as [`HttpOptions`]) are ignored
+    #[must_use]
+    pub fn transport<T: Transport + 'static>(self, transport: T) -> Self {
+        self.builder.transport(Arc::new(transport)).into()
+    }
+
+    /// Set the base URL of the Metadata API, such as `https://metadata.example.com/`.
+    ///
+    /// Defaults to the value of the [`METADATA_API_URL_ENV`] environment variable if set, and
+    /// otherwise to [`METADATA_API_URL`]. The URL is parsed when the request is built, returning an
+    /// error if it is invalid.
+    #[must_use]
+    pub fn api_url<U: Into<String>>(self, url: U) -> Self { self.builder.api_url(url).into() }
+
     // Used in testing and development to manually set the URL
     #[doc(hidden)]
     pub fn base_url<U: AsRef<str>>(self, url: U) -> Self { self.builder.base_url(url).into() }
@@ -97,6 +163,9 @@ impl MetadataRequest {
     /// Create a new request builder
     pub fn builder() -> MetadataRequestBuilder { MetadataRequestBuilder::new() }
 
+    /// The rate limit reported by the server in the most recent response to this request, if any
+    pub fn rate_limit(&self) -> Option<RateLimit> { self.request.rate_limit() }
+
     // Internal method creating the URL for all single key endpoints
     fn single_key_url(&self) -> Result<Url> {
         match &self.request.target {
@@ -152,17 +221,38 @@ impl MetadataRequest {
     /// dbg!(resp);
     /// ```
     pub fn get_value(&self) -> Result<Value> {
+        self.get_value_with_response().map(Response::into_body)
+    }
+
+    /// The async variant of [`MetadataRequest::get_value`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn get_value_async(&self) -> Result<Value> {
+        self.get_value_with_response_async()
+            .await
+            .map(Response::into_body)
+    }
+
+    /// Like [`MetadataRequest::get_value`], but also returns the status and headers of the
+    /// response
+    pub fn get_value_with_response(&self) -> Result<Response<Value>> {
+        let url = self.single_key_url()?;
+        let resp = self
+            .request
+            .send(ApiCall::get(url).operation("get_value"))?;
+        Ok(Response::json::<KeyValue>(resp)?.map(|kv| kv.value))
+    }
+
+    /// The async variant of [`MetadataRequest::get_value_with_response`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn get_value_with_response_async(&self) -> Result<Response<Value>> {
         let url = self.single_key_url()?;
         let resp = self
             .request
-            .client
-            .get(url)
-            .bearer_auth(&self.request.token)
-            .send()?;
-        map_api_error(resp)?
-            .json::<KeyValue>()
-            .map(|kv| kv.value)
-            .map_err(Into::into)
+            .send_async(ApiCall::get(url).operation("get_value"))
+            .await?;
+        Ok(Response::json::<KeyValue>(resp)?.map(|kv| kv.value))
     }
 
     /// Adds an unencoded value to the store at the given key performing the encoding before
@@ -187,6 +277,13 @@ impl MetadataRequest {
         self.put_value(Value::from_unencoded(value))
     }
 
+    /// The async variant of [`MetadataRequest::put_value_unencoded`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn put_value_unencoded_async<S: AsRef<[u8]>>(&self, value: S) -> Result<()> {
+        self.put_value_async(Value::from_unencoded(value)).await
+    }
+
     /// Adds a base64 encoded value to the store at the given key.
     ///
     /// **NOTE:** This endpoint requires the `RequestTarget` be a `Key`.
@@ -205,19 +302,40 @@ impl MetadataRequest {
     /// dbg!(resp);
     /// ```
     pub fn put_value(&self, value: Value) -> Result<()> {
-        let url = self.single_key_url()?;
-        let resp = self
-            .request
-            .client
-            .put(url)
-            .bearer_auth(&self.request.token)
-            .header(CONTENT_TYPE, header::HeaderValue::from_static("application/octet-stream"))
-            .body(value.to_string())
-            .send()?;
-        map_api_error(resp)?
-            .text()
-            .map(|_| ()) // TODO: for now we drop the "success" message to control it ourselves
-            .map_err(Into::into)
+        self.put_value_with_response(value).map(Response::into_body)
+    }
+
+    /// The async variant of [`MetadataRequest::put_value`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn put_value_async(&self, value: Value) -> Result<()> {
+        self.put_value_with_response_async(value)
+            .await
+            .map(Response::into_body)
+    }
+
+    /// Like [`MetadataRequest::put_value`], but also returns the status and headers of the
+    /// response
+    pub fn put_value_with_response(&self, value: Value) -> Result<Response<()>> {
+        // TODO: for now we drop the "success" message to control it ourselves
+        let resp = self.request.send(self.put_value_call(value)?)?;
+        Ok(Response::empty(resp))
+    }
+
+    /// The async variant of [`MetadataRequest::put_value_with_response`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn put_value_with_response_async(&self, value: Value) -> Result<Response<()>> {
+        // TODO: for now we drop the "success" message to control it ourselves
+        let resp = self.request.send_async(self.put_value_call(value)?).await?;
+        Ok(Response::empty(resp))
+    }
+
+    fn put_value_call(&self, value: Value) -> Result<ApiCall> {
+        Ok(ApiCall::put(self.single_key_url()?)
+            .operation("put_value")
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/octet-stream"))
+            .body(value.to_string()))
     }
 
     /// Deletes the key value pair at from a given base64 encoded key.
@@ -238,17 +356,40 @@ impl MetadataRequest {
     /// dbg!(resp);
     /// ```
     pub fn delete_value(&self) -> Result<()> {
+        self.delete_value_with_response().map(Response::into_body)
+    }
+
+    /// The async variant of [`MetadataRequest::delete_value`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn delete_value_async(&self) -> Result<()> {
+        self.delete_value_with_response_async()
+            .await
+            .map(Response::into_body)
+    }
+
+    /// Like [`MetadataRequest::delete_value`], but also returns the status and headers of the
+    /// response
+    pub fn delete_value_with_response(&self) -> Result<Response<()>> {
         let url = self.single_key_url()?;
+        // TODO: for now we drop the "success" message to control it ourselves
         let resp = self
             .request
-            .client
-            .delete(url)
-            .bearer_auth(&self.request.token)
-            .send()?;
-        map_api_error(resp)?
-            .text()
-            .map(|_| ()) // TODO: for now we drop the "success" message to control it ourselves
-            .map_err(Into::into)
+            .send(ApiCall::delete(url).operation("delete_value"))?;
+        Ok(Response::empty(resp))
+    }
+
+    /// The async variant of [`MetadataRequest::delete_value_with_response`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn delete_value_with_response_async(&self) -> Result<Response<()>> {
+        let url = self.single_key_url()?;
+        // TODO: for now we drop the "success" message to control it ourselves
+        let resp = self
+            .request
+            .send_async(ApiCall::delete(url).operation("delete_value"))
+            .await?;
+        Ok(Response::empty(resp))
     }
 
     /// Returns a single page of key value pairs for the given directory, beginning with the `from`
@@ -293,24 +434,21 @@ impl MetadataRequest {
     /// }
     /// ```
     pub fn get_page(&self) -> Result<KeyValueRange> {
-        match &self.request.target {
-            None | Some(RequestTarget::Key(_)) => {
-                Err(MetadataError::IncorrectMetadataRequestTarget)?
-            }
-            Some(RequestTarget::Range(_)) => {
-                let url = self.range_url()?;
-
-                let resp = self
-                    .request
-                    .client
-                    .get(url)
-                    .bearer_auth(&self.request.token)
-                    .send()?;
-                map_api_error(resp)?
-                    .json::<KeyValueRange>()
-                    .map_err(Into::into)
-            }
-        }
+        let url = self.range_url()?;
+        self.request
+            .send(ApiCall::get(url).operation("get_page"))?
+            .json()
+    }
+
+    /// The async variant of [`MetadataRequest::get_page`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn get_page_async(&self) -> Result<KeyValueRange> {
+        let url = self.range_url()?;
+        self.request
+            .send_async(ApiCall::get(url).operation("get_page"))
+            .await?
+            .json()
     }
 
     /// Returns all key-value pairs for the given directory, from the `from` key onwards. May
@@ -344,18 +482,42 @@ impl MetadataRequest {
         loop {
             let mut kvr = self.get_page()?;
             pages.append(&mut kvr.kvs);
-            if let Some(next_key) = kvr.next_key {
-                // TODO: Regrettable duplication here suggests that there should
-                // be a MetadataKeyRequest and a MetadataRangeRequest
-                if let Some(RequestTarget::Range(ref mut context)) = self.request.target {
-                    context.set_from(next_key);
-                } else {
-                    Err(MetadataError::IncorrectMetadataRequestTarget)?
-                }
-            } else {
+            if !self.set_next_page(kvr.next_key)? {
+                break;
+            }
+        }
+        Ok(pages)
+    }
+
+    /// The async variant of [`MetadataRequest::get_all_pages`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn get_all_pages_async(&mut self) -> Result<Vec<KeyValue>> {
+        let mut pages = Vec::new();
+        loop {
+            let mut kvr = self.get_page_async().await?;
+            pages.append(&mut kvr.kvs);
+            if !self.set_next_page(kvr.next_key)? {
                 break;
             }
         }
         Ok(pages)
     }
+
+    // Internal method moving the range target to start from `next_key`, returning `false` if
+    // there is no next page
+    fn set_next_page(&mut self, next_key: Option<Key>) -> Result<bool> {
+        if let Some(next_key) = next_key {
+            // TODO: Regrettable duplication here suggests that there should
+            // be a MetadataKeyRequest and a MetadataRangeRequest
+            if let Some(RequestTarget::Range(ref mut context)) = self.request.target {
+                context.set_from(next_key);
+            } else {
+                Err(MetadataError::IncorrectMetadataRequestTarget)?
+            }
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
 }
@@ -0,0 +1,211 @@
+//! Observing the outcome of every call made to the APIs, such as to export metrics
+
+use std::{fmt, sync::Arc, time::Duration};
+
+use reqwest::{Method, StatusCode};
+use strum::{Display, IntoStaticStr};
+
+#[cfg(doc)]
+use crate::error::SeaplaneError;
+use crate::error::{ErrorKind, Result};
+
+/// The API a call was made to
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Display, IntoStaticStr)]
+#[strum(serialize_all = "lowercase")]
+#[non_exhaustive]
+pub enum Service {
+    /// The `/formations` APIs
+    Compute,
+    /// The `/config` APIs
+    Metadata,
+    /// The `/locks` APIs
+    Locks,
+    /// The `/restrict` APIs
+    Restrict,
+    /// The `/token` APIs
+    Identity,
+}
+
+impl Service {
+    /// The lowercase name of the service, such as `metadata`
+    pub fn as_str(self) -> &'static str { self.into() }
+}
+
+/// The outcome of a single call to an endpoint, including all of its retries
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CallEvent {
+    /// The API the call was made to
+    pub service: Service,
+    /// The name of the operation, which is the name of the method making the call such as
+    /// `get_value` or `acquire` (without any `_async` suffix)
+    pub operation: &'static str,
+    /// The HTTP method of the call
+    pub method: Method,
+    /// The status of the final response, or `None` if no response was received
+    pub status: Option<StatusCode>,
+    /// The kind of error the call failed with, if it failed
+    pub error: Option<ErrorKind>,
+    /// The number of attempts made, which is more than one if the call was retried
+    pub attempts: u32,
+    /// The time taken by all attempts, including any delays between them
+    pub duration: Duration,
+}
+
+impl CallEvent {
+    pub(crate) fn new(
+        service: Service,
+        operation: &'static str,
+        method: Method,
+        attempts: u32,
+        duration: Duration,
+        res: &Result<crate::api::HttpResponse>,
+    ) -> Self {
+        let (status, error) = match res {
+            Ok(resp) if resp.status.is_success() => (Some(resp.status), None),
+            Ok(resp) => (Some(resp.status), Some(ErrorKind::from_status(resp.status))),
+            Err(e) => (None, Some(e.kind())),
+        };
+        Self { service, operation, method, status, error, attempts, duration }
+    }
+
+    /// Whether the call succeeded
+    pub fn is_success(&self) -> bool { self.error.is_none() }
+}
+
+/// Receives a [`CallEvent`] once every call to an API has finished, whether it succeeded or not.
+///
+/// Observers are called synchronously, on the thread (or task) which made the call, so they should
+/// return quickly. Any closure taking a `&CallEvent` is an `Observer`.
+///
+/// # Examples
+///
+/// ```
+/// # use seaplane::api::{CallEvent, SeaplaneClient};
+/// let client = SeaplaneClient::builder()
+///     .token("abc123")
+///     .observer(|event: &CallEvent| {
+///         println!("{} {} took {:?}", event.service, event.operation, event.duration)
+///     })
+///     .build()
+///     .unwrap();
+/// ```
+pub trait Observer: Send + Sync {
+    /// Called with the outcome of a finished call
+    fn on_call(&self, event: &CallEvent);
+}
+
+impl<F> Observer for F
+where
+    F: Fn(&CallEvent) + Send + Sync,
+{
+    fn on_call(&self, event: &CallEvent) { self(event) }
+}
+
+/// A cheaply cloneable [`Observer`] shared between requests
+#[derive(Clone)]
+pub(crate) struct SharedObserver(Arc<dyn Observer>);
+
+impl SharedObserver {
+    pub(crate) fn new<O: Observer + 'static>(observer: O) -> Self { Self(Arc::new(observer)) }
+
+    pub(crate) fn on_call(&self, event: &CallEvent) { self.0.on_call(event) }
+}
+
+impl fmt::Debug for SharedObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str("Observer") }
+}
+
+/// An [`Observer`] which records metrics about every call using the [`metrics`] crate, to be
+/// exported by whichever recorder (such as a Prometheus exporter) the application installed.
+///
+/// The following metrics are recorded, all labeled with the `service` and `operation` of the call:
+///
+/// - `seaplane_api_calls_total`: a counter of finished calls, additionally labeled with the
+///   `status` of the final response (or `none` if there was no response)
+/// - `seaplane_api_call_duration_seconds`: a histogram of the duration of calls, including retries
+/// - `seaplane_api_call_errors_total`: a counter of failed calls, additionally labeled with the
+///   `kind` of error, as in [`SeaplaneError::kind`]
+/// - `seaplane_api_call_retries_total`: a counter of the retries made
+#[cfg(feature = "metrics")]
+#[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct MetricsObserver;
+
+#[cfg(feature = "metrics")]
+impl MetricsObserver {
+    /// Create a new observer recording to the global `metrics` recorder
+    pub fn new() -> Self { Self }
+}
+
+#[cfg(feature = "metrics")]
+impl Observer for MetricsObserver {
+    fn on_call(&self, event: &CallEvent) {
+        let service = event.service.as_str();
+        let operation = event.operation;
+        let status = event
+            .status
+            .map_or_else(|| "none".to_owned(), |s| s.as_u16().to_string());
+
+        metrics::counter!(
+            "seaplane_api_calls_total", 1,
+            "service" => service, "operation" => operation, "status" => status
+        );
+        metrics::histogram!(
+            "seaplane_api_call_duration_seconds", event.duration.as_secs_f64(),
+            "service" => service, "operation" => operation
+        );
+        if let Some(kind) = event.error {
+            metrics::counter!(
+                "seaplane_api_call_errors_total", 1,
+                "service" => service, "operation" => operation, "kind" => format!("{kind:?}")
+            );
+        }
+        if event.attempts > 1 {
+            metrics::counter!(
+                "seaplane_api_call_retries_total", u64::from(event.attempts - 1),
+                "service" => service, "operation" => operation
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod observer_tests {
+    use reqwest::header::HeaderMap;
+
+    use super::*;
+    use crate::api::{HttpResponse, TransportError};
+
+    #[test]
+    fn event_outcome() {
+        let ok = Ok(HttpResponse::new(StatusCode::OK, HeaderMap::new(), Vec::new()));
+        let event =
+            CallEvent::new(Service::Metadata, "get_value", Method::GET, 1, Duration::ZERO, &ok);
+        assert!(event.is_success());
+        assert_eq!(event.status, Some(StatusCode::OK));
+
+        let not_found = Ok(HttpResponse::new(StatusCode::NOT_FOUND, HeaderMap::new(), Vec::new()));
+        let event = CallEvent::new(
+            Service::Metadata,
+            "get_value",
+            Method::GET,
+            1,
+            Duration::ZERO,
+            &not_found,
+        );
+        assert_eq!(event.error, Some(ErrorKind::NotFound));
+
+        let refused = Err(TransportError::new(ErrorKind::Connect, "connection refused").into());
+        let event =
+            CallEvent::new(Service::Locks, "acquire", Method::POST, 3, Duration::ZERO, &refused);
+        assert_eq!(event.status, None);
+        assert_eq!(event.error, Some(ErrorKind::Connect));
+    }
+
+    #[test]
+    fn service_names() {
+        assert_eq!(Service::Compute.as_str(), "compute");
+        assert_eq!(Service::Identity.to_string(), "identity");
+    }
+}
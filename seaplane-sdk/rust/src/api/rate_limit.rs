@@ -0,0 +1,119 @@
+//! Rate limits reported by the APIs in response headers
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use reqwest::header::HeaderMap;
+
+// Values of `*-Reset` larger than this are taken to be a Unix timestamp rather than a number of
+// seconds (it is roughly 30 years of seconds, and a timestamp from 2001)
+const RESET_EPOCH_THRESHOLD: u64 = 1_000_000_000;
+
+/// The rate limit of the tenant as reported by the server in the `RateLimit-Limit`,
+/// `RateLimit-Remaining` and `RateLimit-Reset` response headers (or their `X-RateLimit-*`
+/// equivalents).
+///
+/// Any of the values may be missing if the server did not send (or sent an invalid) header.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RateLimit {
+    /// The maximum number of requests allowed in the current window
+    pub limit: Option<u64>,
+    /// The number of requests remaining in the current window
+    pub remaining: Option<u64>,
+    /// The time until the current window resets, measured from when the response was received
+    pub reset: Option<Duration>,
+}
+
+impl RateLimit {
+    /// Parses the rate limit from response headers, returning `None` if none of the headers are
+    /// present.
+    ///
+    /// The reset may be either a number of seconds or a Unix timestamp.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use reqwest::header::{HeaderMap, HeaderValue};
+    /// # use seaplane::api::RateLimit;
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+    /// headers.insert("x-ratelimit-reset", HeaderValue::from_static("30"));
+    ///
+    /// let limit = RateLimit::from_headers(&headers).unwrap();
+    /// assert_eq!(limit.remaining, Some(0));
+    /// assert_eq!(limit.reset, Some(Duration::from_secs(30)));
+    /// assert!(limit.is_exhausted());
+    /// ```
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let limit = Self {
+            limit: header_u64(headers, "limit"),
+            remaining: header_u64(headers, "remaining"),
+            reset: header_u64(headers, "reset").map(|reset| {
+                if reset < RESET_EPOCH_THRESHOLD {
+                    return Duration::from_secs(reset);
+                }
+                // A timestamp in the past means the window has already reset
+                (UNIX_EPOCH + Duration::from_secs(reset))
+                    .duration_since(SystemTime::now())
+                    .unwrap_or_default()
+            }),
+        };
+        (limit != Self::default()).then_some(limit)
+    }
+
+    /// Whether no requests remain in the current window
+    pub fn is_exhausted(&self) -> bool { self.remaining == Some(0) }
+}
+
+// Reads `RateLimit-{name}`, falling back to `X-RateLimit-{name}`
+fn header_u64(headers: &HeaderMap, name: &str) -> Option<u64> {
+    [format!("ratelimit-{name}"), format!("x-ratelimit-{name}")]
+        .iter()
+        .find_map(|header| headers.get(header.as_str()))
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+}
+
+#[cfg(test)]
+mod rate_limit_tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn no_headers() {
+        assert_eq!(RateLimit::from_headers(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn standard_headers_take_precedence() {
+        let mut headers = HeaderMap::new();
+        headers.insert("ratelimit-limit", HeaderValue::from_static("100"));
+        headers.insert("x-ratelimit-limit", HeaderValue::from_static("50"));
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("7"));
+        headers.insert("ratelimit-reset", HeaderValue::from_static("soon"));
+
+        let limit = RateLimit::from_headers(&headers).unwrap();
+        assert_eq!(limit.limit, Some(100));
+        assert_eq!(limit.remaining, Some(7));
+        assert_eq!(limit.reset, None);
+        assert!(!limit.is_exhausted());
+    }
+
+    #[test]
+    fn reset_timestamp() {
+        let mut headers = HeaderMap::new();
+        let in_a_minute = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 60;
+        headers.insert("x-ratelimit-reset", HeaderValue::from(in_a_minute));
+        let reset = RateLimit::from_headers(&headers).unwrap().reset.unwrap();
+        assert!(reset > Duration::from_secs(58) && reset <= Duration::from_secs(60));
+
+        headers.insert("x-ratelimit-reset", HeaderValue::from(RESET_EPOCH_THRESHOLD));
+        assert_eq!(RateLimit::from_headers(&headers).unwrap().reset, Some(Duration::ZERO));
+    }
+}
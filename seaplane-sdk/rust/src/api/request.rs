@@ -1,58 +1,131 @@
 //! Purpose of those structs is basically just to allow token re-use/retry so
 //! that we don't have to request a new API token on each and every call
 
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "identity_api_v1",
+    feature = "metadata_api_v1"
+))]
+use reqwest::header::HeaderName;
 use reqwest::{
-    blocking,
-    header::{self, CONTENT_TYPE},
-    Url,
+    header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE},
+    Method, Url,
 };
+#[cfg(any(feature = "compute_api_v2", feature = "restrict_api_v1"))]
+use serde::Serialize;
 
-use crate::error::{Result, SeaplaneError};
+#[cfg(feature = "identity_api_v1")]
+use crate::api::identity::v1::Credentials;
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1"
+))]
+use crate::api::Observer;
+use crate::{
+    api::{
+        CallEvent, CallSpan, ClientConfig, HttpClient, HttpOptions, HttpRequest, HttpResponse,
+        RateLimit, RateLimiter, RetryPolicy, Service, SharedObserver, Transport,
+    },
+    error::{Result, SeaplaneError},
+};
+
+/// The header carrying the key which identifies repeated attempts at the same call
+#[cfg(feature = "compute_api_v2")]
+pub(crate) const IDEMPOTENCY_KEY: &str = "idempotency-key";
 
 /// A builder struct for creating a ApiRequest which will then be used for
 /// making a request against the APIs
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub(crate) struct RequestBuilder<T> {
+    // The API the request is made to
+    pub service: Service,
     // Target resource of this request
     pub target: Option<T>,
-    // Required for Bearer Auth
-    pub token: Option<String>,
-    // API URL
-    pub api_url: String,
+    // Required for Bearer Auth (unless credentials are provided)
+    pub token: Option<Secret>,
+    // Provides (and refreshes) the token used in Bearer Auth
+    #[cfg(feature = "identity_api_v1")]
+    pub credentials: Option<Credentials>,
+    // API URL explicitly set by the user
+    pub api_url: Option<String>,
+    // API URL used when none is set explicitly or in the environment
+    pub default_api_url: &'static str,
+    // Environment variable which overrides the default API URL
+    pub api_url_env: &'static str,
     // Base path for the api
     pub base_path: String,
-    // Only allow HTTPS endpoints (phrasing is to allow deriving Default since default for bool is
-    // false)
-    #[cfg(any(feature = "allow_insecure_urls", feature = "danger_zone"))]
-    pub allow_http: bool,
-    #[cfg(any(feature = "allow_invalid_certs", feature = "danger_zone"))]
-    pub allow_invalid_certs: bool,
-    // Used for testing
-    #[doc(hidden)]
-    pub base_url: Option<Url>,
+    // Settings used to construct the HTTP client
+    pub config: ClientConfig,
+    // A transport to use instead of constructing an HTTP client from `config`
+    pub transport: Option<Arc<dyn Transport>>,
+    // How requests which failed for transient reasons are retried
+    pub retry: RetryPolicy,
+    // Throttles the requests on the client side
+    pub limiter: Option<RateLimiter>,
+    // Notified of the outcome of every call
+    pub observer: Option<SharedObserver>,
+    // Lets the server recognize repeated attempts at the same non-idempotent call
+    pub idempotency_key: Option<String>,
 }
 
 impl<T> RequestBuilder<T> {
-    /// Create a new builder
-    pub(crate) fn new<S: Into<String>>(api_url: S, base_path: S) -> Self {
+    /// Create a new builder for the `service` API at `default_api_url` (unless overridden by the
+    /// `api_url_env` environment variable)
+    pub(crate) fn new(
+        service: Service,
+        default_api_url: &'static str,
+        api_url_env: &'static str,
+        base_path: &'static str,
+    ) -> Self {
         Self {
+            service,
             target: None,
             token: None,
-            api_url: api_url.into(),
+            #[cfg(feature = "identity_api_v1")]
+            credentials: None,
+            api_url: None,
+            default_api_url,
+            api_url_env,
             base_path: base_path.into(),
-            base_url: None,
-            #[cfg(any(feature = "allow_insecure_urls", feature = "danger_zone"))]
-            allow_http: false,
-            #[cfg(any(feature = "allow_invalid_certs", feature = "danger_zone"))]
-            allow_invalid_certs: false,
+            config: ClientConfig::default(),
+            transport: None,
+            retry: RetryPolicy::default(),
+            limiter: None,
+            observer: None,
+            idempotency_key: None,
         }
     }
 
     /// Set the token used in Bearer Authorization
     ///
-    /// **NOTE:** This is required for all endpoints
+    /// **NOTE:** This (or `credentials`) is required for all endpoints
+    #[cfg(any(
+        feature = "compute_api_v2",
+        feature = "locks_api_v1",
+        feature = "metadata_api_v1",
+        feature = "restrict_api_v1"
+    ))]
     pub(crate) fn token<U: Into<String>>(mut self, token: U) -> Self {
-        self.token = Some(token.into());
+        self.token = Some(Secret::new(token));
+        self
+    }
+
+    /// Set the credentials which provide the token used in Bearer Authorization
+    ///
+    /// **NOTE:** This (or `token`) is required for all endpoints
+    #[cfg(feature = "identity_api_v1")]
+    pub(crate) fn credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(credentials);
         self
     }
 
@@ -65,11 +138,19 @@ impl<T> RequestBuilder<T> {
         self
     }
 
+    /// Set the key sent in the `Idempotency-Key` header of non-idempotent calls, which lets them
+    /// be retried without the server performing them twice
+    #[cfg(feature = "compute_api_v2")]
+    pub(crate) fn idempotency_key(mut self, key: String) -> Self {
+        self.idempotency_key = Some(key);
+        self
+    }
+
     /// Allow non-HTTPS endpoints for this request (default: `false`)
     #[cfg(any(feature = "allow_insecure_urls", feature = "danger_zone"))]
     #[cfg_attr(docsrs, doc(cfg(any(feature = "allow_insecure_urls", feature = "danger_zone"))))]
     pub(crate) fn allow_http(mut self, yes: bool) -> Self {
-        self.allow_http = yes;
+        self.config.allow_http = yes;
         self
     }
 
@@ -77,74 +158,578 @@ impl<T> RequestBuilder<T> {
     #[cfg(any(feature = "allow_invalid_certs", feature = "danger_zone"))]
     #[cfg_attr(docsrs, doc(cfg(any(feature = "allow_invalid_certs", feature = "danger_zone"))))]
     pub(crate) fn allow_invalid_certs(mut self, yes: bool) -> Self {
-        self.allow_invalid_certs = yes;
+        self.config.allow_invalid_certs = yes;
         self
     }
 
-    /// Build an APIRequest from the given parameters
-    pub(crate) fn build(self) -> Result<ApiRequest<T>> {
-        if self.token.is_none() {
-            return Err(SeaplaneError::MissingRequestAuthToken);
-        }
+    /// Send requests through `transport` (which may be shared) rather than constructing a new
+    /// HTTP client, in which case any client settings on this builder are ignored
+    pub(crate) fn transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    /// Set the transport settings used to construct the HTTP client
+    pub(crate) fn http_options(mut self, options: HttpOptions) -> Self {
+        self.config.options = options;
+        self
+    }
+
+    /// Set the policy used to retry idempotent requests which failed for transient reasons
+    pub(crate) fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
 
-        let mut headers = header::HeaderMap::new();
-        headers.insert(CONTENT_TYPE, header::HeaderValue::from_static("application/json"));
-
-        #[cfg_attr(
-            not(any(
-                feature = "api_tests",
-                feature = "allow_insecure_urls",
-                feature = "danger_zone"
-            )),
-            allow(unused_mut)
-        )]
-        let mut builder = blocking::Client::builder()
-            .default_headers(headers)
-            .https_only(true);
-
-        cfg_if::cfg_if! {
-            if #[cfg(feature = "api_tests")] {
-                builder = builder.https_only(false);
-            } else if #[cfg(any(feature = "allow_insecure_urls", feature = "danger_zone"))] {
-                builder = builder.https_only(!self.allow_http);
+    /// Set the limiter used to throttle requests on the client side
+    pub(crate) fn rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Set the observer notified of the outcome of every call
+    #[cfg(any(
+        feature = "compute_api_v2",
+        feature = "locks_api_v1",
+        feature = "metadata_api_v1",
+        feature = "restrict_api_v1"
+    ))]
+    pub(crate) fn observer<O: Observer + 'static>(self, observer: O) -> Self {
+        self.shared_observer(SharedObserver::new(observer))
+    }
+
+    /// Set an observer which may be shared with other requests
+    pub(crate) fn shared_observer(mut self, observer: SharedObserver) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Set the authorization used for this request
+    pub(crate) fn auth(mut self, auth: Auth) -> Self {
+        match auth {
+            Auth::Token(token) => {
+                self.token = Some(token);
+                self
             }
+            #[cfg(feature = "identity_api_v1")]
+            Auth::Credentials(creds) => self.credentials(creds),
         }
-        #[cfg(any(feature = "allow_invalid_certs", feature = "danger_zone"))]
-        {
-            builder = builder.danger_accept_invalid_certs(self.allow_invalid_certs);
-        }
+    }
 
-        let url = if let Some(url) = &self.base_url {
-            url.join(&self.base_path)?
-        } else {
-            let mut url: Url = self.api_url.parse()?;
-            url.set_path(&self.base_path);
-            url
+    /// Set the base URL of the API, overriding both the environment and the default. The URL is
+    /// parsed when the request is built.
+    pub(crate) fn api_url<U: Into<String>>(mut self, url: U) -> Self {
+        self.api_url = Some(url.into());
+        self
+    }
+
+    /// Build an APIRequest from the given parameters
+    pub(crate) fn build(self) -> Result<ApiRequest<T>> {
+        // An explicit token takes precedence over credentials
+        let auth = match self.token {
+            Some(token) => Auth::Token(token),
+            #[cfg(feature = "identity_api_v1")]
+            None if self.credentials.is_some() => Auth::Credentials(self.credentials.unwrap()),
+            None => return Err(SeaplaneError::MissingRequestAuthToken),
         };
 
+        let url = endpoint_url(
+            self.api_url.as_deref(),
+            self.api_url_env,
+            self.default_api_url,
+            &self.base_path,
+        )?;
+
         Ok(ApiRequest::<T> {
+            service: self.service,
             target: self.target,
-            token: self.token.unwrap(),
-            client: builder.build()?,
+            auth,
+            retry: self.retry,
+            limiter: self.limiter,
+            rate_limit: Mutex::new(None),
+            observer: self.observer,
+            idempotency_key: self.idempotency_key,
+            transport: match self.transport {
+                Some(transport) => transport,
+                None => Arc::new(HttpClient::with_config(self.config)?),
+            },
             endpoint_url: url,
         })
     }
 
     // Used in testing and development to manually set the URL
+    #[cfg(any(
+        feature = "compute_api_v2",
+        feature = "locks_api_v1",
+        feature = "metadata_api_v1",
+        feature = "restrict_api_v1"
+    ))]
     #[doc(hidden)]
-    pub(crate) fn base_url<U: AsRef<str>>(mut self, url: U) -> Self {
-        self.base_url = Some(url.as_ref().parse().unwrap());
-        self
+    pub(crate) fn base_url<U: AsRef<str>>(self, url: U) -> Self { self.api_url(url.as_ref()) }
+}
+
+/// Resolves the URL of an endpoint by appending `base_path` to the first of: the explicitly set
+/// `api_url`, the value of the `env` environment variable, or the `default` URL.
+///
+/// The API URL is treated as a directory, so `https://example.com/api` and
+/// `https://example.com/api/` both resolve to `https://example.com/api/{base_path}`.
+pub(crate) fn endpoint_url(
+    api_url: Option<&str>,
+    env: &str,
+    default: &str,
+    base_path: &str,
+) -> Result<Url> {
+    let env_url = std::env::var(env).ok().filter(|url| !url.is_empty());
+    let mut url: Url = api_url.or(env_url.as_deref()).unwrap_or(default).parse()?;
+    if !url.path().ends_with('/') {
+        let path = format!("{}/", url.path());
+        url.set_path(&path);
     }
+    Ok(url.join(base_path)?)
 }
 
 #[derive(Debug)]
 pub(crate) struct ApiRequest<T> {
+    /// The API the request is made to
+    pub(crate) service: Service,
     /// The target resource
+    #[cfg_attr(
+        not(any(
+            feature = "compute_api_v2",
+            feature = "locks_api_v1",
+            feature = "metadata_api_v1",
+            feature = "restrict_api_v1"
+        )),
+        allow(dead_code)
+    )]
     pub(crate) target: Option<T>,
-    pub(crate) token: String,
+    pub(crate) auth: Auth,
+    pub(crate) retry: RetryPolicy,
+    pub(crate) limiter: Option<RateLimiter>,
+    // The rate limit reported in the most recent response
+    pub(crate) rate_limit: Mutex<Option<RateLimit>>,
+    pub(crate) observer: Option<SharedObserver>,
+    #[cfg_attr(not(feature = "compute_api_v2"), allow(dead_code))]
+    pub(crate) idempotency_key: Option<String>,
     #[doc(hidden)]
-    pub(crate) client: reqwest::blocking::Client,
+    pub(crate) transport: Arc<dyn Transport>,
     #[doc(hidden)]
     pub(crate) endpoint_url: Url,
 }
+
+impl<T> ApiRequest<T> {
+    /// Sends the call using the blocking client, authorized with the request's Bearer token, and
+    /// maps any error status into a [`SeaplaneError`].
+    ///
+    /// Idempotent calls which fail for transient reasons are retried according to the request's
+    /// [`RetryPolicy`]. Every attempt first waits for the request's [`RateLimiter`], if any.
+    #[cfg(any(
+        feature = "compute_api_v2",
+        feature = "locks_api_v1",
+        feature = "metadata_api_v1",
+        feature = "restrict_api_v1"
+    ))]
+    pub(crate) fn send(&self, call: ApiCall) -> Result<HttpResponse> {
+        self.send_with(call, |call| self.send_authorized(call))
+    }
+
+    /// Like [`ApiRequest::send`], but only ever authorized with the request's static token, such
+    /// as the API key of a request for an access token
+    #[cfg(feature = "identity_api_v1")]
+    pub(crate) fn send_with_token(&self, call: ApiCall) -> Result<HttpResponse> {
+        let token = self.static_token()?;
+        self.send_with(call, |call| self.dispatch(call.bearer_auth(token.expose())))
+    }
+
+    // Makes every attempt at sending the call with `send_attempt`
+    fn send_with(
+        &self,
+        call: ApiCall,
+        send_attempt: impl Fn(ApiCall) -> Result<HttpResponse>,
+    ) -> Result<HttpResponse> {
+        let span = CallSpan::new(&call);
+        let start = Instant::now();
+        let mut attempt = 1;
+        loop {
+            if let Some(limiter) = &self.limiter {
+                limiter.acquire();
+            }
+            let res = span.attempt(attempt, || send_attempt(call.clone()));
+            self.observe(&res);
+            match self.retry.delay(&call, attempt, &res) {
+                Some(delay) => {
+                    span.retry(attempt, delay);
+                    std::thread::sleep(delay);
+                }
+                None => {
+                    span.finish(attempt, &res);
+                    self.notify(&call, attempt, start, &res);
+                    return res?.error_for_status();
+                }
+            }
+            attempt += 1;
+        }
+    }
+
+    /// The async variant of [`ApiRequest::send`]
+    #[cfg(all(
+        feature = "async",
+        any(
+            feature = "compute_api_v2",
+            feature = "locks_api_v1",
+            feature = "metadata_api_v1",
+            feature = "restrict_api_v1"
+        )
+    ))]
+    pub(crate) async fn send_async(&self, call: ApiCall) -> Result<HttpResponse> {
+        self.send_with_async(call, |call| self.send_authorized_async(call))
+            .await
+    }
+
+    /// The async variant of [`ApiRequest::send_with_token`].
+    ///
+    /// Unlike [`ApiRequest::send_async`] the returned future does not depend on [`Credentials`],
+    /// which is what allows `Credentials` to fetch tokens through it.
+    #[cfg(all(feature = "async", feature = "identity_api_v1"))]
+    pub(crate) async fn send_with_token_async(&self, call: ApiCall) -> Result<HttpResponse> {
+        let token = self.static_token()?;
+        self.send_with_async(call, |call| self.dispatch_async(call.bearer_auth(token.expose())))
+            .await
+    }
+
+    #[cfg(feature = "async")]
+    async fn send_with_async<F: Future<Output = Result<HttpResponse>>>(
+        &self,
+        call: ApiCall,
+        send_attempt: impl Fn(ApiCall) -> F,
+    ) -> Result<HttpResponse> {
+        let span = CallSpan::new(&call);
+        let start = Instant::now();
+        let mut attempt = 1;
+        loop {
+            if let Some(limiter) = &self.limiter {
+                limiter.acquire_async().await;
+            }
+            let res = span
+                .attempt_async(attempt, send_attempt(call.clone()))
+                .await;
+            self.observe(&res);
+            match self.retry.delay(&call, attempt, &res) {
+                Some(delay) => {
+                    span.retry(attempt, delay);
+                    tokio::time::sleep(delay).await;
+                }
+                None => {
+                    span.finish(attempt, &res);
+                    self.notify(&call, attempt, start, &res);
+                    return res?.error_for_status();
+                }
+            }
+            attempt += 1;
+        }
+    }
+
+    // The static token the request is authorized with
+    #[cfg(feature = "identity_api_v1")]
+    fn static_token(&self) -> Result<&Secret> {
+        match &self.auth {
+            Auth::Token(token) => Ok(token),
+            Auth::Credentials(_) => Err(SeaplaneError::MissingRequestAuthToken),
+        }
+    }
+
+    /// The rate limit reported by the server in the most recent response, if any
+    #[cfg(any(
+        feature = "compute_api_v2",
+        feature = "locks_api_v1",
+        feature = "metadata_api_v1",
+        feature = "restrict_api_v1"
+    ))]
+    pub(crate) fn rate_limit(&self) -> Option<RateLimit> {
+        *self.rate_limit.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Notifies the observer, if any, of the final outcome of the call
+    fn notify(&self, call: &ApiCall, attempts: u32, start: Instant, res: &Result<HttpResponse>) {
+        if let Some(observer) = &self.observer {
+            observer.on_call(&CallEvent::new(
+                self.service,
+                call.operation,
+                call.method.clone(),
+                attempts,
+                start.elapsed(),
+                res,
+            ));
+        }
+    }
+
+    // Records the rate limit reported in the response to an attempt
+    fn observe(&self, res: &Result<HttpResponse>) {
+        let Some(limit) = res.as_ref().ok().and_then(HttpResponse::rate_limit) else {
+            return;
+        };
+        if let Some(limiter) = &self.limiter {
+            limiter.observe(&limit);
+        }
+        *self.rate_limit.lock().unwrap_or_else(|e| e.into_inner()) = Some(limit);
+    }
+
+    // Makes a single attempt at sending the call.
+    //
+    // When authorized with `Credentials` a call rejected with HTTP 401 UNAUTHORIZED is sent once
+    // more with a freshly fetched token.
+    #[cfg(any(
+        feature = "compute_api_v2",
+        feature = "locks_api_v1",
+        feature = "metadata_api_v1",
+        feature = "restrict_api_v1"
+    ))]
+    fn send_authorized(&self, call: ApiCall) -> Result<HttpResponse> {
+        match &self.auth {
+            Auth::Token(token) => self.dispatch(call.bearer_auth(token.expose())),
+            #[cfg(feature = "identity_api_v1")]
+            Auth::Credentials(creds) => {
+                let token = creds.access_token()?;
+                let resp = self.dispatch(call.clone().bearer_auth(&token.token))?;
+                if resp.status != reqwest::StatusCode::UNAUTHORIZED {
+                    return Ok(resp);
+                }
+                creds.invalidate(&token);
+                let token = creds.access_token()?;
+                self.dispatch(call.bearer_auth(token.token))
+            }
+        }
+    }
+
+    // Sends the call through the request's transport
+    fn dispatch(&self, call: ApiCall) -> Result<HttpResponse> {
+        self.transport.send(call.into_request()?)
+    }
+
+    #[cfg(feature = "async")]
+    async fn dispatch_async(&self, call: ApiCall) -> Result<HttpResponse> {
+        self.transport.send_async(call.into_request()?).await
+    }
+
+    #[cfg(all(
+        feature = "async",
+        any(
+            feature = "compute_api_v2",
+            feature = "locks_api_v1",
+            feature = "metadata_api_v1",
+            feature = "restrict_api_v1"
+        )
+    ))]
+    async fn send_authorized_async(&self, call: ApiCall) -> Result<HttpResponse> {
+        match &self.auth {
+            Auth::Token(token) => self.dispatch_async(call.bearer_auth(token.expose())).await,
+            #[cfg(feature = "identity_api_v1")]
+            Auth::Credentials(creds) => {
+                let token = creds.access_token_async().await?;
+                let resp = self
+                    .dispatch_async(call.clone().bearer_auth(&token.token))
+                    .await?;
+                if resp.status != reqwest::StatusCode::UNAUTHORIZED {
+                    return Ok(resp);
+                }
+                creds.invalidate(&token);
+                let token = creds.access_token_async().await?;
+                self.dispatch_async(call.bearer_auth(token.token)).await
+            }
+        }
+    }
+}
+
+/// How a request is authorized
+#[derive(Debug, Clone)]
+pub(crate) enum Auth {
+    /// A static Bearer token
+    Token(Secret),
+    /// A Bearer token provided (and refreshed) by [`Credentials`]
+    #[cfg(feature = "identity_api_v1")]
+    Credentials(Credentials),
+}
+
+/// A credential such as a token or API key, which is redacted from `Debug` output so that it does
+/// not end up in logs
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct Secret(String);
+
+impl Secret {
+    pub(crate) fn new<S: Into<String>>(secret: S) -> Self { Self(secret.into()) }
+
+    /// The actual value of the secret
+    pub(crate) fn expose(&self) -> &str { &self.0 }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str("<redacted>") }
+}
+
+/// A description of a single HTTP call to an endpoint, independent of whether it will be sent
+/// using the blocking or async client
+#[derive(Debug, Clone)]
+pub(crate) struct ApiCall {
+    pub(crate) method: Method,
+    pub(crate) url: Url,
+    pub(crate) headers: HeaderMap,
+    pub(crate) body: Option<Vec<u8>>,
+    pub(crate) token: Option<Secret>,
+    // Whether the call may safely be retried
+    pub(crate) idempotent: bool,
+    // The name of the operation making the call, as reported to observers
+    pub(crate) operation: &'static str,
+}
+
+impl ApiCall {
+    /// Create a new call with the default `Content-Type: application/json` header.
+    ///
+    /// Calls using `GET`, `PUT` or `DELETE` are considered idempotent.
+    pub(crate) fn new(method: Method, url: Url) -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        let idempotent = matches!(method, Method::GET | Method::PUT | Method::DELETE);
+        Self { method, url, headers, body: None, token: None, idempotent, operation: "unknown" }
+    }
+
+    #[cfg(any(
+        feature = "compute_api_v2",
+        feature = "locks_api_v1",
+        feature = "metadata_api_v1",
+        feature = "restrict_api_v1"
+    ))]
+    pub(crate) fn get(url: Url) -> Self { Self::new(Method::GET, url) }
+
+    #[cfg(any(feature = "compute_api_v2", feature = "identity_api_v1", feature = "locks_api_v1"))]
+    pub(crate) fn post(url: Url) -> Self { Self::new(Method::POST, url) }
+
+    #[cfg(any(
+        feature = "compute_api_v2",
+        feature = "metadata_api_v1",
+        feature = "restrict_api_v1"
+    ))]
+    pub(crate) fn put(url: Url) -> Self { Self::new(Method::PUT, url) }
+
+    #[cfg(feature = "locks_api_v1")]
+    pub(crate) fn patch(url: Url) -> Self { Self::new(Method::PATCH, url) }
+
+    #[cfg(any(
+        feature = "compute_api_v2",
+        feature = "locks_api_v1",
+        feature = "metadata_api_v1",
+        feature = "restrict_api_v1"
+    ))]
+    pub(crate) fn delete(url: Url) -> Self { Self::new(Method::DELETE, url) }
+
+    /// Names the operation making the call, such as `get_value`
+    pub(crate) fn operation(mut self, operation: &'static str) -> Self {
+        self.operation = operation;
+        self
+    }
+
+    /// Sets (or replaces) a header
+    #[cfg(any(
+        feature = "compute_api_v2",
+        feature = "identity_api_v1",
+        feature = "metadata_api_v1"
+    ))]
+    pub(crate) fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Sets the `Idempotency-Key` header, with which the server performs the call at most once no
+    /// matter how many times it is sent, and therefore allows the call to be retried
+    #[cfg(feature = "compute_api_v2")]
+    pub(crate) fn idempotency_key(mut self, key: &str) -> Result<Self> {
+        self.headers
+            .insert(IDEMPOTENCY_KEY, HeaderValue::try_from(key)?);
+        self.idempotent = true;
+        Ok(self)
+    }
+
+    /// Sets the token used in the `Authorization: Bearer` header
+    pub(crate) fn bearer_auth(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(Secret::new(token));
+        self
+    }
+
+    /// Sets the raw body of the request
+    #[cfg(any(
+        feature = "compute_api_v2",
+        feature = "metadata_api_v1",
+        feature = "restrict_api_v1"
+    ))]
+    pub(crate) fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Sets the body of the request to the JSON serialization of `body`
+    #[cfg(any(feature = "compute_api_v2", feature = "restrict_api_v1"))]
+    pub(crate) fn json<B: Serialize + ?Sized>(self, body: &B) -> Result<Self> {
+        Ok(self.body(serde_json::to_vec(body)?))
+    }
+
+    /// Converts the call into the request handed to a [`Transport`], adding the (sensitive)
+    /// `Authorization` header
+    pub(crate) fn into_request(self) -> Result<HttpRequest> {
+        let mut headers = self.headers;
+        if let Some(token) = self.token {
+            let mut value = HeaderValue::try_from(format!("Bearer {}", token.expose()))?;
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+        Ok(HttpRequest::new(self.method, self.url, headers, self.body))
+    }
+}
+
+#[cfg(test)]
+mod endpoint_url_tests {
+    use super::*;
+
+    #[test]
+    fn default_url() {
+        let url = endpoint_url(None, "SEAPLANE_TEST_UNSET_URL", "https://a.example/", "v1/config/")
+            .unwrap();
+        assert_eq!(url.as_str(), "https://a.example/v1/config/");
+    }
+
+    #[test]
+    fn explicit_url_is_a_directory() {
+        for api_url in ["https://b.example/api", "https://b.example/api/"] {
+            let url = endpoint_url(
+                Some(api_url),
+                "SEAPLANE_TEST_UNSET_URL",
+                "https://a.example/",
+                "v1/config/",
+            )
+            .unwrap();
+            assert_eq!(url.as_str(), "https://b.example/api/v1/config/");
+        }
+    }
+
+    #[test]
+    fn env_url() {
+        std::env::set_var("SEAPLANE_TEST_ENDPOINT_URL", "https://c.example/");
+        let url =
+            endpoint_url(None, "SEAPLANE_TEST_ENDPOINT_URL", "https://a.example/", "v1/token")
+                .unwrap();
+        assert_eq!(url.as_str(), "https://c.example/v1/token");
+
+        // An explicit URL takes precedence over the environment
+        let url = endpoint_url(
+            Some("https://b.example/"),
+            "SEAPLANE_TEST_ENDPOINT_URL",
+            "https://a.example/",
+            "v1/token",
+        )
+        .unwrap();
+        assert_eq!(url.as_str(), "https://b.example/v1/token");
+    }
+
+    #[test]
+    fn invalid_url() {
+        assert!(endpoint_url(Some("not a url"), "SEAPLANE_TEST_UNSET_URL", "", "v1").is_err());
+    }
+}
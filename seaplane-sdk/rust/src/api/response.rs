@@ -0,0 +1,146 @@
+//! The metadata of responses from the APIs, alongside their parsed bodies
+
+use std::time::SystemTime;
+
+use reqwest::{
+    header::{HeaderMap, DATE, ETAG},
+    StatusCode,
+};
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1"
+))]
+use serde::de::DeserializeOwned;
+
+use crate::api::{error::request_id, RateLimit};
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1"
+))]
+use crate::{api::HttpResponse, error::Result};
+
+/// The parsed body of a successful response, along with its status code and headers.
+///
+/// Returned by the `*_with_response` variants of endpoint methods, for when more than the body is
+/// needed, such as the request ID to include in a support ticket or the `ETag` for caching.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use seaplane::api::metadata::v1::MetadataRequest;
+/// let req = MetadataRequest::builder()
+///     .token("abc123")
+///     .encoded_key("bW9ieQo")
+///     .build()
+///     .unwrap();
+///
+/// let resp = req.get_value_with_response().unwrap();
+/// println!("request {:?} returned {}", resp.request_id(), resp.status);
+/// let value = resp.into_body();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Response<T> {
+    /// The status code of the response
+    pub status: StatusCode,
+    /// All headers of the response
+    pub headers: HeaderMap,
+    /// The parsed body of the response
+    pub body: T,
+}
+
+impl<T> Response<T> {
+    /// Create a new response
+    pub fn new(status: StatusCode, headers: HeaderMap, body: T) -> Self {
+        Self { status, headers, body }
+    }
+
+    /// The ID the server assigned to the request, from the `X-Request-ID` header (or its
+    /// `Request-ID` or `X-Correlation-ID` equivalents)
+    pub fn request_id(&self) -> Option<&str> { request_id(&self.headers) }
+
+    /// The value of the `ETag` header, including any quotes and `W/` prefix
+    pub fn etag(&self) -> Option<&str> { self.header(ETAG.as_str()) }
+
+    /// The time the response was generated according to the `Date` header
+    pub fn date(&self) -> Option<SystemTime> {
+        httpdate::parse_http_date(self.header(DATE.as_str())?).ok()
+    }
+
+    /// The rate limit reported in the headers, if any
+    pub fn rate_limit(&self) -> Option<RateLimit> { RateLimit::from_headers(&self.headers) }
+
+    /// Discards the metadata, returning only the body
+    pub fn into_body(self) -> T { self.body }
+
+    /// Maps the body while keeping the metadata
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Response<U> {
+        Response { status: self.status, headers: self.headers, body: f(self.body) }
+    }
+
+    // The value of the header `name`, if it is present and valid UTF-8
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+}
+
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1"
+))]
+impl Response<()> {
+    /// Keeps the metadata of the response, discarding its body
+    pub(crate) fn empty(resp: HttpResponse) -> Self { Self::new(resp.status, resp.headers, ()) }
+
+    /// Keeps the metadata of the response, deserializing its body as JSON
+    pub(crate) fn json<T: DeserializeOwned>(resp: HttpResponse) -> Result<Response<T>> {
+        let body = resp.json()?;
+        Ok(Response::new(resp.status, resp.headers, body))
+    }
+}
+
+#[cfg(test)]
+mod response_tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    fn response(headers: &[(&'static str, &'static str)]) -> Response<()> {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.insert(*name, HeaderValue::from_static(value));
+        }
+        Response::new(StatusCode::OK, map, ())
+    }
+
+    #[test]
+    fn request_id_headers() {
+        assert_eq!(response(&[]).request_id(), None);
+        assert_eq!(response(&[("request-id", "abc")]).request_id(), Some("abc"));
+        let resp = response(&[("x-correlation-id", "def"), ("x-request-id", "abc")]);
+        assert_eq!(resp.request_id(), Some("abc"));
+    }
+
+    #[test]
+    fn etag_and_date() {
+        let resp = response(&[("etag", "W/\"123\""), ("date", "Sun, 06 Nov 1994 08:49:37 GMT")]);
+        assert_eq!(resp.etag(), Some("W/\"123\""));
+        assert_eq!(resp.date(), Some(UNIX_EPOCH + Duration::from_secs(784111777)));
+        assert_eq!(response(&[("date", "yesterday")]).date(), None);
+    }
+
+    #[test]
+    fn map_keeps_metadata() {
+        let resp = response(&[("x-request-id", "abc")]).map(|()| 42);
+        assert_eq!(resp.body, 42);
+        assert_eq!(resp.request_id(), Some("abc"));
+    }
+}
@@ -4,3 +4,8 @@ pub mod v1;
 
 /// The base URL for our Restrict API endpoints
 pub static RESTRICT_API_URL: &str = "https://metadata.cplane.cloud/";
+
+/// The environment variable which, when set, overrides [`RESTRICT_API_URL`]. The Restrict API is
+/// served by the Metadata service, so this is the same variable as
+/// [`METADATA_API_URL_ENV`](crate::api::metadata::METADATA_API_URL_ENV).
+pub static RESTRICT_API_URL_ENV: &str = "SEAPLANE_METADATA_URL";
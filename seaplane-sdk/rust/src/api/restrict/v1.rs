@@ -1,20 +1,19 @@
 //! The `/restrict` endpoint APIs which allows working with [`Restriction`]s
 pub mod models;
 
-use std::str::FromStr;
+use std::{str::FromStr, sync::Arc};
 
-use reqwest::{
-    header::{self, CONTENT_TYPE},
-    Url,
-};
+use reqwest::Url;
 
 pub use self::models::*;
+#[cfg(feature = "identity_api_v1")]
+use crate::api::identity::v1::Credentials;
 use crate::{
     api::{
-        map_api_error,
-        restrict::{error::RestrictError, RESTRICT_API_URL},
+        restrict::{error::RestrictError, RESTRICT_API_URL, RESTRICT_API_URL_ENV},
         shared::v1::RangeQueryContext,
-        ApiRequest, RequestBuilder,
+        ApiCall, ApiRequest, HttpOptions, Observer, RateLimit, RateLimiter, RequestBuilder,
+        Response, RetryPolicy, Service, Transport,
     },
     error::Result,
 };
@@ -25,7 +24,7 @@ static RESTRICT_API_BASE_PATH: &str = "v1/restrict/";
 /// request against the `/restrict` APIs
 #[derive(Debug)]
 pub struct RestrictRequestBuilder {
-    builder: RequestBuilder<RequestTarget>,
+    pub(crate) builder: RequestBuilder<RequestTarget>,
 }
 
 impl From<RequestBuilder<RequestTarget>> for RestrictRequestBuilder {
@@ -38,17 +37,36 @@ impl Default for RestrictRequestBuilder {
 
 impl RestrictRequestBuilder {
     /// Create a new RestrictRequestBuilder
-    pub fn new() -> Self { RequestBuilder::new(RESTRICT_API_URL, RESTRICT_API_BASE_PATH).into() }
+    pub fn new() -> Self {
+        RequestBuilder::new(
+            Service::Restrict,
+            RESTRICT_API_URL,
+            RESTRICT_API_URL_ENV,
+            RESTRICT_API_BASE_PATH,
+        )
+        .into()
+    }
 
     /// Build a RestrictRequest from the given parameters
     pub fn build(self) -> Result<RestrictRequest> { Ok(self.builder.build()?.into()) }
 
     /// Set the token used in Bearer Authorization
     ///
-    /// **NOTE:** This is required for all endpoints
+    /// **NOTE:** This (or [`RestrictRequestBuilder::credentials`]) is required for all endpoints
     #[must_use]
     pub fn token<U: Into<String>>(self, token: U) -> Self { self.builder.token(token).into() }
 
+    /// Set the [`Credentials`] which provide the token used in Bearer Authorization, fetching and
+    /// refreshing it as required
+    ///
+    /// **NOTE:** This (or [`RestrictRequestBuilder::token`]) is required for all endpoints
+    #[cfg(feature = "identity_api_v1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "identity_api_v1")))]
+    #[must_use]
+    pub fn credentials(self, credentials: Credentials) -> Self {
+        self.builder.credentials(credentials).into()
+    }
+
     /// Allow non-HTTPS endpoints for this request (default: `false`)
     #[cfg(any(feature = "allow_insecure_urls", feature = "danger_zone"))]
     #[cfg_attr(docsrs, doc(cfg(any(feature = "allow_insecure_urls", feature = "danger_zone"))))]
@@ -61,6 +79,49 @@ impl RestrictRequestBuilder {
         self.builder.allow_invalid_certs(yes).into()
     }
 
+    /// Set the [`HttpOptions`] such as timeouts, proxy and TLS certificates used for the
+    /// connection (default: [`HttpOptions::default`])
+    #[must_use]
+    pub fn http_options(self, options: HttpOptions) -> Self {
+        self.builder.http_options(options).into()
+    }
+
+    /// Set the [`RetryPolicy`] used to retry idempotent requests which failed for transient
+    /// reasons (default: [`RetryPolicy::default`])
+    #[must_use]
+    pub fn retry_policy(self, policy: RetryPolicy) -> Self {
+        self.builder.retry_policy(policy).into()
+    }
+
+    /// Throttle requests on the client side using the [`RateLimiter`], which may be shared with
+    /// other requests (default: no client-side limit)
+    #[must_use]
+    pub fn rate_limiter(self, limiter: RateLimiter) -> Self {
+        self.builder.rate_limiter(limiter).into()
+    }
+
+    /// Notify the [`Observer`] of the outcome of every call, such as to record metrics (default: no
+    /// observer)
+    #[must_use]
+    pub fn observer<O: Observer + 'static>(self, observer: O) -> Self {
+        self.builder.observer(observer).into()
+    }
+
+    /// Send requests through the [`Transport`] rather than an HTTP client constructed from this
+    /// builder's settings, in which case those settings (such as [`HttpOptions`]) are ignored
+    #[must_use]
+    pub fn transport<T: Transport + 'static>(self, transport: T) -> Self {
+        self.builder.transport(Arc::new(transport)).into()
+    }
+
+    /// Set the base URL of the Restrict API, such as `https://metadata.example.com/`.
+    ///
+    /// Defaults to the value of the [`RESTRICT_API_URL_ENV`] environment variable if set, and
+    /// otherwise to [`RESTRICT_API_URL`]. The URL is parsed when the request is built, returning an
+    /// error if it is invalid.
+    #[must_use]
+    pub fn api_url<U: Into<String>>(self, url: U) -> Self { self.builder.api_url(url).into() }
+
     // Used in testing and development to manually set the URL
     #[doc(hidden)]
     pub fn base_url<U: AsRef<str>>(self, url: U) -> Self { self.builder.base_url(url).into() }
@@ -119,6 +180,9 @@ impl RestrictRequest {
     /// Create a new request builder
     pub fn builder() -> RestrictRequestBuilder { RestrictRequestBuilder::new() }
 
+    /// The rate limit reported by the server in the most recent response to this request, if any
+    pub fn rate_limit(&self) -> Option<RateLimit> { self.request.rate_limit() }
+
     // Internal method creating the URL for single key endpoints
     fn single_url(&self) -> Result<Url> {
         match &self.request.target {
@@ -187,16 +251,39 @@ impl RestrictRequest {
     /// dbg!(resp);
     /// ```
     pub fn get_restriction(&self) -> Result<Restriction> {
+        self.get_restriction_with_response()
+            .map(Response::into_body)
+    }
+
+    /// The async variant of [`RestrictRequest::get_restriction`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn get_restriction_async(&self) -> Result<Restriction> {
+        self.get_restriction_with_response_async()
+            .await
+            .map(Response::into_body)
+    }
+
+    /// Like [`RestrictRequest::get_restriction`], but also returns the status and headers of the
+    /// response
+    pub fn get_restriction_with_response(&self) -> Result<Response<Restriction>> {
+        let url = self.single_url()?;
+        let resp = self
+            .request
+            .send(ApiCall::get(url).operation("get_restriction"))?;
+        Response::json(resp)
+    }
+
+    /// The async variant of [`RestrictRequest::get_restriction_with_response`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn get_restriction_with_response_async(&self) -> Result<Response<Restriction>> {
         let url = self.single_url()?;
         let resp = self
             .request
-            .client
-            .get(url)
-            .bearer_auth(&self.request.token)
-            .send()?;
-        map_api_error(resp)?
-            .json::<Restriction>()
-            .map_err(Into::into)
+            .send_async(ApiCall::get(url).operation("get_restriction"))
+            .await?;
+        Response::json(resp)
     }
 
     /// Returns a single page of restrictions, starting from `from_api` and
@@ -284,37 +371,21 @@ impl RestrictRequest {
 
     /// ```
     pub fn get_page(&self) -> Result<RestrictionRange> {
-        match &self.request.target {
-            None | Some(RequestTarget::Single { .. }) => {
-                Err(RestrictError::IncorrectRestrictRequestTarget)?
-            }
-            Some(RequestTarget::ApiRange { .. }) => {
-                let url = self.range_url()?;
-
-                let resp = self
-                    .request
-                    .client
-                    .get(url)
-                    .bearer_auth(&self.request.token)
-                    .send()?;
-                map_api_error(resp)?
-                    .json::<RestrictionRange>()
-                    .map_err(Into::into)
-            }
-            Some(RequestTarget::AllRange { .. }) => {
-                let url = self.range_url()?;
-
-                let resp = self
-                    .request
-                    .client
-                    .get(url)
-                    .bearer_auth(&self.request.token)
-                    .send()?;
-                map_api_error(resp)?
-                    .json::<RestrictionRange>()
-                    .map_err(Into::into)
-            }
-        }
+        let url = self.range_url()?;
+        self.request
+            .send(ApiCall::get(url).operation("get_page"))?
+            .json()
+    }
+
+    /// The async variant of [`RestrictRequest::get_page`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn get_page_async(&self) -> Result<RestrictionRange> {
+        let url = self.range_url()?;
+        self.request
+            .send_async(ApiCall::get(url).operation("get_page"))
+            .await?
+            .json()
     }
 
     /// Returns all restrictions within for a tenant or API.
@@ -372,29 +443,57 @@ impl RestrictRequest {
         loop {
             let mut rr = self.get_page()?;
             pages.append(&mut rr.restrictions);
-            if let Some(next_key) = rr.next_key {
-                match &mut self.request.target {
-                    None | Some(RequestTarget::Single { .. }) => {
-                        Err(RestrictError::IncorrectRestrictRequestTarget)?
-                    }
-                    Some(RequestTarget::ApiRange { api: _, context }) => {
-                        context.set_from(next_key);
-                    }
-                    Some(RequestTarget::AllRange { from_api: _, context }) => {
-                        context.set_from(next_key);
-                        self.request.target = Some(RequestTarget::AllRange {
-                            from_api: rr.next_api.map(|a| a.to_string()),
-                            context: context.to_owned(),
-                        });
-                    }
-                }
-            } else {
+            if !self.set_next_page(rr.next_api, rr.next_key)? {
+                break;
+            }
+        }
+        Ok(pages)
+    }
+
+    /// The async variant of [`RestrictRequest::get_all_pages`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn get_all_pages_async(&mut self) -> Result<Vec<Restriction>> {
+        let mut pages = Vec::new();
+        loop {
+            let mut rr = self.get_page_async().await?;
+            pages.append(&mut rr.restrictions);
+            if !self.set_next_page(rr.next_api, rr.next_key)? {
                 break;
             }
         }
         Ok(pages)
     }
 
+    // Internal method moving the range target to start from `next_api` and `next_key`, returning
+    // `false` if there is no next page
+    fn set_next_page(
+        &mut self,
+        next_api: Option<Api>,
+        next_key: Option<RestrictedDirectory>,
+    ) -> Result<bool> {
+        if let Some(next_key) = next_key {
+            match &mut self.request.target {
+                None | Some(RequestTarget::Single { .. }) => {
+                    Err(RestrictError::IncorrectRestrictRequestTarget)?
+                }
+                Some(RequestTarget::ApiRange { api: _, context }) => {
+                    context.set_from(next_key);
+                }
+                Some(RequestTarget::AllRange { from_api: _, context }) => {
+                    context.set_from(next_key);
+                    self.request.target = Some(RequestTarget::AllRange {
+                        from_api: next_api.map(|a| a.to_string()),
+                        context: context.to_owned(),
+                    });
+                }
+            }
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
     /// Sets a restriction for an API-directory combination
     ///
     /// **NOTE:** This endpoint requires the `RequestTarget` be a `Single`.
@@ -424,19 +523,46 @@ impl RestrictRequest {
     /// dbg!(resp);
     /// ```
     pub fn set_restriction(&self, details: RestrictionDetails) -> Result<()> {
-        let url = self.single_url()?;
-        let resp = self
-            .request
-            .client
-            .put(url)
-            .bearer_auth(&self.request.token)
-            .header(CONTENT_TYPE, header::HeaderValue::from_static("application/json"))
-            .body(serde_json::to_string(&details)?)
-            .send()?;
-        map_api_error(resp)?
-            .text()
-            .map(|_| ()) // TODO: for now we drop the "success" message to control it ourselves
-            .map_err(Into::into)
+        self.set_restriction_with_response(details)
+            .map(Response::into_body)
+    }
+
+    /// The async variant of [`RestrictRequest::set_restriction`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn set_restriction_async(&self, details: RestrictionDetails) -> Result<()> {
+        self.set_restriction_with_response_async(details)
+            .await
+            .map(Response::into_body)
+    }
+
+    /// Like [`RestrictRequest::set_restriction`], but also returns the status and headers of the
+    /// response
+    pub fn set_restriction_with_response(
+        &self,
+        details: RestrictionDetails,
+    ) -> Result<Response<()>> {
+        let call = ApiCall::put(self.single_url()?)
+            .operation("set_restriction")
+            .json(&details)?;
+        // TODO: for now we drop the "success" message to control it ourselves
+        let resp = self.request.send(call)?;
+        Ok(Response::empty(resp))
+    }
+
+    /// The async variant of [`RestrictRequest::set_restriction_with_response`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn set_restriction_with_response_async(
+        &self,
+        details: RestrictionDetails,
+    ) -> Result<Response<()>> {
+        let call = ApiCall::put(self.single_url()?)
+            .operation("set_restriction")
+            .json(&details)?;
+        // TODO: for now we drop the "success" message to control it ourselves
+        let resp = self.request.send_async(call).await?;
+        Ok(Response::empty(resp))
     }
 
     /// Removes a restriction for an API-directory combination
@@ -458,16 +584,40 @@ impl RestrictRequest {
     /// dbg!(resp);
     /// ```
     pub fn delete_restriction(&self) -> Result<()> {
+        self.delete_restriction_with_response()
+            .map(Response::into_body)
+    }
+
+    /// The async variant of [`RestrictRequest::delete_restriction`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn delete_restriction_async(&self) -> Result<()> {
+        self.delete_restriction_with_response_async()
+            .await
+            .map(Response::into_body)
+    }
+
+    /// Like [`RestrictRequest::delete_restriction`], but also returns the status and headers of the
+    /// response
+    pub fn delete_restriction_with_response(&self) -> Result<Response<()>> {
+        let url = self.single_url()?;
+        // TODO: for now we drop the "success" message to control it ourselves
+        let resp = self
+            .request
+            .send(ApiCall::delete(url).operation("delete_restriction"))?;
+        Ok(Response::empty(resp))
+    }
+
+    /// The async variant of [`RestrictRequest::delete_restriction_with_response`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn delete_restriction_with_response_async(&self) -> Result<Response<()>> {
         let url = self.single_url()?;
+        // TODO: for now we drop the "success" message to control it ourselves
         let resp = self
             .request
-            .client
-            .delete(url)
-            .bearer_auth(&self.request.token)
-            .send()?;
-        map_api_error(resp)?
-            .text()
-            .map(|_| ()) // TODO: for now we drop the "success" message to control it ourselves
-            .map_err(Into::into)
+            .send_async(ApiCall::delete(url).operation("delete_restriction"))
+            .await?;
+        Ok(Response::empty(resp))
     }
 }
@@ -0,0 +1,229 @@
+//! Retrying requests which failed for transient reasons
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::{Duration, SystemTime},
+};
+
+use reqwest::header::RETRY_AFTER;
+
+use crate::{
+    api::{ApiCall, HttpResponse, RateLimit},
+    error::{ErrorKind, Result},
+};
+
+/// The default maximum number of attempts (including the first) made for an idempotent request
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// The default delay before the first retry
+pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(200);
+
+/// The default upper bound on the delay between two attempts
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Controls how requests are retried after a transient failure.
+///
+/// A failure is transient when [`ErrorKind::is_retryable`], that is when the connection could not
+/// be established or timed out, or the server responded with HTTP 408, 429, 502, 503 or 504. Only
+/// idempotent operations (those using `GET`, `PUT` or `DELETE` such as
+/// [`MetadataRequest::get_value`] or [`MetadataRequest::put_value`]) are retried. Operations which
+/// are not idempotent, such as [`LocksRequest::acquire`] or [`FormationsRequest::create`], are
+/// attempted only once, unless they are sent with an idempotency key (see
+/// [`FormationsRequestBuilder::idempotency_key`]).
+///
+/// The delay between attempts grows exponentially from the initial backoff up to the maximum
+/// backoff, and by default is randomized (between half and all of the computed delay) so that
+/// many clients failing at once do not retry in lockstep. When the server responds with a
+/// `Retry-After` header (or reports its [`RateLimit`] as exhausted) the delay until then is used
+/// instead, unless it exceeds the maximum backoff in which case the request is not retried at all.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use seaplane::api::{metadata::v1::MetadataRequest, RetryPolicy};
+/// let req = MetadataRequest::builder()
+///     .token("abc123")
+///     .encoded_key("Zm9v")
+///     .retry_policy(
+///         RetryPolicy::new()
+///             .max_attempts(5)
+///             .initial_backoff(Duration::from_secs(1)),
+///     )
+///     .build()
+///     .unwrap();
+/// ```
+///
+/// [`MetadataRequest::get_value`]: crate::api::metadata::v1::MetadataRequest::get_value
+/// [`MetadataRequest::put_value`]: crate::api::metadata::v1::MetadataRequest::put_value
+/// [`LocksRequest::acquire`]: crate::api::locks::v1::LocksRequest::acquire
+/// [`FormationsRequest::create`]: crate::api::compute::v2::FormationsRequest::create
+/// [`FormationsRequestBuilder::idempotency_key`]: crate::api::compute::v2::FormationsRequestBuilder::idempotency_key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: u32,
+    jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            multiplier: 2,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Create a new `Default` policy
+    pub fn new() -> Self { Self::default() }
+
+    /// A policy which never retries
+    pub fn none() -> Self { Self::default().max_attempts(1) }
+
+    /// The maximum number of attempts, including the first (default: [`DEFAULT_MAX_ATTEMPTS`]).
+    /// A value of `0` is treated as `1`.
+    #[must_use]
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// The delay before the first retry (default: [`DEFAULT_INITIAL_BACKOFF`])
+    #[must_use]
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// The upper bound on the delay between two attempts (default: [`DEFAULT_MAX_BACKOFF`])
+    #[must_use]
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// The factor by which the delay grows after each attempt (default: `2`)
+    #[must_use]
+    pub fn multiplier(mut self, multiplier: u32) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Whether to randomize the delay between attempts (default: `true`)
+    #[must_use]
+    pub fn jitter(mut self, yes: bool) -> Self {
+        self.jitter = yes;
+        self
+    }
+
+    /// Returns how long to wait before retrying `call` given the result of its `attempt`th
+    /// (starting at `1`) attempt, or `None` if it should not be retried
+    pub(crate) fn delay(
+        &self,
+        call: &ApiCall,
+        attempt: u32,
+        res: &Result<HttpResponse>,
+    ) -> Option<Duration> {
+        if !call.idempotent || attempt >= self.max_attempts {
+            return None;
+        }
+        match res {
+            Ok(resp) if ErrorKind::from_status(resp.status).is_retryable() => {
+                // Without a `Retry-After` wait for an exhausted rate limit to reset
+                let server_delay = resp.retry_after().or_else(|| {
+                    resp.rate_limit()
+                        .filter(RateLimit::is_exhausted)
+                        .and_then(|limit| limit.reset)
+                });
+                match server_delay {
+                    Some(delay) if delay > self.max_backoff => None,
+                    Some(delay) => Some(delay),
+                    None => Some(self.backoff(attempt)),
+                }
+            }
+            Err(e) if e.is_retryable() => Some(self.backoff(attempt)),
+            _ => None,
+        }
+    }
+
+    // The (possibly randomized) delay after the `attempt`th attempt
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.saturating_pow(attempt.saturating_sub(1));
+        let backoff = self
+            .initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |b| b.min(self.max_backoff));
+        if self.jitter {
+            backoff.mul_f64(0.5 + random_fraction() / 2.0)
+        } else {
+            backoff
+        }
+    }
+}
+
+/// Parses a `Retry-After` header value, which is either a number of seconds or an HTTP date
+pub(crate) fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    // A date in the past means "retry now"
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+impl HttpResponse {
+    /// The delay requested by the server in the `Retry-After` header, if any
+    pub(crate) fn retry_after(&self) -> Option<Duration> {
+        parse_retry_after(self.headers.get(RETRY_AFTER)?.to_str().ok()?)
+    }
+}
+
+// A random number in `[0, 1)`, which is plenty for jitter without requiring an RNG dependency
+// since each `RandomState` is randomly seeded
+fn random_fraction() -> f64 {
+    let n = RandomState::new().build_hasher().finish();
+    (n >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod retry_tests {
+    use super::*;
+
+    #[test]
+    fn backoff_is_exponential_and_capped() {
+        let policy = RetryPolicy::new()
+            .jitter(false)
+            .initial_backoff(Duration::from_secs(1))
+            .max_backoff(Duration::from_secs(5));
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(4), Duration::from_secs(5));
+        assert_eq!(policy.backoff(100), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn backoff_jitter() {
+        let policy = RetryPolicy::new().initial_backoff(Duration::from_secs(2));
+        for _ in 0..100 {
+            let backoff = policy.backoff(1);
+            assert!(backoff >= Duration::from_secs(1) && backoff <= Duration::from_secs(2));
+        }
+    }
+
+    #[test]
+    fn retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}
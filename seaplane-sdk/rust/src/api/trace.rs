@@ -0,0 +1,225 @@
+//! Optional `tracing` instrumentation of the calls made to the APIs.
+//!
+//! Without the `tracing` feature everything here compiles down to nothing.
+
+#[cfg(feature = "async")]
+use std::future::Future;
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+
+use crate::{
+    api::{ApiCall, HttpResponse},
+    error::Result,
+};
+
+/// A span covering every attempt made for a single endpoint call.
+///
+/// The span records the method and path (which includes any base64 encoded keys) of the call, and
+/// once finished the final status, the number of attempts and the total duration. Every attempt
+/// runs inside the span and is recorded as an event with its own status and duration. Headers,
+/// including the `Authorization` header carrying the bearer token or API key, are never recorded.
+pub(crate) struct CallSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "tracing")]
+    start: Instant,
+}
+
+impl CallSpan {
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn new(call: &ApiCall) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                "seaplane_api_call",
+                method = %call.method,
+                path = call.url.path(),
+                status = tracing::field::Empty,
+                attempts = tracing::field::Empty,
+                duration_ms = tracing::field::Empty,
+            ),
+            #[cfg(feature = "tracing")]
+            start: Instant::now(),
+        }
+    }
+
+    /// Makes a single attempt at the call with `send`, inside the span so that the events of the
+    /// transport (and of any token request it requires) are nested under the call, and records
+    /// the outcome and duration of the attempt
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn attempt(
+        &self,
+        attempt: u32,
+        send: impl FnOnce() -> Result<HttpResponse>,
+    ) -> Result<HttpResponse> {
+        #[cfg(feature = "tracing")]
+        {
+            let start = Instant::now();
+            let res = self.span.in_scope(send);
+            self.record_attempt(attempt, start, &res);
+            res
+        }
+        #[cfg(not(feature = "tracing"))]
+        send()
+    }
+
+    /// The async variant of [`CallSpan::attempt`]
+    #[cfg(feature = "async")]
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) async fn attempt_async(
+        &self,
+        attempt: u32,
+        send: impl Future<Output = Result<HttpResponse>>,
+    ) -> Result<HttpResponse> {
+        #[cfg(feature = "tracing")]
+        {
+            use tracing::Instrument;
+
+            let start = Instant::now();
+            let res = send.instrument(self.span.clone()).await;
+            self.record_attempt(attempt, start, &res);
+            res
+        }
+        #[cfg(not(feature = "tracing"))]
+        send.await
+    }
+
+    #[cfg(feature = "tracing")]
+    fn record_attempt(&self, attempt: u32, start: Instant, res: &Result<HttpResponse>) {
+        let duration_ms = start.elapsed().as_millis() as u64;
+        match res {
+            Ok(resp) => tracing::debug!(
+                parent: &self.span,
+                attempt,
+                status = resp.status.as_u16(),
+                duration_ms,
+                "attempt finished"
+            ),
+            Err(e) => {
+                tracing::debug!(parent: &self.span, attempt, error = %e, duration_ms, "attempt failed")
+            }
+        }
+    }
+
+    /// Records that the call will be retried after `delay`
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn retry(&self, attempt: u32, delay: std::time::Duration) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            parent: &self.span,
+            attempt,
+            delay_ms = delay.as_millis() as u64,
+            "retrying after transient failure"
+        );
+    }
+
+    /// Records the final outcome of the call after `attempts` attempts
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn finish(&self, attempts: u32, res: &Result<HttpResponse>) {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("attempts", attempts);
+            self.span
+                .record("duration_ms", self.start.elapsed().as_millis() as u64);
+            match res {
+                Ok(resp) => {
+                    self.span.record("status", resp.status.as_u16());
+                    if resp.status.is_success() {
+                        tracing::debug!(parent: &self.span, "call succeeded");
+                    } else {
+                        tracing::warn!(parent: &self.span, "call failed");
+                    }
+                }
+                Err(e) => tracing::warn!(parent: &self.span, error = %e, "call failed"),
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod call_span_tests {
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    };
+
+    use reqwest::{header::HeaderMap, StatusCode};
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+        Event, Metadata, Subscriber,
+    };
+
+    use super::*;
+
+    // The name of the span entered when an event was emitted, and the names of its fields
+    type Recorded = (Option<&'static str>, Vec<&'static str>);
+
+    // Records the events along with the name of the span entered when they were emitted
+    #[derive(Default)]
+    struct Recorder {
+        next_id: AtomicU64,
+        names: Mutex<Vec<&'static str>>,
+        entered: Mutex<Vec<u64>>,
+        events: Arc<Mutex<Vec<Recorded>>>,
+    }
+
+    struct FieldNames(Vec<&'static str>);
+
+    impl Visit for FieldNames {
+        fn record_debug(&mut self, field: &Field, _: &dyn std::fmt::Debug) {
+            self.0.push(field.name());
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool { true }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            self.names.lock().unwrap().push(span.metadata().name());
+            Id::from_u64(self.next_id.fetch_add(1, Ordering::SeqCst) + 1)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let entered = self.entered.lock().unwrap().last().copied();
+            let span = entered.map(|id| self.names.lock().unwrap()[id as usize - 1]);
+            let mut fields = FieldNames(Vec::new());
+            event.record(&mut fields);
+            self.events.lock().unwrap().push((span, fields.0));
+        }
+
+        fn enter(&self, span: &Id) { self.entered.lock().unwrap().push(span.into_u64()); }
+
+        fn exit(&self, _: &Id) { self.entered.lock().unwrap().pop(); }
+    }
+
+    #[test]
+    fn attempt_runs_inside_span() {
+        let recorder = Recorder::default();
+        let events = Arc::clone(&recorder.events);
+        tracing::subscriber::with_default(recorder, || {
+            let call = ApiCall::new(
+                reqwest::Method::GET,
+                "https://example.com/v1/config/".parse().unwrap(),
+            );
+            let span = CallSpan::new(&call);
+            let res = span.attempt(1, || {
+                tracing::info!("sending");
+                Ok(HttpResponse::new(StatusCode::OK, HeaderMap::new(), Vec::new()))
+            });
+            assert!(res.is_ok());
+        });
+
+        let events = events.lock().unwrap();
+        // The event of the transport is nested under the call
+        assert_eq!(events[0].0, Some("seaplane_api_call"));
+        assert!(events[0].1.contains(&"message"));
+        // The attempt is recorded along with its duration
+        assert!(events[1].1.contains(&"duration_ms"));
+        assert!(events[1].1.contains(&"status"));
+    }
+}
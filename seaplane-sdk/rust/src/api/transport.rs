@@ -0,0 +1,327 @@
+//! The transport which sends the HTTP requests made to the APIs
+
+use std::{fmt, sync::Arc};
+#[cfg(feature = "async")]
+use std::{future::Future, pin::Pin};
+
+use once_cell::sync::OnceCell;
+use reqwest::{blocking, header::HeaderMap, Method, StatusCode, Url};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    api::{status_error, HttpOptions, RateLimit, ResolvedHttpOptions},
+    error::Result,
+};
+
+/// The future returned by [`Transport::send_async`]
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub type TransportFuture<'a> = Pin<Box<dyn Future<Output = Result<HttpResponse>> + Send + 'a>>;
+
+/// Sends the HTTP requests made to the APIs.
+///
+/// By default requests are sent with [`HttpClient`], which uses `reqwest`. A different transport
+/// can be set on any request builder (or on a [`SeaplaneClient`]) to send requests through an
+/// in-memory fake in tests, record and replay them, or use an HTTP stack with its own middleware.
+/// When a transport is set, the client settings of the builder (such as [`HttpOptions`]) are
+/// ignored.
+///
+/// A transport only sends a single request: retries, rate limiting, refreshing tokens and mapping
+/// error statuses into a [`SeaplaneError`] all happen before or after calling it. A response with
+/// an error status must therefore be returned as an `Ok(HttpResponse)`, while a failure to get a
+/// response at all should be returned as a [`TransportError`].
+///
+/// # Examples
+///
+/// ```
+/// # use reqwest::{header::HeaderMap, StatusCode};
+/// # use seaplane::{api::{metadata::v1::MetadataRequest, HttpRequest, HttpResponse, Transport}, error::Result};
+/// #[derive(Debug)]
+/// struct AlwaysNotFound;
+///
+/// impl Transport for AlwaysNotFound {
+///     fn send(&self, req: HttpRequest) -> Result<HttpResponse> {
+///         let body = br#"{"title": "Not Found", "status": 404}"#.to_vec();
+///         Ok(HttpResponse::new(StatusCode::NOT_FOUND, HeaderMap::new(), body))
+///     }
+/// }
+///
+/// let req = MetadataRequest::builder()
+///     .token("abc123")
+///     .encoded_key("Zm9v")
+///     .transport(AlwaysNotFound)
+///     .build()
+///     .unwrap();
+/// assert!(req.get_value().is_err());
+/// ```
+///
+/// [`SeaplaneClient`]: crate::api::SeaplaneClient
+/// [`SeaplaneError`]: crate::error::SeaplaneError
+/// [`TransportError`]: crate::api::TransportError
+pub trait Transport: fmt::Debug + Send + Sync {
+    /// Sends the request, blocking the current thread until the entire response has been read
+    fn send(&self, req: HttpRequest) -> Result<HttpResponse>;
+
+    /// Sends the request from within an async runtime.
+    ///
+    /// The default implementation calls [`Transport::send`], which blocks the runtime's thread and
+    /// is therefore only suitable for transports which never wait, such as in-memory fakes.
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    fn send_async(&self, req: HttpRequest) -> TransportFuture<'_> {
+        Box::pin(std::future::ready(self.send(req)))
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    fn send(&self, req: HttpRequest) -> Result<HttpResponse> { (**self).send(req) }
+
+    #[cfg(feature = "async")]
+    fn send_async(&self, req: HttpRequest) -> TransportFuture<'_> { (**self).send_async(req) }
+}
+
+/// A single HTTP request to an endpoint, including the `Authorization` header.
+///
+/// The `Authorization` header is marked as sensitive, so it is omitted from `Debug` output.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap,
+    pub body: Option<Vec<u8>>,
+}
+
+impl HttpRequest {
+    /// Create a new request
+    pub fn new(method: Method, url: Url, headers: HeaderMap, body: Option<Vec<u8>>) -> Self {
+        Self { method, url, headers, body }
+    }
+}
+
+/// A response from an endpoint with the body already read in full
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    // The error for an error status, as found prior to reading the body
+    error: Option<reqwest::Error>,
+}
+
+impl HttpResponse {
+    /// Create a new response
+    pub fn new(status: StatusCode, headers: HeaderMap, body: Vec<u8>) -> Self {
+        Self { status, headers, body, error: None }
+    }
+
+    /// Maps an error status into an [`ApiError`](crate::api::ApiError) whose response is the RFC
+    /// 7807 body of the error, or an [`HttpError`](crate::api::HttpError) if the body is not
+    /// RFC 7807
+    pub(crate) fn error_for_status(self) -> Result<Self> {
+        if self.status.is_client_error() || self.status.is_server_error() {
+            return Err(status_error(self.status, self.headers, &self.body, self.error));
+        }
+        Ok(Self { error: None, ..self })
+    }
+
+    /// Deserializes the body as JSON
+    pub(crate) fn json<T: DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_slice(&self.body).map_err(Into::into)
+    }
+
+    /// The rate limit reported in the headers, if any
+    pub(crate) fn rate_limit(&self) -> Option<RateLimit> { RateLimit::from_headers(&self.headers) }
+
+    /// Returns the body as a UTF-8 string, replacing any invalid UTF-8
+    #[cfg(feature = "identity_api_v1")]
+    pub(crate) fn text(self) -> String { String::from_utf8_lossy(&self.body).into_owned() }
+}
+
+/// The settings used to construct the underlying HTTP clients
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ClientConfig {
+    // Allow non-HTTPS endpoints (phrasing is to allow deriving Default since default for bool is
+    // false)
+    #[cfg(any(feature = "allow_insecure_urls", feature = "danger_zone"))]
+    pub(crate) allow_http: bool,
+    #[cfg(any(feature = "allow_invalid_certs", feature = "danger_zone"))]
+    pub(crate) allow_invalid_certs: bool,
+    pub(crate) options: HttpOptions,
+}
+
+impl ClientConfig {
+    /// Whether the client must refuse non-HTTPS endpoints
+    fn https_only(&self) -> bool {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "api_tests")] {
+                false
+            } else if #[cfg(any(feature = "allow_insecure_urls", feature = "danger_zone"))] {
+                !self.allow_http
+            } else {
+                true
+            }
+        }
+    }
+}
+
+/// The default [`Transport`], which wraps the blocking and async `reqwest` HTTP clients.
+///
+/// The clients are only constructed on first use. This matters because a blocking client cannot
+/// be created or dropped from within an async runtime, so a request only ever used through its
+/// `*_async` methods must never construct one.
+///
+/// Cloning an `HttpClient` is cheap, and all clones share the same connection pools. This makes it
+/// a suitable base for a custom transport which adds middleware, such as extra headers, before
+/// sending requests on.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    inner: Arc<HttpClientInner>,
+}
+
+#[derive(Debug)]
+struct HttpClientInner {
+    config: ClientConfig,
+    options: ResolvedHttpOptions,
+    blocking: OnceCell<blocking::Client>,
+    #[cfg(feature = "async")]
+    non_blocking: OnceCell<reqwest::Client>,
+}
+
+// Applies the configuration to either a blocking or async `ClientBuilder`, which share method
+// names but not a trait
+macro_rules! configure_client_builder {
+    ($builder:expr, $inner:expr) => {{
+        let config = &$inner.config;
+        let options = &$inner.options;
+        let mut builder = $builder
+            .https_only(config.https_only())
+            .tls_built_in_root_certs(options.built_in_root_certificates);
+        #[cfg(any(feature = "allow_invalid_certs", feature = "danger_zone"))]
+        {
+            builder = builder.danger_accept_invalid_certs(config.allow_invalid_certs);
+        }
+        if let Some(timeout) = options.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = options.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if options.no_proxy {
+            builder = builder.no_proxy();
+        }
+        if let Some(proxy) = &options.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        for cert in &options.root_certificates {
+            builder = builder.add_root_certificate(cert.clone());
+        }
+        if let Some(identity) = &options.identity {
+            builder = builder.identity(identity.clone());
+        }
+        builder.build().map_err(Into::into)
+    }};
+}
+
+impl HttpClient {
+    /// Creates a new client, returning an error if any of the [`HttpOptions`] are invalid
+    pub fn new(options: HttpOptions) -> Result<Self> {
+        // The other fields only exist with some features
+        #[allow(clippy::needless_update)]
+        let config = ClientConfig { options, ..Default::default() };
+        Self::with_config(config)
+    }
+
+    /// Creates a new client from the settings of a request builder
+    pub(crate) fn with_config(config: ClientConfig) -> Result<Self> {
+        Ok(Self {
+            inner: Arc::new(HttpClientInner {
+                options: config.options.resolve()?,
+                config,
+                blocking: OnceCell::new(),
+                #[cfg(feature = "async")]
+                non_blocking: OnceCell::new(),
+            }),
+        })
+    }
+
+    fn blocking(&self) -> Result<&blocking::Client> {
+        self.inner
+            .blocking
+            .get_or_try_init(|| configure_client_builder!(blocking::Client::builder(), self.inner))
+    }
+
+    #[cfg(feature = "async")]
+    fn non_blocking(&self) -> Result<&reqwest::Client> {
+        self.inner
+            .non_blocking
+            .get_or_try_init(|| configure_client_builder!(reqwest::Client::builder(), self.inner))
+    }
+}
+
+impl Transport for HttpClient {
+    fn send(&self, req: HttpRequest) -> Result<HttpResponse> {
+        let mut builder = self
+            .blocking()?
+            .request(req.method, req.url)
+            .headers(req.headers);
+        if let Some(body) = req.body {
+            builder = builder.body(body);
+        }
+        let resp = builder.send()?;
+        let status = resp.status();
+        let headers = resp.headers().clone();
+        let error = resp.error_for_status_ref().err();
+        let body = resp.bytes()?.to_vec();
+
+        Ok(HttpResponse { status, headers, body, error })
+    }
+
+    #[cfg(feature = "async")]
+    fn send_async(&self, req: HttpRequest) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let mut builder = self
+                .non_blocking()?
+                .request(req.method, req.url)
+                .headers(req.headers);
+            if let Some(body) = req.body {
+                builder = builder.body(body);
+            }
+            let resp = builder.send().await?;
+            let status = resp.status();
+            let headers = resp.headers().clone();
+            let error = resp.error_for_status_ref().err();
+            let body = resp.bytes().await?.to_vec();
+
+            Ok(HttpResponse { status, headers, body, error })
+        })
+    }
+}
+
+#[cfg(test)]
+mod http_response_tests {
+    use super::*;
+    use crate::error::{ErrorKind, SeaplaneError};
+
+    #[test]
+    fn error_for_status_without_source() {
+        let resp = HttpResponse::new(StatusCode::OK, HeaderMap::new(), Vec::new());
+        assert!(resp.error_for_status().is_ok());
+
+        let body = br#"{"title": "Conflict", "status": 409}"#.to_vec();
+        let resp = HttpResponse::new(StatusCode::CONFLICT, HeaderMap::new(), body);
+        let err = resp.error_for_status().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Conflict);
+        match err {
+            SeaplaneError::ApiResponse(e) => {
+                assert_eq!(e.status, StatusCode::CONFLICT);
+                assert!(e.source.is_none());
+            }
+            e => panic!("expected an API error, got {e:?}"),
+        }
+
+        let resp = HttpResponse::new(StatusCode::BAD_GATEWAY, HeaderMap::new(), Vec::new());
+        assert!(matches!(resp.error_for_status(), Err(SeaplaneError::HttpResponse(_))));
+    }
+}
@@ -8,25 +8,32 @@
 //!
 //! All server-side errors should be represented as the [SeaplaneError::ApiResponse] variant which
 //! wraps the [ApiError] type that expects server-side error responses to follow [RFC
-//! 7807][rfc_7807]. There is a the minor exception of if the HTTP response could not be converted
-//! into a [ApiError] due to an unknown or unimplemented status, in which case you should see
-//! something like the [SeaplaneError::UnknownHttp] variant.
+//! 7807][rfc_7807]. When an error response does not follow RFC 7807 (for example an HTML page or
+//! an empty body from a load balancer) it is represented as the [SeaplaneError::HttpResponse]
+//! variant, which preserves the status code, headers and raw body of the response.
 //!
 //! Client-side errors are represented by the various enum variants. Errors specific to a
 //! particular servicer are grouped by a dedicated variant wrapping that service's client-side
 //! error type, such as [SeaplaneError::ComputeRequest] which wraps the client-side Compute service
 //! error type ([ComputeError]) and contains errors specific to Compute requests.
 //!
+//! Regardless of the variant, [SeaplaneError::kind] classifies an error as an [ErrorKind] (such as
+//! a conflict, a rate limit or a timeout) and [SeaplaneError::is_retryable] tells whether the same
+//! request may succeed if retried later.
+//!
 //! [rfc_7807]: https://www.rfc-editor.org/rfc/rfc7807
 
 use thiserror::Error as ThisError;
 
-use crate::api::ApiError;
 #[cfg(feature = "compute_api_v2")]
-use crate::api::{
-    compute::error::ComputeError, locks::error::LocksError, metadata::error::MetadataError,
-    restrict::error::RestrictError,
-};
+use crate::api::compute::error::ComputeError;
+#[cfg(feature = "locks_api_v1")]
+use crate::api::locks::error::LocksError;
+#[cfg(feature = "metadata_api_v1")]
+use crate::api::metadata::error::MetadataError;
+#[cfg(feature = "restrict_api_v1")]
+use crate::api::restrict::error::RestrictError;
+use crate::api::{ApiError, HttpError, RateLimit, TransportError};
 
 pub type Result<T> = std::result::Result<T, SeaplaneError>;
 
@@ -35,6 +42,10 @@ pub enum SeaplaneError {
     /// Server-side errors from all services
     #[error("{0}")]
     ApiResponse(#[from] ApiError),
+    /// Server-side errors whose response was not an RFC 7807 problem, such as an HTML page from a
+    /// load balancer
+    #[error("{0}")]
+    HttpResponse(#[from] HttpError),
     /// An unknown, or unimplemented HTTP response was found
     #[error("http error: {0}")]
     UnknownHttp(reqwest::Error),
@@ -46,6 +57,15 @@ pub enum SeaplaneError {
     MissingRequestAuthToken,
     #[error("invalid URL")]
     UrlParse(#[from] url::ParseError),
+    #[error("invalid header value")]
+    InvalidHeader(#[from] reqwest::header::InvalidHeaderValue),
+    /// A custom transport failed to send the request
+    #[error("{0}")]
+    Transport(#[from] TransportError),
+    /// A file, such as a cassette or manifest, could not be read or written
+    #[cfg(any(feature = "cassette", feature = "manifest"))]
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
     #[error("{0}")]
     Json(#[from] serde_json::error::Error),
     /// Client-side Compute Service Request Errors
@@ -66,6 +86,132 @@ pub enum SeaplaneError {
     RestrictRequest(#[from] RestrictError),
 }
 
+/// A coarse classification of a [`SeaplaneError`] (or [`ApiError`]) which is suitable for deciding
+/// how to handle it, such as whether to retry the request, prompt for new credentials or give up.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The server rejected the request as malformed or invalid (HTTP 400, 422 and other 4xx
+    /// statuses not covered by a more specific kind)
+    BadRequest,
+    /// The request was not authenticated or the token has expired (HTTP 401)
+    Unauthorized,
+    /// The request was authenticated but is not allowed (HTTP 403)
+    Forbidden,
+    /// The requested resource does not exist (HTTP 404)
+    NotFound,
+    /// The request conflicts with the current state of the resource (HTTP 409)
+    Conflict,
+    /// A precondition of the request, such as an `If-Match` header, did not hold (HTTP 412)
+    PreconditionFailed,
+    /// Too many requests have been made (HTTP 429)
+    RateLimited,
+    /// The service is temporarily unavailable (HTTP 502, 503 and 504)
+    Unavailable,
+    /// The service failed to handle the request (HTTP 500 and other 5xx statuses)
+    ServerError,
+    /// The request or the connection timed out (including HTTP 408)
+    Timeout,
+    /// A connection to the service could not be established, such as when the connection was
+    /// refused, the host could not be resolved, or the TLS handshake failed
+    Connect,
+    /// The request was invalid and was never sent, such as when a required parameter is missing
+    InvalidRequest,
+    /// The response could not be decoded
+    Decode,
+    /// Any other error
+    Other,
+}
+
+impl ErrorKind {
+    /// Classifies an HTTP error status
+    pub(crate) fn from_status(status: reqwest::StatusCode) -> Self {
+        use reqwest::StatusCode;
+
+        match status {
+            StatusCode::UNAUTHORIZED => ErrorKind::Unauthorized,
+            StatusCode::FORBIDDEN => ErrorKind::Forbidden,
+            StatusCode::NOT_FOUND => ErrorKind::NotFound,
+            StatusCode::CONFLICT => ErrorKind::Conflict,
+            StatusCode::PRECONDITION_FAILED => ErrorKind::PreconditionFailed,
+            StatusCode::REQUEST_TIMEOUT => ErrorKind::Timeout,
+            StatusCode::TOO_MANY_REQUESTS => ErrorKind::RateLimited,
+            StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT => ErrorKind::Unavailable,
+            s if s.is_client_error() => ErrorKind::BadRequest,
+            s if s.is_server_error() => ErrorKind::ServerError,
+            _ => ErrorKind::Other,
+        }
+    }
+
+    /// Whether an error of this kind is transient, meaning the same request may succeed if retried
+    /// later
+    pub fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            ErrorKind::RateLimited
+                | ErrorKind::Unavailable
+                | ErrorKind::Timeout
+                | ErrorKind::Connect
+        )
+    }
+}
+
+impl SeaplaneError {
+    /// Classifies the error, see [`ErrorKind`]
+    pub fn kind(&self) -> ErrorKind {
+        use SeaplaneError::*;
+
+        match self {
+            ApiResponse(e) => e.kind(),
+            HttpResponse(e) => e.kind(),
+            UnknownHttp(e) => {
+                if e.is_timeout() {
+                    ErrorKind::Timeout
+                } else if e.is_connect() {
+                    ErrorKind::Connect
+                } else if let Some(status) = e.status() {
+                    ErrorKind::from_status(status)
+                } else if e.is_decode() {
+                    ErrorKind::Decode
+                } else {
+                    ErrorKind::Other
+                }
+            }
+            Json(_) => ErrorKind::Decode,
+            UnknownRequest(_)
+            | MissingRequestApiKey
+            | MissingRequestAuthToken
+            | UrlParse(_)
+            | InvalidHeader(_) => ErrorKind::InvalidRequest,
+            Transport(e) => e.kind(),
+            #[cfg(any(feature = "cassette", feature = "manifest"))]
+            Io(_) => ErrorKind::Other,
+            #[cfg(feature = "compute_api_v2")]
+            ComputeRequest(e) => e.kind(),
+            #[cfg(feature = "locks_api_v1")]
+            LocksRequest(_) => ErrorKind::InvalidRequest,
+            #[cfg(feature = "metadata_api_v1")]
+            MetadataRequest(_) => ErrorKind::InvalidRequest,
+            #[cfg(feature = "restrict_api_v1")]
+            RestrictRequest(_) => ErrorKind::InvalidRequest,
+        }
+    }
+
+    /// Whether the error is transient, meaning the same request may succeed if retried later
+    pub fn is_retryable(&self) -> bool { self.kind().is_retryable() }
+
+    /// The rate limit reported by the server in the error response, if any
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        match self {
+            SeaplaneError::ApiResponse(e) => e.rate_limit,
+            SeaplaneError::HttpResponse(e) => e.rate_limit(),
+            _ => None,
+        }
+    }
+}
+
 impl From<reqwest::Error> for SeaplaneError {
     fn from(e: reqwest::Error) -> Self {
         if e.is_builder() {
@@ -86,11 +232,19 @@ impl PartialEq for SeaplaneError {
             MissingRequestApiKey => matches!(rhs, MissingRequestApiKey),
             MissingRequestAuthToken => matches!(rhs, MissingRequestAuthToken),
             UrlParse(_) => matches!(rhs, UrlParse(_)),
+            InvalidHeader(_) => matches!(rhs, InvalidHeader(_)),
+            Transport(_) => matches!(rhs, Transport(_)),
+            #[cfg(any(feature = "cassette", feature = "manifest"))]
+            Io(_) => matches!(rhs, Io(_)),
             Json(_) => matches!(rhs, Json(_)),
             ApiResponse(ae) => match rhs {
                 ApiResponse(oae) => ae == oae,
                 _ => false,
             },
+            HttpResponse(he) => match rhs {
+                HttpResponse(ohe) => he == ohe,
+                _ => false,
+            },
             #[cfg(feature = "compute_api_v2")]
             ComputeRequest(e) => {
                 if let ComputeRequest(re) = rhs {
@@ -19,7 +19,7 @@
 #![forbid(unsafe_code)]
 
 #[cfg(any(
-    feature = "compute_api_v1",
+    feature = "compute_api_v2",
     feature = "locks_api_v1",
     feature = "metadata_api_v1",
     feature = "restrict_api_v1"
@@ -29,6 +29,18 @@ mod macros;
 pub mod api;
 pub mod base64;
 pub mod error;
+#[cfg(all(
+    feature = "testing",
+    any(
+        feature = "compute_api_v2",
+        feature = "identity_api_v1",
+        feature = "locks_api_v1",
+        feature = "metadata_api_v1",
+        feature = "restrict_api_v1",
+    )
+))]
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+pub mod testing;
 
 /// Allows using the exact same traits derived from these dependencies. If re-exported here, that
 /// most likely means the derived trait appears in a types public API and you should `use` the
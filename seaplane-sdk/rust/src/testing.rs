@@ -0,0 +1,492 @@
+//! An in-process fake of the Seaplane APIs for integration testing
+//!
+//! Unlike a mock which answers each request with a canned response, [`FakeSeaplane`] keeps the
+//! state of every service in memory and implements the semantics of the real endpoints:
+//!
+//! - `/v1/token` issues access tokens, optionally checking the API key
+//! - `/v2beta/formations` creates, lists (with paging), gets, updates and deletes Formations,
+//!   assigning new `FormationId`s and `FlightId`s, replaying creates which reuse an
+//!   `Idempotency-Key`, and rejecting updates whose `If-Match` is not the current `ETag`. The
+//!   Flights of a Formation can be listed, inspected (each running as a single container instance)
+//!   and restarted
+//! - `/v1/config` stores key-value pairs under base64 encoded paths, with paged range queries
+//! - `/v1/locks` acquires, renews and releases locks which expire after their TTL, handing out an
+//!   increasing sequencer for each lock
+//! - `/v1/restrict` sets, gets, lists and deletes restrictions
+//!
+//! Only the endpoints of the enabled API features are served, any other path returns `404 Not
+//! Found`. All services share the same routes as the real APIs, so a single fake can stand in for
+//! all of them at once.
+//!
+//! The fake can either be used directly as a [`Transport`], in which case no sockets are involved
+//! at all, or served over HTTP on a local port with [`FakeSeaplane::serve`], in which case requests
+//! also go through the default HTTP client.
+//!
+//! # Examples
+//!
+//! ```
+//! # #[cfg(feature = "metadata_api_v1")] {
+//! use seaplane::{api::metadata::v1::MetadataRequest, testing::FakeSeaplane};
+//!
+//! let fake = FakeSeaplane::new();
+//! let server = fake.serve().unwrap();
+//!
+//! let req = MetadataRequest::builder()
+//!     .token("abc123")
+//!     .api_url(server.url())
+//!     .allow_http(true)
+//!     .encoded_key("Zm9v")
+//!     .build()
+//!     .unwrap();
+//! req.put_value_unencoded("bar").unwrap();
+//! assert_eq!(req.get_value().unwrap().decode(), b"bar");
+//! # }
+//! ```
+
+#[cfg(feature = "metadata_api_v1")]
+mod config;
+#[cfg(feature = "compute_api_v2")]
+mod formations;
+#[cfg(feature = "locks_api_v1")]
+mod locks;
+#[cfg(feature = "restrict_api_v1")]
+mod restrict;
+mod server;
+
+#[cfg(feature = "locks_api_v1")]
+use std::time::Instant;
+use std::{
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+#[cfg(any(
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1"
+))]
+use base64::{
+    alphabet::URL_SAFE,
+    engine::{
+        general_purpose::{GeneralPurpose, GeneralPurposeConfig},
+        DecodePaddingMode,
+    },
+    Engine,
+};
+use reqwest::{
+    header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE},
+    Method, StatusCode,
+};
+use serde::Serialize;
+use serde_json::json;
+
+pub use self::server::FakeServer;
+#[cfg(feature = "compute_api_v2")]
+use crate::api::compute::v2::FlightStatus;
+use crate::{
+    api::{HttpRequest, HttpResponse, Transport},
+    error::Result,
+};
+
+/// The tenant subdomain reported by the fake Identity API, and used in the URLs of Formations
+pub const FAKE_SUBDOMAIN: &str = "fake";
+
+/// The tenant OID reported by the fake Identity API
+pub const FAKE_TENANT: &str = "tnt-fake0000000000000000000000";
+
+/// The default maximum number of entries in a single page of results
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// An in-memory fake of the Seaplane APIs.
+///
+/// Cloning a `FakeSeaplane` is cheap, and all clones share the same state. This allows keeping a
+/// handle to inspect or manipulate the fake (for example to [`advance`](FakeSeaplane::advance) its
+/// clock) while another is used as the transport of a request.
+///
+/// Every endpoint other than `/v1/token` requires a bearer token, but accepts any token.
+#[derive(Debug, Clone)]
+pub struct FakeSeaplane {
+    api_key: Option<String>,
+    page_size: usize,
+    state: Arc<Mutex<State>>,
+}
+
+impl Default for FakeSeaplane {
+    fn default() -> Self { Self::new() }
+}
+
+// The state of all the fake services
+#[derive(Debug, Default)]
+struct State {
+    // How far the clock of the fake has been moved ahead of the real clock
+    skew: Duration,
+    #[cfg(feature = "compute_api_v2")]
+    formations: formations::Formations,
+    #[cfg(feature = "metadata_api_v1")]
+    config: config::Config,
+    #[cfg(feature = "locks_api_v1")]
+    locks: locks::Locks,
+    #[cfg(feature = "restrict_api_v1")]
+    restrict: restrict::Restrictions,
+}
+
+impl FakeSeaplane {
+    /// Create a new fake with no data stored in any of the services
+    pub fn new() -> Self {
+        Self { api_key: None, page_size: DEFAULT_PAGE_SIZE, state: Arc::default() }
+    }
+
+    /// Only issue access tokens for the given API key, instead of for any API key.
+    #[must_use]
+    pub fn api_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.api_key = Some(key.into());
+        self
+    }
+
+    /// The maximum number of entries returned in each page of a listing or range query.
+    ///
+    /// Defaults to [`DEFAULT_PAGE_SIZE`]. Setting a small page size is useful to exercise paging.
+    ///
+    /// # Panics
+    ///
+    /// If `size` is `0`
+    #[must_use]
+    pub fn page_size(mut self, size: usize) -> Self {
+        assert!(size > 0, "page size must be greater than zero");
+        self.page_size = size;
+        self
+    }
+
+    /// Moves the clock of the fake forward, for example to expire held locks without waiting for
+    /// their TTL to elapse.
+    pub fn advance(&self, by: Duration) { self.state().skew += by; }
+
+    /// Sets the status of the Flight named `flight` in the Formation named `formation`, as the
+    /// runtime would once the Flight started (or failed).
+    ///
+    /// # Panics
+    ///
+    /// If there is no such Formation or Flight
+    #[cfg(feature = "compute_api_v2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "compute_api_v2")))]
+    pub fn set_flight_status(&self, formation: &str, flight: &str, status: FlightStatus) {
+        self.state()
+            .formations
+            .set_flight_status(formation, flight, status);
+    }
+
+    /// Handle a single request, returning the response the real API would have returned.
+    pub fn handle(&self, req: &HttpRequest) -> HttpResponse {
+        let path = req.url.path();
+
+        #[cfg(feature = "identity_api_v1")]
+        if path == "/v1/token" {
+            return self.token(req);
+        }
+
+        if bearer_token(req).map_or(true, str::is_empty) {
+            return problem(StatusCode::UNAUTHORIZED, "missing bearer token");
+        }
+
+        // Only mutated by the endpoints of some features
+        #[cfg(any(
+            feature = "compute_api_v2",
+            feature = "locks_api_v1",
+            feature = "metadata_api_v1",
+            feature = "restrict_api_v1"
+        ))]
+        #[allow(unused_mut)]
+        let mut state = self.state();
+        #[cfg(any(
+            feature = "compute_api_v2",
+            feature = "locks_api_v1",
+            feature = "metadata_api_v1",
+            feature = "restrict_api_v1"
+        ))]
+        let ctx = Context {
+            req,
+            #[cfg(feature = "locks_api_v1")]
+            now: Instant::now() + state.skew,
+            page_size: self.page_size,
+        };
+
+        #[cfg(feature = "compute_api_v2")]
+        if let Some(rest) = path.strip_prefix("/v2beta/formations") {
+            return state.formations.handle(&ctx, rest);
+        }
+        #[cfg(feature = "metadata_api_v1")]
+        if let Some(rest) = path.strip_prefix("/v1/config/") {
+            return state.config.handle(&ctx, rest);
+        }
+        #[cfg(feature = "locks_api_v1")]
+        if let Some(rest) = path.strip_prefix("/v1/locks/") {
+            return state.locks.handle(&ctx, rest);
+        }
+        #[cfg(feature = "restrict_api_v1")]
+        if let Some(rest) = path.strip_prefix("/v1/restrict/") {
+            return state.restrict.handle(&ctx, rest);
+        }
+
+        problem(StatusCode::NOT_FOUND, format!("no such endpoint: {path}"))
+    }
+
+    // `POST /v1/token`
+    #[cfg(feature = "identity_api_v1")]
+    fn token(&self, req: &HttpRequest) -> HttpResponse {
+        if req.method != Method::POST {
+            return method_not_allowed(req);
+        }
+        match (bearer_token(req), &self.api_key) {
+            (None | Some(""), _) => return problem(StatusCode::UNAUTHORIZED, "missing API key"),
+            (Some(key), Some(expected)) if key != expected => {
+                return problem(StatusCode::UNAUTHORIZED, "invalid API key")
+            }
+            _ => (),
+        }
+
+        let token = format!("fake-{}", uuid::Uuid::new_v4().simple());
+        let wants_json = req
+            .headers
+            .get(reqwest::header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .map_or(false, |v| v.contains("application/json"));
+        if wants_json {
+            json_response(
+                StatusCode::CREATED,
+                &json!({ "token": token, "tenant": FAKE_TENANT, "subdomain": FAKE_SUBDOMAIN }),
+            )
+        } else {
+            HttpResponse::new(StatusCode::CREATED, HeaderMap::new(), token.into_bytes())
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // The state is only ever mutated by complete operations, so it stays consistent even if
+        // another thread panicked while holding the lock
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Transport for FakeSeaplane {
+    fn send(&self, req: HttpRequest) -> Result<HttpResponse> { Ok(self.handle(&req)) }
+}
+
+// Everything a service needs to know about the request being handled
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1"
+))]
+struct Context<'a> {
+    req: &'a HttpRequest,
+    // The current time on the (possibly advanced) clock of the fake
+    #[cfg(feature = "locks_api_v1")]
+    now: Instant,
+    page_size: usize,
+}
+
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1"
+))]
+impl Context<'_> {
+    fn method(&self) -> &Method { &self.req.method }
+
+    #[cfg(any(
+        feature = "compute_api_v2",
+        feature = "metadata_api_v1",
+        feature = "restrict_api_v1"
+    ))]
+    fn body(&self) -> &[u8] { self.req.body.as_deref().unwrap_or_default() }
+
+    // The value of the first query parameter named `name`
+    fn query(&self, name: &str) -> Option<String> {
+        self.req
+            .url
+            .query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+    }
+
+    // The decoded value of a `from=base64:{key}` query parameter, or `Err` with a response if it is
+    // malformed
+    #[cfg(any(feature = "locks_api_v1", feature = "metadata_api_v1", feature = "restrict_api_v1"))]
+    fn range_start(&self) -> std::result::Result<Option<Vec<u8>>, HttpResponse> {
+        match self.query("from") {
+            None => Ok(None),
+            Some(from) => from
+                .strip_prefix("base64:")
+                .and_then(decode)
+                .map(Some)
+                .ok_or_else(|| problem(StatusCode::BAD_REQUEST, "invalid from key")),
+        }
+    }
+}
+
+// The target of a request to a service with base64 encoded keys
+#[cfg(any(feature = "locks_api_v1", feature = "metadata_api_v1"))]
+enum KeyTarget {
+    // A single key, from `base64:{key}`
+    Single(Vec<u8>),
+    // A range query over all keys, or those within a directory from `base64:{dir}/`
+    Range(Option<Vec<u8>>),
+}
+
+#[cfg(any(feature = "locks_api_v1", feature = "metadata_api_v1"))]
+impl KeyTarget {
+    // Parses the path following the base path of the service
+    fn parse(path: &str) -> Option<Self> {
+        if path.is_empty() {
+            return Some(KeyTarget::Range(None));
+        }
+        let encoded = path.strip_prefix("base64:")?;
+        match encoded.strip_suffix('/') {
+            Some(dir) => decode(dir).map(|dir| KeyTarget::Range(Some(dir))),
+            None => decode(encoded).map(KeyTarget::Single),
+        }
+    }
+}
+
+// Returns the page of `entries` (which must be sorted by key) starting at `from`, within the
+// directory `dir` if any, along with the first key of the next page if there is one.
+//
+// A key is within a directory if it starts with the directory followed by a `/`.
+#[cfg(any(feature = "locks_api_v1", feature = "metadata_api_v1"))]
+fn page<'a, T: 'a>(
+    entries: impl IntoIterator<Item = (&'a Vec<u8>, T)>,
+    dir: Option<&[u8]>,
+    from: Option<&[u8]>,
+    size: usize,
+) -> (Vec<T>, Option<Vec<u8>>) {
+    let mut entries = entries
+        .into_iter()
+        .filter(|(key, _)| {
+            dir.map_or(true, |dir| key.starts_with(dir) && key.get(dir.len()) == Some(&b'/'))
+        })
+        .filter(|(key, _)| from.map_or(true, |from| key.as_slice() >= from));
+    let page = entries.by_ref().take(size).map(|(_, v)| v).collect();
+    (page, entries.next().map(|(key, _)| key.clone()))
+}
+
+// The encoding used for all keys and values by the APIs
+#[cfg(any(feature = "locks_api_v1", feature = "metadata_api_v1", feature = "restrict_api_v1"))]
+const ENGINE: GeneralPurpose = GeneralPurpose::new(
+    &URL_SAFE,
+    GeneralPurposeConfig::new()
+        .with_encode_padding(false)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+#[cfg(any(feature = "locks_api_v1", feature = "metadata_api_v1"))]
+fn encode(bytes: impl AsRef<[u8]>) -> String { ENGINE.encode(bytes) }
+
+#[cfg(any(feature = "locks_api_v1", feature = "metadata_api_v1", feature = "restrict_api_v1"))]
+fn decode(encoded: &str) -> Option<Vec<u8>> { ENGINE.decode(encoded).ok() }
+
+fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+// A JSON response
+fn json_response<T: Serialize>(status: StatusCode, body: &T) -> HttpResponse {
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    HttpResponse::new(
+        status,
+        headers,
+        serde_json::to_vec(body).expect("responses are always serializable"),
+    )
+}
+
+// The success message returned by endpoints which do not return any data
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1"
+))]
+fn success() -> HttpResponse {
+    json_response(StatusCode::OK, &json!({ "status": 200, "title": "Ok" }))
+}
+
+// An RFC 7807 problem response
+fn problem(status: StatusCode, detail: impl Into<String>) -> HttpResponse {
+    let mut res = json_response(
+        status,
+        &json!({
+            "title": status.canonical_reason().unwrap_or("Error"),
+            "status": status.as_u16(),
+            "detail": detail.into(),
+        }),
+    );
+    res.headers
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/problem+json"));
+    res
+}
+
+fn method_not_allowed(req: &HttpRequest) -> HttpResponse {
+    problem(
+        StatusCode::METHOD_NOT_ALLOWED,
+        format!("{} is not supported on {}", req.method, req.url.path()),
+    )
+}
+
+#[cfg(test)]
+mod testing_tests {
+    use super::*;
+
+    #[cfg(any(feature = "locks_api_v1", feature = "metadata_api_v1"))]
+    #[test]
+    fn page_within_directory() {
+        let keys: Vec<Vec<u8>> = ["a", "foo", "foo/a", "foo/b", "foo/c", "foobar/a"]
+            .iter()
+            .map(|k| k.as_bytes().to_vec())
+            .collect();
+        let entries = || keys.iter().map(|k| (k, k.as_slice()));
+
+        let (page1, next) = page(entries(), Some(b"foo"), None, 2);
+        assert_eq!(page1, [&b"foo/a"[..], b"foo/b"]);
+        assert_eq!(next.as_deref(), Some(&b"foo/c"[..]));
+
+        let (page2, next) = page(entries(), Some(b"foo"), next.as_deref(), 2);
+        assert_eq!(page2, [&b"foo/c"[..]]);
+        assert_eq!(next, None);
+
+        let (all, next) = page(entries(), None, None, 10);
+        assert_eq!(all.len(), keys.len());
+        assert_eq!(next, None);
+    }
+
+    #[cfg(any(feature = "locks_api_v1", feature = "metadata_api_v1"))]
+    #[test]
+    fn key_target() {
+        assert!(matches!(KeyTarget::parse(""), Some(KeyTarget::Range(None))));
+        assert!(
+            matches!(KeyTarget::parse("base64:Zm9v/"), Some(KeyTarget::Range(Some(d))) if d == b"foo")
+        );
+        assert!(
+            matches!(KeyTarget::parse("base64:Zm9v"), Some(KeyTarget::Single(k)) if k == b"foo")
+        );
+        assert!(KeyTarget::parse("Zm9v").is_none());
+        assert!(KeyTarget::parse("base64:!!").is_none());
+    }
+
+    #[test]
+    fn requires_bearer_token() {
+        let fake = FakeSeaplane::new();
+        let req = HttpRequest::new(
+            Method::GET,
+            "http://localhost/v1/config/".parse().unwrap(),
+            HeaderMap::new(),
+            None,
+        );
+        assert_eq!(fake.handle(&req).status, StatusCode::UNAUTHORIZED);
+    }
+}
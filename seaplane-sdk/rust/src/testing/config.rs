@@ -0,0 +1,67 @@
+//! The fake `/v1/config` endpoints
+
+use std::collections::BTreeMap;
+
+use reqwest::{Method, StatusCode};
+use serde_json::json;
+
+use super::{decode, encode, json_response, page, problem, success, Context, KeyTarget};
+use crate::api::HttpResponse;
+
+// The key-value store, keyed by the decoded key
+#[derive(Debug, Default)]
+pub(super) struct Config {
+    kvs: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl Config {
+    // `path` is the path following `/v1/config/`
+    pub(super) fn handle(&mut self, ctx: &Context<'_>, path: &str) -> HttpResponse {
+        let Some(target) = KeyTarget::parse(path) else {
+            return problem(StatusCode::NOT_FOUND, "invalid key");
+        };
+        match (ctx.method(), target) {
+            (&Method::GET, KeyTarget::Single(key)) => match self.kvs.get(&key) {
+                Some(value) => json_response(
+                    StatusCode::OK,
+                    &json!({ "key": encode(&key), "value": encode(value) }),
+                ),
+                None => problem(StatusCode::NOT_FOUND, "no such key"),
+            },
+            (&Method::PUT, KeyTarget::Single(key)) => {
+                // The value is sent encoded, as the body of the request
+                let value = std::str::from_utf8(ctx.body()).ok();
+                let Some(value) = value.and_then(|v| decode(v.trim())) else {
+                    return problem(StatusCode::BAD_REQUEST, "value must be base64 encoded");
+                };
+                self.kvs.insert(key, value);
+                success()
+            }
+            (&Method::DELETE, KeyTarget::Single(key)) => match self.kvs.remove(&key) {
+                Some(_) => success(),
+                None => problem(StatusCode::NOT_FOUND, "no such key"),
+            },
+            (&Method::GET, KeyTarget::Range(dir)) => {
+                let from = match ctx.range_start() {
+                    Ok(from) => from,
+                    Err(res) => return res,
+                };
+                let (kvs, next_key) = page(
+                    self.kvs.iter().map(|kv| (kv.0, kv)),
+                    dir.as_deref(),
+                    from.as_deref(),
+                    ctx.page_size,
+                );
+                let kvs: Vec<_> = kvs
+                    .into_iter()
+                    .map(|(key, value)| json!({ "key": encode(key), "value": encode(value) }))
+                    .collect();
+                json_response(
+                    StatusCode::OK,
+                    &json!({ "next_key": next_key.map(encode), "kvs": kvs }),
+                )
+            }
+            _ => super::method_not_allowed(ctx.req),
+        }
+    }
+}
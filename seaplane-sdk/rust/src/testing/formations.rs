@@ -0,0 +1,321 @@
+//! The fake `/v2beta/formations` endpoints
+
+use std::collections::HashMap;
+
+use reqwest::{
+    header::{HeaderValue, ETAG, IF_MATCH},
+    Method, StatusCode,
+};
+
+use super::{json_response, problem, success, Context, FAKE_SUBDOMAIN};
+use crate::api::{
+    compute::v2::{
+        validate_formation_name, ContainerInstance, FlightDetails, FlightId, FlightStatus,
+        Formation, FormationId, PageMetadata, PagedResponse,
+    },
+    HttpResponse, IDEMPOTENCY_KEY,
+};
+
+// The Formations, in the order in which they were created
+#[derive(Debug, Default)]
+pub(super) struct Formations {
+    formations: Vec<Formation>,
+    // The Formation created with each `Idempotency-Key`, which is returned again when the key is
+    // reused
+    created: HashMap<String, Formation>,
+    // The version of each Formation (keyed by its ID), which changes whenever it is updated and
+    // is its `ETag`
+    versions: HashMap<String, u64>,
+    last_version: u64,
+    // The number of times each Flight (keyed by its ID) has been restarted, which makes up the IDs
+    // of its container instances
+    restarts: HashMap<String, u64>,
+}
+
+impl Formations {
+    // `path` is the path following `/v2beta/formations`
+    pub(super) fn handle(&mut self, ctx: &Context<'_>, path: &str) -> HttpResponse {
+        match path.trim_start_matches('/') {
+            "" => match *ctx.method() {
+                Method::GET => self.list(ctx),
+                Method::POST => self.create(ctx),
+                _ => super::method_not_allowed(ctx.req),
+            },
+            path => {
+                let (id, rest) = path.split_once('/').unwrap_or((path, ""));
+                let Some(i) = id
+                    .parse::<FormationId>()
+                    .ok()
+                    .and_then(|oid| self.formations.iter().position(|f| f.oid == Some(oid)))
+                else {
+                    return problem(StatusCode::NOT_FOUND, format!("no such formation: {id}"));
+                };
+                if !rest.is_empty() {
+                    return self.handle_flights(ctx, i, rest);
+                }
+                match *ctx.method() {
+                    Method::GET => {
+                        self.with_etag(json_response(StatusCode::OK, &self.formations[i]))
+                    }
+                    Method::PUT => self.update(ctx, i),
+                    Method::DELETE => {
+                        self.formations.remove(i);
+                        success()
+                    }
+                    _ => super::method_not_allowed(ctx.req),
+                }
+            }
+        }
+    }
+
+    // `path` is the path following `/v2beta/formations/ID` of the Formation at index `i`
+    fn handle_flights(&mut self, ctx: &Context<'_>, i: usize, path: &str) -> HttpResponse {
+        let flights = &self.formations[i].flights;
+        let (flight, action) = match path.split('/').collect::<Vec<_>>()[..] {
+            ["flights"] => {
+                return match *ctx.method() {
+                    Method::GET => json_response(StatusCode::OK, flights),
+                    _ => super::method_not_allowed(ctx.req),
+                };
+            }
+            ["flights", id] => (id, None),
+            ["flights", id, action] => (id, Some(action)),
+            _ => return problem(StatusCode::NOT_FOUND, format!("no such path: {path}")),
+        };
+        let Some(j) = flight
+            .parse::<FlightId>()
+            .ok()
+            .and_then(|oid| flights.iter().position(|f| f.oid == Some(oid)))
+        else {
+            return problem(StatusCode::NOT_FOUND, format!("no such flight: {flight}"));
+        };
+
+        match (ctx.method().clone(), action) {
+            (Method::GET, None) => json_response(StatusCode::OK, &self.flight_details(i, j)),
+            (Method::POST, Some("restart")) => {
+                let flight = &mut self.formations[i].flights[j];
+                flight.status = FlightStatus::Starting;
+                let oid = flight.oid.expect("stored flights have an ID").to_string();
+                *self.restarts.entry(oid).or_default() += 1;
+                success()
+            }
+            (_, None | Some("restart")) => super::method_not_allowed(ctx.req),
+            _ => problem(StatusCode::NOT_FOUND, format!("no such path: {path}")),
+        }
+    }
+
+    // The Flight at index `j` of the Formation at index `i`, which runs as a single container
+    // instance
+    fn flight_details(&self, i: usize, j: usize) -> FlightDetails {
+        let flight = self.formations[i].flights[j].clone();
+        let oid = flight.oid.expect("stored flights have an ID").to_string();
+        let restarts = self.restarts.get(&oid).copied().unwrap_or_default();
+        FlightDetails {
+            instances: vec![ContainerInstance {
+                id: format!("{oid}-{restarts}"),
+                status: flight.status,
+                region: None,
+                provider: None,
+                started_at: None,
+            }],
+            flight,
+        }
+    }
+
+    // A page of Formations, starting at the `offset` query parameter and containing at most `limit`
+    // Formations (capped by the page size of the fake)
+    fn list(&self, ctx: &Context<'_>) -> HttpResponse {
+        let (Ok(offset), Ok(limit)) = (
+            ctx.query("offset").map_or(Ok(0), |o| o.parse::<usize>()),
+            ctx.query("limit").map_or(Ok(ctx.page_size), |l| l.parse::<usize>()),
+        ) else {
+            return problem(StatusCode::BAD_REQUEST, "invalid offset or limit");
+        };
+        let limit = limit.clamp(1, ctx.page_size);
+        let total = self.formations.len();
+
+        // The URL of the page starting at `offset`, keeping the limit if one was requested
+        let page_url = |offset: usize| {
+            let mut url = ctx.req.url.clone();
+            url.query_pairs_mut()
+                .clear()
+                .append_pair("offset", &offset.to_string());
+            if let Some(limit) = ctx.query("limit") {
+                url.query_pairs_mut().append_pair("limit", &limit);
+            }
+            url
+        };
+
+        json_response(
+            StatusCode::OK,
+            &PagedResponse {
+                objects: self
+                    .formations
+                    .iter()
+                    .skip(offset)
+                    .take(limit)
+                    .cloned()
+                    .collect(),
+                meta: PageMetadata {
+                    total,
+                    next: (offset + limit < total).then(|| page_url(offset + limit)),
+                    prev: (offset > 0).then(|| page_url(offset.saturating_sub(limit))),
+                },
+            },
+        )
+    }
+
+    fn create(&mut self, ctx: &Context<'_>) -> HttpResponse {
+        let key = ctx
+            .req
+            .headers
+            .get(IDEMPOTENCY_KEY)
+            .and_then(|key| key.to_str().ok())
+            .map(str::to_owned);
+        if let Some(formation) = key.as_ref().and_then(|key| self.created.get(key)) {
+            return json_response(StatusCode::CREATED, formation);
+        }
+
+        let mut formation = match parse(ctx) {
+            Ok(formation) => formation,
+            Err(res) => return res,
+        };
+        if self.formations.iter().any(|f| f.name == formation.name) {
+            return problem(
+                StatusCode::CONFLICT,
+                format!("formation {} already exists", formation.name),
+            );
+        }
+
+        formation.oid = Some(FormationId::new());
+        formation.url = Some(
+            format!("https://{}.{FAKE_SUBDOMAIN}.on.cplane.cloud/", formation.name)
+                .parse()
+                .expect("formation names are valid hostnames"),
+        );
+        for flight in &mut formation.flights {
+            flight.oid = Some(FlightId::new());
+            flight.status = FlightStatus::Starting;
+        }
+        self.formations.push(formation.clone());
+        self.bump_version(formation.oid);
+        if let Some(key) = key {
+            self.created.insert(key, formation.clone());
+        }
+
+        json_response(StatusCode::CREATED, &formation)
+    }
+
+    // Replaces the Formation at index `i`, keeping its ID and URL as well as the IDs of Flights
+    // which keep their name
+    fn update(&mut self, ctx: &Context<'_>, i: usize) -> HttpResponse {
+        let current = &self.formations[i];
+        if let Some(if_match) = ctx.req.headers.get(IF_MATCH) {
+            if if_match != "*" && Some(if_match) != self.etag(current.oid).as_ref() {
+                return problem(
+                    StatusCode::PRECONDITION_FAILED,
+                    "formation has changed since it was fetched",
+                );
+            }
+        }
+        let mut formation = match parse(ctx) {
+            Ok(formation) => formation,
+            Err(res) => return res,
+        };
+        if self
+            .formations
+            .iter()
+            .any(|f| f.name == formation.name && f.oid != current.oid)
+        {
+            return problem(
+                StatusCode::CONFLICT,
+                format!("formation {} already exists", formation.name),
+            );
+        }
+
+        formation.oid = current.oid;
+        formation.url = current.url.clone();
+        for flight in &mut formation.flights {
+            match current.flights.iter().find(|f| f.name == flight.name) {
+                Some(old) if old.image == flight.image => {
+                    flight.oid = old.oid;
+                    flight.status = old.status;
+                }
+                Some(old) => {
+                    flight.oid = old.oid;
+                    flight.status = FlightStatus::Starting;
+                }
+                None => {
+                    flight.oid = Some(FlightId::new());
+                    flight.status = FlightStatus::Starting;
+                }
+            }
+        }
+        self.formations[i] = formation.clone();
+        self.bump_version(formation.oid);
+
+        self.with_etag(json_response(StatusCode::OK, &formation))
+    }
+
+    pub(super) fn set_flight_status(
+        &mut self,
+        formation: &str,
+        flight: &str,
+        status: FlightStatus,
+    ) {
+        let formation = self
+            .formations
+            .iter_mut()
+            .find(|f| f.name == formation)
+            .unwrap_or_else(|| panic!("no such formation: {formation}"));
+        let flight = formation
+            .flights
+            .iter_mut()
+            .find(|f| f.name == flight)
+            .unwrap_or_else(|| panic!("no such flight: {flight}"));
+        flight.status = status;
+    }
+
+    fn bump_version(&mut self, oid: Option<FormationId>) {
+        self.last_version += 1;
+        self.versions
+            .insert(oid.expect("stored formations have an ID").to_string(), self.last_version);
+    }
+
+    // The `ETag` of the Formation `oid`
+    fn etag(&self, oid: Option<FormationId>) -> Option<HeaderValue> {
+        let version = self.versions.get(&oid?.to_string())?;
+        HeaderValue::try_from(format!("\"{version}\"")).ok()
+    }
+
+    // Adds the `ETag` of the Formation in the body of the response
+    fn with_etag(&self, mut res: HttpResponse) -> HttpResponse {
+        let oid = serde_json::from_slice::<Formation>(&res.body)
+            .ok()
+            .and_then(|f| f.oid);
+        if let Some(etag) = self.etag(oid) {
+            res.headers.insert(ETAG, etag);
+        }
+        res
+    }
+}
+
+// Parses and validates the Formation in the body of the request
+fn parse(ctx: &Context<'_>) -> Result<Formation, HttpResponse> {
+    let formation: Formation = serde_json::from_slice(ctx.body())
+        .map_err(|e| problem(StatusCode::BAD_REQUEST, e.to_string()))?;
+    validate_formation_name(&formation.name)
+        .map_err(|e| problem(StatusCode::BAD_REQUEST, e.to_string()))?;
+    if formation.flights.is_empty() {
+        return Err(problem(StatusCode::BAD_REQUEST, "a formation must have at least one flight"));
+    }
+    if let Some(gateway) = &formation.gateway_flight {
+        if !formation.flights.iter().any(|f| &f.name == gateway) {
+            return Err(problem(
+                StatusCode::BAD_REQUEST,
+                format!("gateway flight {gateway} is not one of the flights"),
+            ));
+        }
+    }
+    Ok(formation)
+}
@@ -0,0 +1,144 @@
+//! The fake `/v1/locks` endpoints
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use super::{encode, json_response, page, problem, success, Context, KeyTarget};
+use crate::api::HttpResponse;
+
+// The address reported as the holder of every lock
+const CLIENT_IP: &str = "127.0.0.1";
+
+// The held locks and the last sequencer handed out for each lock name, keyed by the decoded name
+#[derive(Debug, Default)]
+pub(super) struct Locks {
+    held: BTreeMap<Vec<u8>, Lock>,
+    sequencers: HashMap<Vec<u8>, u32>,
+}
+
+#[derive(Debug)]
+struct Lock {
+    // The encoded lock ID
+    id: String,
+    client_id: String,
+    ttl: u32,
+    expires: Instant,
+}
+
+impl Lock {
+    fn info(&self, name: &[u8]) -> Value {
+        json!({
+            "name": encode(name),
+            "id": self.id,
+            "info": { "ttl": self.ttl, "client-id": self.client_id, "ip": CLIENT_IP },
+        })
+    }
+}
+
+impl Locks {
+    // `path` is the path following `/v1/locks/`
+    pub(super) fn handle(&mut self, ctx: &Context<'_>, path: &str) -> HttpResponse {
+        // Locks which have outlived their TTL are no longer held
+        self.held.retain(|_, lock| lock.expires > ctx.now);
+
+        let Some(target) = KeyTarget::parse(path) else {
+            return problem(StatusCode::NOT_FOUND, "invalid lock name");
+        };
+        match (ctx.method(), target) {
+            (&Method::POST, KeyTarget::Single(name)) => self.acquire(ctx, name),
+            (&Method::PATCH, KeyTarget::Single(name)) => self.renew(ctx, &name),
+            (&Method::DELETE, KeyTarget::Single(name)) => self.release(ctx, &name),
+            (&Method::GET, KeyTarget::Single(name)) => match self.held.get(&name) {
+                Some(lock) => json_response(StatusCode::OK, &lock.info(&name)),
+                None => problem(StatusCode::NOT_FOUND, "lock is not held"),
+            },
+            (&Method::GET, KeyTarget::Range(dir)) => {
+                let from = match ctx.range_start() {
+                    Ok(from) => from,
+                    Err(res) => return res,
+                };
+                let (locks, next) = page(
+                    self.held.iter().map(|(name, lock)| (name, lock.info(name))),
+                    dir.as_deref(),
+                    from.as_deref(),
+                    ctx.page_size,
+                );
+                json_response(StatusCode::OK, &json!({ "next": next.map(encode), "locks": locks }))
+            }
+            _ => super::method_not_allowed(ctx.req),
+        }
+    }
+
+    fn acquire(&mut self, ctx: &Context<'_>, name: Vec<u8>) -> HttpResponse {
+        let ttl = match ttl(ctx) {
+            Ok(ttl) => ttl,
+            Err(res) => return res,
+        };
+        let Some(client_id) = ctx.query("client-id").filter(|id| !id.is_empty()) else {
+            return problem(StatusCode::BAD_REQUEST, "missing client-id");
+        };
+        if self.held.contains_key(&name) {
+            return problem(StatusCode::CONFLICT, "lock is already held");
+        }
+
+        let sequencer = self.sequencers.entry(name.clone()).or_default();
+        *sequencer += 1;
+        let sequencer = *sequencer;
+        let id = encode(uuid::Uuid::new_v4().as_bytes());
+        let expires = ctx.now + Duration::from_secs(ttl.into());
+        self.held
+            .insert(name, Lock { id: id.clone(), client_id, ttl, expires });
+
+        json_response(StatusCode::CREATED, &json!({ "id": id, "sequencer": sequencer }))
+    }
+
+    fn renew(&mut self, ctx: &Context<'_>, name: &[u8]) -> HttpResponse {
+        let ttl = match ttl(ctx) {
+            Ok(ttl) => ttl,
+            Err(res) => return res,
+        };
+        match self.held_by(ctx, name) {
+            Ok(lock) => {
+                lock.ttl = ttl;
+                lock.expires = ctx.now + Duration::from_secs(ttl.into());
+                success()
+            }
+            Err(res) => res,
+        }
+    }
+
+    fn release(&mut self, ctx: &Context<'_>, name: &[u8]) -> HttpResponse {
+        match self.held_by(ctx, name) {
+            Ok(_) => {
+                self.held.remove(name);
+                success()
+            }
+            Err(res) => res,
+        }
+    }
+
+    // The lock `name` if it is held with the ID from the `id` query parameter
+    fn held_by(&mut self, ctx: &Context<'_>, name: &[u8]) -> Result<&mut Lock, HttpResponse> {
+        let Some(id) = ctx.query("id") else {
+            return Err(problem(StatusCode::BAD_REQUEST, "missing lock id"));
+        };
+        match self.held.get_mut(name) {
+            Some(lock) if lock.id == id => Ok(lock),
+            Some(_) => Err(problem(StatusCode::CONFLICT, "lock is held with a different id")),
+            None => Err(problem(StatusCode::NOT_FOUND, "lock is not held")),
+        }
+    }
+}
+
+// The `ttl` query parameter, which must be a positive number of seconds
+fn ttl(ctx: &Context<'_>) -> Result<u32, HttpResponse> {
+    ctx.query("ttl")
+        .and_then(|ttl| ttl.parse().ok())
+        .filter(|&ttl| ttl > 0)
+        .ok_or_else(|| problem(StatusCode::BAD_REQUEST, "ttl must be a positive integer"))
+}
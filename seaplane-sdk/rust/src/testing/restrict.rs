@@ -0,0 +1,135 @@
+//! The fake `/v1/restrict` endpoints
+
+use std::{collections::BTreeMap, str::FromStr};
+
+use reqwest::{Method, StatusCode};
+
+use super::{decode, json_response, problem, success, Context};
+use crate::api::{
+    restrict::v1::{
+        Api, RestrictedDirectory, Restriction, RestrictionDetails, RestrictionRange,
+        RestrictionState,
+    },
+    HttpResponse,
+};
+
+// The restrictions, ordered by API and then by decoded directory
+#[derive(Debug, Default)]
+pub(super) struct Restrictions {
+    restrictions: BTreeMap<(String, Vec<u8>), Restriction>,
+}
+
+impl Restrictions {
+    // `path` is the path following `/v1/restrict/`
+    pub(super) fn handle(&mut self, ctx: &Context<'_>, path: &str) -> HttpResponse {
+        if path.is_empty() {
+            return match *ctx.method() {
+                Method::GET => self.range(ctx, None),
+                _ => super::method_not_allowed(ctx.req),
+            };
+        }
+
+        let (api, rest) = path.split_once('/').unwrap_or((path, ""));
+        let Ok(api) = Api::from_str(api) else {
+            return problem(StatusCode::NOT_FOUND, format!("no such API: {api}"));
+        };
+        if rest.is_empty() {
+            return match *ctx.method() {
+                Method::GET => self.range(ctx, Some(api)),
+                _ => super::method_not_allowed(ctx.req),
+            };
+        }
+
+        let Some((encoded, dir)) = rest
+            .strip_prefix("base64:")
+            .and_then(|dir| dir.strip_suffix('/'))
+            .and_then(|encoded| decode(encoded).map(|dir| (encoded, dir)))
+        else {
+            return problem(StatusCode::NOT_FOUND, "invalid directory");
+        };
+        let key = (api.to_string(), dir);
+
+        match *ctx.method() {
+            Method::GET => match self.restrictions.get(&key) {
+                Some(restriction) => json_response(StatusCode::OK, restriction),
+                None => problem(StatusCode::NOT_FOUND, "no such restriction"),
+            },
+            Method::PUT => {
+                let details: RestrictionDetails = match serde_json::from_slice(ctx.body()) {
+                    Ok(details) => details,
+                    Err(e) => return problem(StatusCode::BAD_REQUEST, e.to_string()),
+                };
+                if details
+                    .regions_allowed
+                    .intersection(&details.regions_denied)
+                    .next()
+                    .is_some()
+                    || details
+                        .providers_allowed
+                        .intersection(&details.providers_denied)
+                        .next()
+                        .is_some()
+                {
+                    return problem(StatusCode::BAD_REQUEST, "conflicting requirements");
+                }
+                self.restrictions.insert(
+                    key,
+                    Restriction {
+                        api,
+                        directory: RestrictedDirectory::from_encoded(encoded),
+                        details,
+                        state: RestrictionState::Enforced,
+                    },
+                );
+                success()
+            }
+            Method::DELETE => match self.restrictions.remove(&key) {
+                Some(_) => success(),
+                None => problem(StatusCode::NOT_FOUND, "no such restriction"),
+            },
+            _ => super::method_not_allowed(ctx.req),
+        }
+    }
+
+    // A page of the restrictions of `api`, or of all APIs if `None`
+    fn range(&self, ctx: &Context<'_>, api: Option<Api>) -> HttpResponse {
+        let from = match ctx.range_start() {
+            Ok(from) => from,
+            Err(res) => return res,
+        };
+        // When listing all APIs the page starts from the API named by `from_api`
+        let from_api = match (api, ctx.query("from_api")) {
+            (Some(api), _) => Some(api.to_string()),
+            (None, Some(from_api)) => match Api::from_str(&from_api) {
+                Ok(api) => Some(api.to_string()),
+                Err(_) => return problem(StatusCode::BAD_REQUEST, "invalid from_api"),
+            },
+            (None, None) => None,
+        };
+
+        let mut entries = self
+            .restrictions
+            .iter()
+            .filter(|((a, _), _)| api.map_or(true, |api| *a == api.to_string()))
+            .filter(|((a, dir), _)| match (&from_api, &from) {
+                (Some(from_api), Some(from)) => (a, dir) >= (from_api, from),
+                (Some(from_api), None) => a >= from_api,
+                (None, _) => true,
+            });
+        let restrictions: Vec<_> = entries
+            .by_ref()
+            .take(ctx.page_size)
+            .map(|(_, r)| r.clone())
+            .collect();
+        let next = entries.next().map(|(_, r)| r);
+
+        json_response(
+            StatusCode::OK,
+            &RestrictionRange {
+                next_api: next.map(|r| r.api),
+                next_key: next.map(|r| r.directory.clone()),
+                restrictions,
+            },
+        )
+    }
+}
@@ -0,0 +1,179 @@
+//! Serving a [`FakeSeaplane`] over HTTP on a local port
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, CONNECTION, CONTENT_LENGTH},
+    Method, Url,
+};
+
+use super::FakeSeaplane;
+use crate::api::{HttpRequest, HttpResponse};
+
+impl FakeSeaplane {
+    /// Serves the fake over plain HTTP on a random port of the loopback interface.
+    ///
+    /// The server runs on background threads until the returned [`FakeServer`] is dropped. Since
+    /// the server does not use TLS, requests must opt in to insecure URLs with `allow_http(true)`.
+    pub fn serve(&self) -> io::Result<FakeServer> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let addr = listener.local_addr()?;
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let fake = self.clone();
+        let stopped = Arc::clone(&shutdown);
+        let thread = thread::Builder::new()
+            .name(format!("fake-seaplane-{addr}"))
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    if stopped.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(stream) = stream else { continue };
+                    let fake = fake.clone();
+                    thread::spawn(move || {
+                        // The client going away mid-request is not an error of the fake
+                        let _ = serve_connection(&fake, stream, addr);
+                    });
+                }
+            })?;
+
+        Ok(FakeServer { addr, shutdown, thread: Some(thread) })
+    }
+}
+
+/// A [`FakeSeaplane`] being served over HTTP, which stops accepting connections when dropped.
+#[derive(Debug)]
+pub struct FakeServer {
+    addr: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FakeServer {
+    /// The address the server is listening on
+    pub fn addr(&self) -> SocketAddr { self.addr }
+
+    /// The base URL of the server, to be passed to the `base_url` of a request builder
+    pub fn url(&self) -> Url {
+        format!("http://{}/", self.addr)
+            .parse()
+            .expect("socket addresses are valid hosts")
+    }
+}
+
+impl Drop for FakeServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Wake the accept loop up so it notices the shutdown
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// Handles the requests sent over a single (possibly kept alive) connection
+fn serve_connection(fake: &FakeSeaplane, stream: TcpStream, addr: SocketAddr) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    while let Some((req, close)) = read_request(&mut reader, addr)? {
+        write_response(&mut writer, &fake.handle(&req))?;
+        if close {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// Reads an HTTP/1.1 request, returning `None` if the connection was closed before it started and
+// whether the client asked for the connection to be closed afterwards.
+//
+// Only bodies with a `Content-Length` are supported, which is all the SDK ever sends.
+fn read_request(
+    reader: &mut impl BufRead,
+    addr: SocketAddr,
+) -> io::Result<Option<(HttpRequest, bool)>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(invalid("malformed request line"));
+    };
+    let method = Method::from_bytes(method.as_bytes()).map_err(invalid)?;
+    let url = Url::parse(&format!("http://{addr}{target}")).map_err(invalid)?;
+
+    let mut headers = HeaderMap::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(invalid("malformed header"));
+        };
+        headers.append(
+            HeaderName::from_bytes(name.trim().as_bytes()).map_err(invalid)?,
+            HeaderValue::from_str(value.trim()).map_err(invalid)?,
+        );
+    }
+
+    let len = match headers.get(CONTENT_LENGTH) {
+        Some(len) => len
+            .to_str()
+            .ok()
+            .and_then(|len| len.parse::<usize>().ok())
+            .ok_or_else(|| invalid("invalid content-length"))?,
+        None => 0,
+    };
+    let body = if len > 0 {
+        let mut body = vec![0; len];
+        reader.read_exact(&mut body)?;
+        Some(body)
+    } else {
+        None
+    };
+
+    let close = headers
+        .get(CONNECTION)
+        .map_or(false, |v| v.as_bytes().eq_ignore_ascii_case(b"close"));
+    Ok(Some((HttpRequest::new(method, url, headers, body), close)))
+}
+
+fn write_response(writer: &mut impl Write, res: &HttpResponse) -> io::Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\n",
+        res.status.as_u16(),
+        res.status.canonical_reason().unwrap_or_default()
+    );
+    for (name, value) in res
+        .headers
+        .iter()
+        .filter(|(name, _)| *name != CONTENT_LENGTH)
+    {
+        head.push_str(&format!("{name}: {}\r\n", String::from_utf8_lossy(value.as_bytes())));
+    }
+    head.push_str(&format!("content-length: {}\r\n\r\n", res.body.len()));
+
+    writer.write_all(head.as_bytes())?;
+    writer.write_all(&res.body)?;
+    writer.flush()
+}
+
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
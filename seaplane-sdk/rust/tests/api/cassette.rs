@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+use httpmock::prelude::*;
+use seaplane::api::{
+    metadata::v1::{MetadataRequest, Value},
+    Cassette, HttpClient, HttpOptions,
+};
+use serde_json::json;
+
+use super::MOCK_SERVER;
+
+fn cassette_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("seaplane-cassette-{}-{name}.json", std::process::id()))
+}
+
+// GET /config/base64:{key} recorded against the mock server replays without it
+#[test]
+fn record_and_replay() {
+    let path = cassette_path("record_and_replay");
+    let mock = MOCK_SERVER.mock(|w, t| {
+        w.method(GET)
+            .path("/v1/config/base64:Y2Fzc2V0dGU")
+            .header("authorization", "Bearer cassette-token");
+        t.status(200)
+            .header("x-tenant", "tnt-secret")
+            .json_body(json!({"key": "Y2Fzc2V0dGU", "value": "dmFsdWU"}));
+    });
+
+    let recorder =
+        Cassette::record(&path, HttpClient::new(HttpOptions::new()).unwrap()).redact("tnt-secret");
+    let req = MetadataRequest::builder()
+        .token("cassette-token")
+        .encoded_key("Y2Fzc2V0dGU")
+        .base_url(MOCK_SERVER.base_url())
+        .transport(recorder)
+        .build()
+        .unwrap();
+    assert_eq!(req.get_value().unwrap(), Value::from_encoded("dmFsdWU"));
+    mock.assert_hits(1);
+
+    let recorded = std::fs::read_to_string(&path).unwrap();
+    assert!(!recorded.contains("cassette-token"));
+    assert!(!recorded.contains("tnt-secret"));
+
+    let player = Cassette::replay(&path).unwrap().redact("tnt-secret");
+    let req = MetadataRequest::builder()
+        .token("another-token")
+        .encoded_key("Y2Fzc2V0dGU")
+        .base_url(MOCK_SERVER.base_url())
+        .transport(player)
+        .build()
+        .unwrap();
+    assert_eq!(req.get_value().unwrap(), Value::from_encoded("dmFsdWU"));
+    // The response was replayed rather than requested again
+    mock.assert_hits(1);
+
+    // Every recorded response is only replayed once
+    assert!(req.get_value().is_err());
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn replay_missing_cassette() {
+    assert!(Cassette::replay(cassette_path("missing")).is_err());
+}
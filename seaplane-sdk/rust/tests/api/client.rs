@@ -0,0 +1,95 @@
+use httpmock::prelude::*;
+use seaplane::api::SeaplaneClient;
+use serde_json::json;
+
+use super::MOCK_SERVER;
+
+// A single token is fetched and shared by the requests handed out to each service
+#[test]
+fn shared_client() {
+    let token_mock = MOCK_SERVER.mock(|when, then| {
+        when.method(POST)
+            .path("/v1/token")
+            .header("authorization", "Bearer jkl012");
+        then.status(201).json_body(
+            json!({"token": "shared", "tenant": "tnt-abcdef1234567890", "subdomain": "pequod"}),
+        );
+    });
+    let metadata_mock = MOCK_SERVER.mock(|when, then| {
+        when.method(GET)
+            .path("/v1/config/base64:Y2xpZW50")
+            .header("authorization", "Bearer shared");
+        then.status(200)
+            .json_body(json!({"key": "Y2xpZW50", "value": "dmFsdWU"}));
+    });
+    let locks_mock = MOCK_SERVER.mock(|when, then| {
+        when.method(GET)
+            .path("/v1/locks/base64:Y2xpZW50")
+            .header("authorization", "Bearer shared");
+        then.status(200).json_body(json!({
+            "name": "Y2xpZW50",
+            "id": "D4lbVpdBE_U",
+            "info": {"ttl": 1000, "client-id": "test", "ip": ""}
+        }));
+    });
+
+    let client = SeaplaneClient::builder()
+        .api_key("jkl012")
+        .identity_url(MOCK_SERVER.base_url())
+        .metadata_url(MOCK_SERVER.base_url())
+        .build()
+        .unwrap();
+
+    let req = client.metadata().encoded_key("Y2xpZW50").build().unwrap();
+    assert!(req.get_value().is_ok());
+    let req = client
+        .locks()
+        .encoded_lock_name("Y2xpZW50")
+        .build()
+        .unwrap();
+    assert!(req.get_lock_info().is_ok());
+
+    metadata_mock.assert();
+    locks_mock.assert();
+    token_mock.assert_hits(1);
+}
+
+// Token requests made through the client follow its retry policy
+#[test]
+fn identity_shares_retry_policy() {
+    use std::time::Duration;
+
+    use seaplane::api::RetryPolicy;
+
+    let token_mock = MOCK_SERVER.mock(|when, then| {
+        when.method(POST)
+            .path("/v1/token")
+            .header("authorization", "Bearer mno345");
+        then.status(503);
+    });
+
+    let client = SeaplaneClient::builder()
+        .api_key("mno345")
+        .identity_url(MOCK_SERVER.base_url())
+        .retry_policy(
+            RetryPolicy::new()
+                .max_attempts(2)
+                .initial_backoff(Duration::from_millis(1))
+                .jitter(false),
+        )
+        .build()
+        .unwrap();
+
+    let req = client.identity().build().unwrap();
+    assert!(req.access_token_json().is_err());
+    token_mock.assert_hits(2);
+}
+
+#[test]
+fn client_invalid_url() {
+    let res = SeaplaneClient::builder()
+        .token("abc123")
+        .compute_url("not a url")
+        .build();
+    assert!(res.is_err());
+}
@@ -1,5 +1,11 @@
 use httpmock::{prelude::*, Method, Then, When};
-use seaplane::api::compute::v2::{response::*, Flight, Formation, FormationsRequest};
+use seaplane::{
+    api::compute::{
+        error::ComputeError,
+        v2::{response::*, Flight, Formation, FormationsRequest},
+    },
+    error::SeaplaneError,
+};
 
 use super::MOCK_SERVER;
 
@@ -87,6 +93,107 @@ fn get_all_formations() {
     assert_eq!(resp, resp_t);
 }
 
+// Iterating over all Formations follows the whole `next` link, including its path
+#[test]
+fn iter_all_follows_next_link() {
+    let server = MockServer::start();
+    let page = |name: &str, next: Option<String>| {
+        let mut formation = build_formation();
+        formation.name = name.to_owned();
+        serde_json::json!({
+            "objects": [formation],
+            "meta": { "total": 2, "next": next, "prev": null },
+        })
+    };
+    let first = server.mock(|w, t| {
+        w.method(GET)
+            .path("/v2beta/formations")
+            .query_param("limit", "1");
+        then(t, page("first", Some(server.url("/v2beta/formations/pages/2?cursor=abc"))));
+    });
+    let second = server.mock(|w, t| {
+        w.method(GET)
+            .path("/v2beta/formations/pages/2")
+            .query_param("cursor", "abc")
+            .query_param("limit", "1");
+        then(t, page("second", None));
+    });
+
+    let req = FormationsRequest::builder()
+        .token("abc123")
+        .base_url(server.base_url())
+        .build()
+        .unwrap();
+    let names = req
+        .iter_all()
+        .page_size(1)
+        .map(|f| f.map(|f| f.name))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    first.assert();
+    second.assert();
+    assert_eq!(names, ["first", "second"]);
+}
+
+// A `next` link to a different origin is never followed, so the token is not sent elsewhere
+#[test]
+fn iter_all_rejects_foreign_next_link() {
+    let server = MockServer::start();
+    let mut formation = build_formation();
+    formation.name = "first".to_owned();
+    let mock = server.mock(|w, t| {
+        w.method(GET).path("/v2beta/formations");
+        then(
+            t,
+            serde_json::json!({
+                "objects": [formation],
+                "meta": { "total": 2, "next": "https://example.com/v2beta/formations?offset=1" },
+            }),
+        );
+    });
+
+    let req = FormationsRequest::builder()
+        .token("abc123")
+        .base_url(server.base_url())
+        .build()
+        .unwrap();
+    let res: Vec<_> = req.iter_all().collect();
+
+    mock.assert();
+    assert_eq!(res.len(), 1);
+    assert!(matches!(res[0], Err(SeaplaneError::ComputeRequest(ComputeError::ForeignPageLink(_)))));
+}
+
+// A `next` link back to a page already fetched ends the iterator rather than looping forever
+#[test]
+fn iter_all_rejects_self_referencing_next_link() {
+    let server = MockServer::start();
+    let mut formation = build_formation();
+    formation.name = "first".to_owned();
+    let mock = server.mock(|w, t| {
+        w.method(GET).path("/v2beta/formations");
+        then(
+            t,
+            serde_json::json!({
+                "objects": [formation],
+                "meta": { "total": 2, "next": server.url("/v2beta/formations") },
+            }),
+        );
+    });
+
+    let req = FormationsRequest::builder()
+        .token("abc123")
+        .base_url(server.base_url())
+        .build()
+        .unwrap();
+    let res: Vec<_> = req.iter_all().collect();
+
+    mock.assert();
+    assert_eq!(res.len(), 1);
+    assert!(matches!(res[0], Err(SeaplaneError::ComputeRequest(ComputeError::PageLinkCycle(_)))));
+}
+
 // GET /formations/OID
 #[test]
 fn get_formation() {
@@ -158,3 +265,32 @@ fn delete_formation() {
     // Ensure the endpoint was hit
     mock.assert();
 }
+
+// GET /formations/OID
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn get_formation_async() {
+    let mut frm = build_formation();
+    frm.oid = Some("frm-0oljkb3rpl9fd7ilhr4ldf4ktg".parse().unwrap());
+    let resp_body = serde_json::to_value(&frm).unwrap();
+
+    let mock = MOCK_SERVER
+        .mock_async(|w, t| {
+            when(w, GET, "/v2beta/formations/frm-0oljkb3rpl9fd7ilhr4ldf4ktg");
+            then(t, resp_body);
+        })
+        .await;
+
+    let req = FormationsRequest::builder()
+        .token("abc123")
+        .base_url(MOCK_SERVER.base_url())
+        .formation_id("frm-0oljkb3rpl9fd7ilhr4ldf4ktg".parse().unwrap())
+        .build()
+        .unwrap();
+    let resp = req.get_async().await.unwrap();
+
+    // Ensure the endpoint was hit
+    mock.assert_async().await;
+
+    assert_eq!(resp, frm);
+}
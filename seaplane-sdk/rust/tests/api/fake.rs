@@ -0,0 +1,780 @@
+#[cfg(any(feature = "compute_api_v2", feature = "locks_api_v1"))]
+use std::time::Duration;
+
+#[cfg(feature = "locks_api_v1")]
+use seaplane::api::locks::v1::{HeldLock, LockName, LocksRequest};
+#[cfg(any(feature = "metadata_api_v1", feature = "restrict_api_v1"))]
+use seaplane::api::shared::v1::RangeQueryContext;
+#[cfg(any(feature = "compute_api_v2", feature = "locks_api_v1"))]
+use seaplane::api::HttpRequest;
+#[cfg(feature = "metadata_api_v1")]
+use seaplane::api::{
+    metadata::v1::{Key, MetadataRequest},
+    shared::v1::Directory,
+};
+#[cfg(feature = "restrict_api_v1")]
+use seaplane::api::{
+    restrict::v1::{RestrictRequest, RestrictedDirectory, RestrictionDetails},
+    shared::v1::Region,
+};
+#[cfg(any(
+    feature = "compute_api_v2",
+    feature = "locks_api_v1",
+    feature = "metadata_api_v1",
+    feature = "restrict_api_v1"
+))]
+use seaplane::{error::ErrorKind, testing::FakeSeaplane};
+
+// Range queries follow `next_key` through every page of a directory, and only that directory
+#[cfg(feature = "metadata_api_v1")]
+#[test]
+fn config_range_paging() {
+    let fake = FakeSeaplane::new().page_size(2);
+    let server = fake.serve().unwrap();
+    let request = |key: &str| {
+        MetadataRequest::builder()
+            .token("abc123")
+            .base_url(server.url())
+            .allow_http(true)
+            .encoded_key(Key::from_unencoded(key).encoded())
+            .build()
+            .unwrap()
+    };
+
+    for key in ["dir/e", "dir/a", "dir/c", "dir/b", "dir/d", "dir", "dirt/a", "other"] {
+        request(key)
+            .put_value_unencoded(key.to_uppercase())
+            .unwrap();
+    }
+    assert_eq!(request("dir/c").get_value().unwrap().decode(), b"DIR/C");
+
+    let mut context = RangeQueryContext::new();
+    context.set_directory(Directory::from_unencoded("dir"));
+    let mut req = MetadataRequest::builder()
+        .token("abc123")
+        .base_url(server.url())
+        .allow_http(true)
+        .range(context)
+        .build()
+        .unwrap();
+    let keys: Vec<_> = req
+        .get_all_pages()
+        .unwrap()
+        .into_iter()
+        .map(|kv| kv.key.decode())
+        .collect();
+    assert_eq!(keys, [&b"dir/a"[..], b"dir/b", b"dir/c", b"dir/d", b"dir/e"]);
+
+    request("dir/c").delete_value().unwrap();
+    let err = request("dir/c").get_value().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
+
+// Locks can only be held once, expire after their TTL and get a new sequencer on each acquire
+#[cfg(feature = "locks_api_v1")]
+#[test]
+fn lock_ttl_and_sequencer() {
+    let fake = FakeSeaplane::new();
+    let request = || {
+        LocksRequest::builder()
+            .token("abc123")
+            .transport(fake.clone())
+            .lock_name(LockName::from_unencoded("job"))
+            .build()
+            .unwrap()
+    };
+
+    let first = request().acquire(30, "client-a").unwrap();
+    assert_eq!(first.sequencer(), 1);
+    let info = request().get_lock_info().unwrap();
+    assert_eq!(&info.id, first.id());
+    assert_eq!(info.info.client_id, "client-a");
+
+    let err = request().acquire(30, "client-b").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Conflict);
+
+    // Renewing pushes the expiry back
+    let held = |lock: &HeldLock| {
+        LocksRequest::builder()
+            .token("abc123")
+            .transport(fake.clone())
+            .held_lock(HeldLock::new(lock.name().clone(), lock.id().clone(), lock.sequencer()))
+            .build()
+            .unwrap()
+    };
+    fake.advance(Duration::from_secs(20));
+    held(&first).renew(30).unwrap();
+    fake.advance(Duration::from_secs(20));
+    assert!(request().get_lock_info().is_ok());
+
+    fake.advance(Duration::from_secs(11));
+    let err = request().get_lock_info().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    let second = request().acquire(30, "client-b").unwrap();
+    assert_eq!(second.sequencer(), 2);
+
+    // The expired lock can no longer be released
+    let err = held(&first).release().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Conflict);
+    held(&second).release().unwrap();
+
+    let unauthenticated = HttpRequest::new(
+        reqwest::Method::GET,
+        "https://metadata.cplane.cloud/v1/locks/".parse().unwrap(),
+        Default::default(),
+        None,
+    );
+    assert_eq!(fake.handle(&unauthenticated).status, reqwest::StatusCode::UNAUTHORIZED);
+}
+
+// Restrictions across all APIs are paged through in order of API and then directory
+#[cfg(feature = "restrict_api_v1")]
+#[test]
+fn restrictions_crud() {
+    let fake = FakeSeaplane::new().page_size(2);
+    let single = |api: &str, dir: &str| {
+        RestrictRequest::builder()
+            .token("abc123")
+            .transport(fake.clone())
+            .single_restriction(api, RestrictedDirectory::from_unencoded(dir).encoded())
+            .build()
+            .unwrap()
+    };
+    let details = RestrictionDetails::builder()
+        .add_allowed_region(Region::XE)
+        .build()
+        .unwrap();
+
+    single("locks", "b")
+        .set_restriction(details.clone())
+        .unwrap();
+    single("config", "b")
+        .set_restriction(details.clone())
+        .unwrap();
+    single("config", "a")
+        .set_restriction(details.clone())
+        .unwrap();
+    assert_eq!(single("config", "a").get_restriction().unwrap().details, details);
+
+    let mut req = RestrictRequest::builder()
+        .token("abc123")
+        .transport(fake.clone())
+        .all_range::<String>(None, RangeQueryContext::new())
+        .build()
+        .unwrap();
+    let all: Vec<_> = req
+        .get_all_pages()
+        .unwrap()
+        .into_iter()
+        .map(|r| (r.api.to_string(), r.directory.decode()))
+        .collect();
+    assert_eq!(
+        all,
+        [
+            ("Config".to_string(), b"a".to_vec()),
+            ("Config".to_string(), b"b".to_vec()),
+            ("Locks".to_string(), b"b".to_vec()),
+        ]
+    );
+
+    single("config", "a").delete_restriction().unwrap();
+    let err = single("config", "a").get_restriction().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
+
+// Formations are created with new IDs, and the client authenticates with the fake Identity API
+#[cfg(all(feature = "compute_api_v2", feature = "identity_api_v1"))]
+#[test]
+fn formations_crud() {
+    use seaplane::api::{
+        compute::v2::{Flight, Formation},
+        SeaplaneClient,
+    };
+
+    let fake = FakeSeaplane::new().api_key("jkl012");
+    let server = fake.serve().unwrap();
+    let client = SeaplaneClient::builder()
+        .api_key("jkl012")
+        .compute_url(server.url())
+        .identity_url(server.url())
+        .allow_http(true)
+        .build()
+        .unwrap();
+
+    let formation = Formation::builder()
+        .name("example")
+        .add_flight(Flight::new("web", "foo.com/web:latest"))
+        .gateway_flight("web")
+        .build()
+        .unwrap();
+    let created = client
+        .formations()
+        .build()
+        .unwrap()
+        .create(&formation)
+        .unwrap();
+    let oid = created.oid.unwrap();
+    assert!(created.flights[0].oid.is_some());
+
+    let err = client
+        .formations()
+        .build()
+        .unwrap()
+        .create(&formation)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Conflict);
+
+    let req = client.formations().formation_id(oid).build().unwrap();
+    assert_eq!(req.get().unwrap().name, "example");
+    let all = client.formations().build().unwrap().get_all().unwrap();
+    assert_eq!(all.meta.total, 1);
+    assert_eq!(all.objects[0].oid, Some(oid));
+
+    req.delete().unwrap();
+    assert_eq!(req.get().unwrap_err().kind(), ErrorKind::NotFound);
+
+    let err = SeaplaneClient::builder()
+        .api_key("wrong")
+        .compute_url(server.url())
+        .identity_url(server.url())
+        .allow_http(true)
+        .build()
+        .unwrap()
+        .formations()
+        .build()
+        .unwrap()
+        .get_all()
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Unauthorized);
+}
+
+// Creating a Formation is safe to retry with an idempotency key, even when the response to the
+// first attempt was lost
+#[cfg(feature = "compute_api_v2")]
+#[test]
+fn create_formation_idempotently() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use seaplane::{
+        api::{
+            compute::v2::{Flight, Formation, FormationsRequest},
+            HttpResponse, RetryPolicy, Transport, TransportError,
+        },
+        error::Result,
+    };
+
+    // Passes requests on to the fake, but loses the response to the first create
+    #[derive(Debug)]
+    struct LoseFirstCreate {
+        fake: FakeSeaplane,
+        lost: AtomicBool,
+    }
+
+    impl Transport for LoseFirstCreate {
+        fn send(&self, req: HttpRequest) -> Result<HttpResponse> {
+            let create = req.method == reqwest::Method::POST;
+            let resp = self.fake.send(req)?;
+            if create && !self.lost.swap(true, Ordering::SeqCst) {
+                return Err(TransportError::new(ErrorKind::Timeout, "connection reset").into());
+            }
+            Ok(resp)
+        }
+    }
+
+    let formation = Formation::builder()
+        .name("example")
+        .add_flight(Flight::new("web", "foo.com/web:latest"))
+        .build()
+        .unwrap();
+    let request = |fake: &FakeSeaplane, key: Option<&str>| {
+        let builder = FormationsRequest::builder()
+            .token("abc123")
+            .retry_policy(RetryPolicy::new().initial_backoff(Duration::from_millis(1)))
+            .transport(LoseFirstCreate { fake: fake.clone(), lost: AtomicBool::new(false) });
+        match key {
+            Some(key) => builder.idempotency_key(key),
+            None => builder,
+        }
+        .build()
+        .unwrap()
+    };
+    let all = |fake: &FakeSeaplane| {
+        FormationsRequest::builder()
+            .token("abc123")
+            .transport(fake.clone())
+            .build()
+            .unwrap()
+            .get_all()
+            .unwrap()
+    };
+
+    // Without a key the create is not retried
+    let fake = FakeSeaplane::new();
+    let err = request(&fake, None).create(&formation).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Timeout);
+    assert_eq!(all(&fake).meta.total, 1);
+
+    // With a key the retry returns the Formation created by the lost attempt
+    let fake = FakeSeaplane::new();
+    let created = request(&fake, Some("key-1")).create(&formation).unwrap();
+    let listed = all(&fake);
+    assert_eq!(listed.meta.total, 1);
+    assert_eq!(listed.objects[0].oid, created.oid);
+
+    // Looking up the Formation by name after a lost response
+    let fake = FakeSeaplane::new();
+    let found = FormationsRequest::builder()
+        .token("abc123")
+        .retry_policy(RetryPolicy::none())
+        .transport(LoseFirstCreate { fake: fake.clone(), lost: AtomicBool::new(false) })
+        .build()
+        .unwrap()
+        .create_or_find(&formation)
+        .unwrap();
+    assert!(found.oid.is_some());
+
+    // A Formation which already exists was not created by this request
+    let err = FormationsRequest::builder()
+        .token("abc123")
+        .transport(fake.clone())
+        .build()
+        .unwrap()
+        .create_or_find(&formation)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Conflict);
+
+    // Nor is one of the same name with another configuration found after a lost response
+    let other = Formation::builder()
+        .name("example")
+        .add_flight(Flight::new("web", "foo.com/web:v2"))
+        .build()
+        .unwrap();
+    let err = request(&fake, None).create_or_find(&other).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Conflict);
+    assert_eq!(all(&fake).meta.total, 1);
+}
+
+// Iterating over all Formations follows the `next` link of every page
+#[cfg(feature = "compute_api_v2")]
+#[test]
+fn iter_all_formations() {
+    use seaplane::api::compute::v2::{Flight, Formation, FormationsRequest};
+
+    let fake = FakeSeaplane::new().page_size(3);
+    let server = fake.serve().unwrap();
+    let req = FormationsRequest::builder()
+        .token("abc123")
+        .base_url(server.url())
+        .allow_http(true)
+        .build()
+        .unwrap();
+    let names: Vec<_> = (0..7).map(|i| format!("formation-{i}")).collect();
+    for name in &names {
+        let formation = Formation::builder()
+            .name(name)
+            .add_flight(Flight::new("web", "foo.com/web:latest"))
+            .build()
+            .unwrap();
+        req.create(&formation).unwrap();
+    }
+
+    // The page size hint is capped by the server's page size
+    for size in [2, 10] {
+        let mut formations = req.iter_all().page_size(size);
+        assert_eq!(formations.total(), None);
+        let listed = formations
+            .by_ref()
+            .map(|f| f.map(|f| f.name))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(listed, names);
+        assert_eq!(formations.total(), Some(7));
+        assert!(formations.next().is_none());
+    }
+    assert_eq!(req.get_all().unwrap().objects.len(), 3);
+
+    let err = FormationsRequest::builder()
+        .token("abc123")
+        .base_url("http://127.0.0.1:1/")
+        .allow_http(true)
+        .retry_policy(seaplane::api::RetryPolicy::none())
+        .build()
+        .unwrap()
+        .iter_all()
+        .collect::<Vec<_>>();
+    assert_eq!(err.len(), 1);
+    assert_eq!(err[0].as_ref().unwrap_err().kind(), ErrorKind::Connect);
+}
+
+// Formations can be addressed by their name, which is resolved through the listing
+#[cfg(feature = "compute_api_v2")]
+#[test]
+fn formations_by_name() {
+    use seaplane::{
+        api::compute::{
+            error::ComputeError,
+            v2::{Flight, Formation, FormationsRequest},
+        },
+        error::SeaplaneError,
+    };
+
+    let fake = FakeSeaplane::new().page_size(1);
+    let req = FormationsRequest::builder()
+        .token("abc123")
+        .transport(fake)
+        .build()
+        .unwrap();
+    for name in ["first", "second"] {
+        let formation = Formation::builder()
+            .name(name)
+            .add_flight(Flight::new("web", "foo.com/web:latest"))
+            .build()
+            .unwrap();
+        req.create(&formation).unwrap();
+    }
+
+    let second = req.get_by_name("second").unwrap();
+    assert_eq!(second.name, "second");
+    assert!(second.oid.is_some());
+
+    req.delete_by_name("second").unwrap();
+    let err = req.get_by_name("second").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert_eq!(
+        err,
+        SeaplaneError::ComputeRequest(ComputeError::FormationNotFound("second".into()))
+    );
+    assert_eq!(req.delete_by_name("second").unwrap_err().kind(), ErrorKind::NotFound);
+    assert_eq!(req.get_by_name("first").unwrap().name, "first");
+
+    // Invalid names are rejected without a request
+    let err = req.get_by_name("Not--Valid").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidRequest);
+}
+
+// Updates keep the IDs of the Formation and are rejected when it changed since it was fetched
+#[cfg(feature = "compute_api_v2")]
+#[test]
+fn update_formation_if_match() {
+    use seaplane::api::compute::v2::{Flight, FlightStatus, Formation, FormationsRequest};
+
+    let fake = FakeSeaplane::new();
+    let formation = Formation::builder()
+        .name("example")
+        .add_flight(Flight::new("web", "foo.com/web:v1"))
+        .add_flight(Flight::new("db", "foo.com/db:v1"))
+        .gateway_flight("web")
+        .build()
+        .unwrap();
+    let created = FormationsRequest::builder()
+        .token("abc123")
+        .transport(fake.clone())
+        .build()
+        .unwrap()
+        .create(&formation)
+        .unwrap();
+    let req = FormationsRequest::builder()
+        .token("abc123")
+        .transport(fake)
+        .formation_id(created.oid.unwrap())
+        .build()
+        .unwrap();
+
+    let fetched = req.get_with_response().unwrap();
+    let etag = fetched.etag().unwrap().to_owned();
+    let mut changed = fetched.into_body();
+    changed.flights[0] = Flight::new("web", "foo.com/web:v2");
+    changed.add_flight(Flight::new("cache", "foo.com/cache:v1"));
+
+    let updated = req.update_with_response(&changed, Some(&etag)).unwrap();
+    assert_ne!(updated.etag(), Some(etag.as_str()));
+    let updated = updated.into_body();
+    assert_eq!(updated.oid, created.oid);
+    assert_eq!(updated.url, created.url);
+    assert_eq!(updated.flights.len(), 3);
+    assert_eq!(updated.flights[0].oid, created.flights[0].oid);
+    assert_eq!(updated.flights[0].status, FlightStatus::Starting);
+    assert_eq!(updated.flights[1], created.flights[1]);
+    assert!(updated.flights[2].oid.is_some());
+    assert_eq!(req.get().unwrap(), updated);
+
+    // The Formation changed since `etag`, so a second update with it is rejected
+    let err = req.update(&formation, Some(&etag)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PreconditionFailed);
+    assert_eq!(req.get().unwrap(), updated);
+
+    // Without a precondition the Formation is replaced unconditionally
+    let replaced = req.update(&formation, None).unwrap();
+    assert_eq!(replaced.flights.len(), 2);
+}
+
+// Waiting for a Formation reports the progress of each Flight until all are healthy, and names
+// the Flights which are unhealthy or still starting on failure
+#[cfg(feature = "compute_api_v2")]
+#[test]
+fn wait_until_formation_healthy() {
+    use seaplane::{
+        api::compute::{
+            error::ComputeError,
+            v2::{Flight, FlightStatus, Formation, FormationsRequest},
+        },
+        error::SeaplaneError,
+    };
+
+    let fake = FakeSeaplane::new();
+    let create = |name: &str| {
+        let formation = Formation::builder()
+            .name(name)
+            .add_flight(Flight::new("web", "foo.com/web:latest"))
+            .add_flight(Flight::new("db", "foo.com/db:latest"))
+            .build()
+            .unwrap();
+        let oid = FormationsRequest::builder()
+            .token("abc123")
+            .transport(fake.clone())
+            .build()
+            .unwrap()
+            .create(&formation)
+            .unwrap()
+            .oid
+            .unwrap();
+        FormationsRequest::builder()
+            .token("abc123")
+            .transport(fake.clone())
+            .formation_id(oid)
+            .build()
+            .unwrap()
+    };
+    let (timeout, interval) = (Duration::from_secs(5), Duration::from_millis(1));
+
+    // The Flights become healthy one after the other as they are reported to be starting
+    let req = create("healthy");
+    let mut progress = Vec::new();
+    let formation = req
+        .wait_until_healthy(timeout, interval, |f: &Flight| {
+            progress.push((f.name.clone(), f.status));
+            if f.status == FlightStatus::Starting {
+                fake.set_flight_status("healthy", &f.name, FlightStatus::Healthy);
+            }
+        })
+        .unwrap();
+    assert!(formation
+        .flights
+        .iter()
+        .all(|f| f.status == FlightStatus::Healthy));
+    assert_eq!(
+        progress,
+        [
+            ("web".into(), FlightStatus::Starting),
+            ("db".into(), FlightStatus::Starting),
+            ("web".into(), FlightStatus::Healthy),
+            ("db".into(), FlightStatus::Healthy),
+        ]
+    );
+
+    let req = create("unhealthy");
+    fake.set_flight_status("unhealthy", "web", FlightStatus::Healthy);
+    fake.set_flight_status("unhealthy", "db", FlightStatus::Unhealthy);
+    let err = req
+        .wait_until_healthy(timeout, interval, |_: &Flight| ())
+        .unwrap_err();
+    assert_eq!(
+        err,
+        SeaplaneError::ComputeRequest(ComputeError::FlightsUnhealthy(vec!["db".into()]))
+    );
+
+    let req = create("starting");
+    fake.set_flight_status("starting", "web", FlightStatus::Unhealthy);
+    let err = req
+        .wait_until_healthy(Duration::from_millis(20), interval, |_: &Flight| ())
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Timeout);
+    assert_eq!(
+        err,
+        SeaplaneError::ComputeRequest(ComputeError::HealthTimeout(vec!["web".into(), "db".into()]))
+    );
+}
+
+// Applying desired Formations creates, updates and deletes the deployed ones to match, unless it
+// is a dry run
+#[cfg(feature = "compute_api_v2")]
+#[test]
+fn plan_and_apply_formations() {
+    use seaplane::api::compute::v2::{Change, Flight, Formation, FormationsRequest};
+
+    let fake = FakeSeaplane::new();
+    let req = FormationsRequest::builder()
+        .token("abc123")
+        .transport(fake)
+        .build()
+        .unwrap();
+    let formation = |name: &str, flights: &[(&str, &str)]| {
+        let mut builder = Formation::builder().name(name);
+        for (flight, image) in flights {
+            builder = builder.add_flight(Flight::new(*flight, image));
+        }
+        builder.build().unwrap()
+    };
+    req.create(&formation("web", &[("web", "foo.com/web:v1")]))
+        .unwrap();
+    req.create(&formation("old", &[("web", "foo.com/web:v1")]))
+        .unwrap();
+
+    let desired = [
+        formation("web", &[("web", "foo.com/web:v2"), ("worker", "foo.com/worker:v1")]),
+        formation("new", &[("cron", "foo.com/cron:v1")]),
+    ];
+    let deployed_names = || {
+        let mut names: Vec<_> = req.iter_all().map(|f| f.unwrap().name).collect();
+        names.sort();
+        names
+    };
+
+    let plan = req.apply(&desired, true).unwrap();
+    assert_eq!(
+        plan.to_string(),
+        "~ update Formation web
+    + add Flight worker
+    ~ change image of Flight web: foo.com/web:v1 -> foo.com/web:v2
++ create Formation new (Flights: cron)
+- delete Formation old
+"
+    );
+    assert!(matches!(plan.changes[0], Change::Update { .. }));
+    assert_eq!(deployed_names(), ["old", "web"]);
+
+    assert_eq!(req.apply(&desired, false).unwrap(), plan);
+    assert_eq!(deployed_names(), ["new", "web"]);
+    let web = req.get_by_name("web").unwrap();
+    assert_eq!(web.flights.len(), 2);
+    assert_eq!(web.flights[0].image_str(), "foo.com/web:v2");
+    assert!(req.plan(&desired).unwrap().is_empty());
+}
+
+// Applying a plan fails rather than overwrites a Formation updated or adopts one created since it
+// was planned, and creates Formations only once however many times the plan is applied
+#[cfg(feature = "compute_api_v2")]
+#[test]
+fn apply_stale_plan() {
+    use seaplane::api::compute::v2::{Change, Flight, Formation, FormationsRequest};
+
+    let fake = FakeSeaplane::new();
+    let req = FormationsRequest::builder()
+        .token("abc123")
+        .transport(fake.clone())
+        .idempotency_key("deploy-1")
+        .build()
+        .unwrap();
+    let formation = |name: &str, image: &str| {
+        Formation::builder()
+            .name(name)
+            .add_flight(Flight::new("web", image))
+            .build()
+            .unwrap()
+    };
+    let created = req.create(&formation("web", "foo.com/web:v1")).unwrap();
+
+    let desired = [formation("web", "foo.com/web:v2"), formation("new", "foo.com/new:v1")];
+    let plan = req.plan(&desired).unwrap();
+    assert!(matches!(&plan.changes[0], Change::Update { etag: Some(_), .. }));
+
+    // The Formation is changed by someone else after planning
+    FormationsRequest::builder()
+        .token("abc123")
+        .transport(fake.clone())
+        .formation_id(created.oid.unwrap())
+        .build()
+        .unwrap()
+        .update(&formation("web", "foo.com/web:v3"), None)
+        .unwrap();
+
+    // The Formation is created first, and then the stale update fails
+    let mut plan = plan;
+    plan.changes.reverse();
+    let err = req.apply_plan(&plan).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PreconditionFailed);
+    assert_eq!(req.get_by_name("web").unwrap().flights[0].image_str(), "foo.com/web:v3");
+
+    // Applying the plan again doesn't create the Formation twice
+    let new = req.get_by_name("new").unwrap();
+    assert!(req.apply_plan(&plan).is_err());
+    let names: Vec<_> = req.iter_all().map(|f| f.unwrap().name).collect();
+    assert_eq!(names, ["web", "new"]);
+    assert_eq!(req.get_by_name("new").unwrap().oid, new.oid);
+
+    // A Formation created by someone else after planning is not taken over
+    let plan = req.plan(&[formation("other", "foo.com/other:v1")]).unwrap();
+    FormationsRequest::builder()
+        .token("abc123")
+        .transport(fake)
+        .build()
+        .unwrap()
+        .create(&formation("other", "foo.com/other:v2"))
+        .unwrap();
+    let err = req.apply_plan(&plan).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Conflict);
+    assert_eq!(req.get_by_name("other").unwrap().flights[0].image_str(), "foo.com/other:v2");
+
+    // Planning again picks up the change
+    req.apply(&desired, false).unwrap();
+    assert_eq!(req.get_by_name("web").unwrap().flights[0].image_str(), "foo.com/web:v2");
+}
+
+// The Flights of a Formation can be listed, inspected and restarted one at a time
+#[cfg(feature = "compute_api_v2")]
+#[test]
+fn flights_list_inspect_restart() {
+    use seaplane::api::compute::v2::{Flight, FlightStatus, Formation, FormationsRequest};
+
+    let fake = FakeSeaplane::new();
+    let formation = Formation::builder()
+        .name("example")
+        .add_flight(Flight::new("web", "foo.com/web:latest"))
+        .add_flight(Flight::new("db", "foo.com/db:latest"))
+        .build()
+        .unwrap();
+    let created = FormationsRequest::builder()
+        .token("abc123")
+        .transport(fake.clone())
+        .build()
+        .unwrap()
+        .create(&formation)
+        .unwrap();
+    fake.set_flight_status("example", "web", FlightStatus::Healthy);
+    fake.set_flight_status("example", "db", FlightStatus::Healthy);
+    let req = FormationsRequest::builder()
+        .token("abc123")
+        .transport(fake)
+        .formation_id(created.oid.unwrap())
+        .build()
+        .unwrap();
+
+    let flights = req.get_flights().unwrap();
+    let names: Vec<_> = flights.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, ["web", "db"]);
+    assert!(flights.iter().all(|f| f.status == FlightStatus::Healthy));
+
+    let web = flights[0].oid.unwrap();
+    let before = req.get_flight(&web).unwrap();
+    assert_eq!(before.flight, flights[0]);
+    assert_eq!(before.instances.len(), 1);
+
+    req.restart_flight(&web).unwrap();
+    let after = req.get_flight(&web).unwrap();
+    assert_eq!(after.flight.status, FlightStatus::Starting);
+    assert_ne!(after.instances[0].id, before.instances[0].id);
+    let db = req.get_flight(&flights[1].oid.unwrap()).unwrap();
+    assert_eq!(db.flight.status, FlightStatus::Healthy);
+
+    let err = req
+        .get_flight(&"flt-6f9asfo8ql0ar3mihb0ruv14i0".parse().unwrap())
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    let unknown = FormationsRequest::builder()
+        .token("abc123")
+        .transport(FakeSeaplane::new())
+        .build()
+        .unwrap();
+    assert_eq!(unknown.get_flights().unwrap_err().kind(), ErrorKind::InvalidRequest);
+}
@@ -1,9 +1,15 @@
+use std::time::Duration;
+
 use httpmock::{prelude::*, Method, Then, When};
-use seaplane::api::{
-    locks::v1::{
-        HeldLock, LockId, LockInfo, LockInfoInner, LockInfoRange, LockName, LocksRequestBuilder,
+use seaplane::{
+    api::{
+        locks::v1::{
+            HeldLock, LockId, LockInfo, LockInfoInner, LockInfoRange, LockName, LocksRequestBuilder,
+        },
+        shared::v1::{Directory, RangeQueryContext},
+        RetryPolicy,
     },
-    shared::v1::{Directory, RangeQueryContext},
+    error::{ErrorKind, SeaplaneError},
 };
 use serde_json::json;
 
@@ -55,6 +61,48 @@ fn acquire_lock() {
     assert_eq!(lock, resp);
 }
 
+// POST /locks/base64:{key}?ttl={ttl}&client-id={client-id}
+#[test]
+fn acquire_lock_with_response() {
+    let mock = MOCK_SERVER.mock(|w, t| {
+        when(w, POST, "/v1/locks/base64:Zm9vMg")
+            .query_param("ttl", "10")
+            .query_param("client-id", "test-client");
+        then(t, json!({"id": "D4lbVpdBE_U", "sequencer": 3})).header("x-request-id", "req-456");
+    });
+
+    let req = partial_build().encoded_lock_name("Zm9vMg").build().unwrap();
+    let resp = req.acquire_with_response(10, "test-client").unwrap();
+
+    // Ensure the endpoint was hit
+    mock.assert();
+
+    assert_eq!(resp.status, 200);
+    assert_eq!(resp.request_id(), Some("req-456"));
+    let lock =
+        HeldLock::new(LockName::from_encoded("Zm9vMg"), LockId::from_encoded("D4lbVpdBE_U"), 3);
+    assert_eq!(resp.into_body(), lock);
+}
+
+// The request ID of a failed request is kept on the error
+#[test]
+fn acquire_lock_error_request_id() {
+    let mock = MOCK_SERVER.mock(|w, t| {
+        when(w, POST, "/v1/locks/base64:Zm9vMw");
+        t.status(409)
+            .header("x-request-id", "req-789")
+            .json_body(json!({"title": "Conflict", "status": 409}));
+    });
+
+    let req = partial_build().encoded_lock_name("Zm9vMw").build().unwrap();
+    let err = req.acquire(10, "test-client").unwrap_err();
+
+    mock.assert();
+
+    let SeaplaneError::ApiResponse(e) = err else { panic!("expected an API error") };
+    assert_eq!(e.request_id(), Some("req-789"));
+}
+
 // PATCH /locks/base64:{key}?id={id}&ttl={ttl}
 #[test]
 fn renew_lock() {
@@ -213,3 +261,75 @@ fn get_dir() {
 
     assert_eq!(resp_val, resp);
 }
+
+// POST /locks/base64:{key}?ttl={ttl}&client-id={client_id}
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn acquire_lock_async() {
+    let resp_json = json!({
+       "id": "D4lbVpdBG_U",
+       "sequencer": 3
+    });
+
+    let mock = MOCK_SERVER
+        .mock_async(|w, t| {
+            when(w, POST, "/v1/locks/base64:Zm9m")
+                .query_param("ttl", "10")
+                .query_param("client-id", "test-client");
+            then(t, resp_json);
+        })
+        .await;
+
+    let req = partial_build().encoded_lock_name("Zm9m").build().unwrap();
+    let resp = req.acquire_async(10, "test-client").await.unwrap();
+
+    // Ensure the endpoint was hit
+    mock.assert_async().await;
+
+    let lock =
+        HeldLock::new(LockName::from_encoded("Zm9m"), LockId::from_encoded("D4lbVpdBG_U"), 3);
+    assert_eq!(lock, resp);
+}
+
+// POST /locks/base64:{key} is never retried
+#[test]
+fn acquire_lock_not_retried() {
+    let mock = MOCK_SERVER.mock(|w, t| {
+        when(w, POST, "/v1/locks/base64:bm9yZXRyeQ");
+        t.status(503)
+            .json_body(json!({"title": "Service Unavailable", "status": 503}));
+    });
+
+    let req = partial_build()
+        .encoded_lock_name("bm9yZXRyeQ")
+        .retry_policy(RetryPolicy::new().initial_backoff(Duration::from_millis(1)))
+        .build()
+        .unwrap();
+    assert!(req.acquire(10, "test-client").is_err());
+
+    mock.assert_hits(1);
+}
+
+// POST /locks/base64:{key} for a lock which is already held
+#[test]
+fn acquire_lock_conflict() {
+    let mock = MOCK_SERVER.mock(|w, t| {
+        when(w, POST, "/v1/locks/base64:aGVsZA");
+        t.status(409).json_body(json!({
+            "title": "Conflict",
+            "status": 409,
+            "holder": "other-client"
+        }));
+    });
+
+    let req = partial_build().encoded_lock_name("aGVsZA").build().unwrap();
+    let err = req.acquire(10, "test-client").unwrap_err();
+
+    mock.assert();
+
+    assert_eq!(err.kind(), ErrorKind::Conflict);
+    assert!(!err.is_retryable());
+    let SeaplaneError::ApiResponse(e) = err else { panic!("expected an API error") };
+    let meta: serde_json::Map<String, serde_json::Value> = e.response.meta_as().unwrap();
+    assert_eq!(meta["holder"], "other-client");
+}
@@ -1,7 +1,13 @@
+use std::time::{Duration, Instant};
+
 use httpmock::{prelude::*, Method, Then, When};
-use seaplane::api::{
-    metadata::v1::{Key, KeyValue, MetadataRequestBuilder, Value},
-    shared::v1::{Directory, RangeQueryContext},
+use seaplane::{
+    api::{
+        metadata::v1::{Key, KeyValue, MetadataRequestBuilder, Value},
+        shared::v1::{Directory, RangeQueryContext},
+        HttpOptions, RateLimiter, RetryPolicy,
+    },
+    error::{ErrorKind, SeaplaneError},
 };
 use serde_json::json;
 
@@ -110,6 +116,33 @@ fn put_value() {
     assert!(resp.is_ok())
 }
 
+// PUT /config/base64:{key}
+#[test]
+fn put_value_with_response() {
+    let resp_json = json!({"status": 200, "title": "Ok"});
+
+    let mock = MOCK_SERVER.mock(|w, t| {
+        when(w, PUT, "/v1/config/base64:Zm9vNg");
+        then(t, resp_json)
+            .header("x-request-id", "req-123")
+            .header("etag", "\"abc\"")
+            .header("date", "Sun, 06 Nov 1994 08:49:37 GMT");
+    });
+
+    let req = partial_build().encoded_key("Zm9vNg").build().unwrap();
+    let resp = req
+        .put_value_with_response(Value::from_encoded("YmFy"))
+        .unwrap();
+
+    // Ensure the endpoint was hit
+    mock.assert();
+
+    assert_eq!(resp.status, 200);
+    assert_eq!(resp.request_id(), Some("req-123"));
+    assert_eq!(resp.etag(), Some("\"abc\""));
+    assert_eq!(resp.date(), Some(std::time::UNIX_EPOCH + Duration::from_secs(784111777)));
+}
+
 // PUT /config/base64:{key}
 #[test]
 fn put_value_unencoded() {
@@ -147,3 +180,219 @@ fn delete_value() {
 
     assert!(resp.is_ok())
 }
+
+// PUT /config/base64:{key}
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn put_value_async() {
+    let resp_json = json!({"status": 200, "title": "Ok"});
+
+    let mock = MOCK_SERVER
+        .mock_async(|w, t| {
+            when(w, PUT, "/v1/config/base64:Zm9vMw")
+                .header("content-type", "application/octet-stream")
+                .body("YmFy");
+            then(t, resp_json);
+        })
+        .await;
+
+    let req = partial_build().encoded_key("Zm9vMw").build().unwrap();
+    let resp = req.put_value_async(Value::from_encoded("YmFy")).await;
+
+    // Ensure the endpoint was hit
+    mock.assert_async().await;
+
+    assert!(resp.is_ok())
+}
+
+// GET /config/base64:{key} is retried on a transient error status
+#[test]
+fn get_value_retries_transient_errors() {
+    let mock = MOCK_SERVER.mock(|w, t| {
+        when(w, GET, "/v1/config/base64:cmV0cnk");
+        t.status(503)
+            .json_body(json!({"title": "Service Unavailable", "status": 503}));
+    });
+
+    let req = partial_build()
+        .encoded_key("cmV0cnk")
+        .retry_policy(
+            RetryPolicy::new()
+                .max_attempts(3)
+                .initial_backoff(Duration::from_millis(1)),
+        )
+        .build()
+        .unwrap();
+    assert!(req.get_value().is_err());
+
+    mock.assert_hits(3);
+}
+
+// GET /config/base64:{key} is not retried when asked to wait longer than the maximum backoff
+#[test]
+fn get_value_retry_after_exceeds_max_backoff() {
+    let mock = MOCK_SERVER.mock(|w, t| {
+        when(w, GET, "/v1/config/base64:cmV0cnlhZnRlcg");
+        t.status(429)
+            .header("retry-after", "3600")
+            .json_body(json!({"title": "Too Many Requests", "status": 429}));
+    });
+
+    let req = partial_build()
+        .encoded_key("cmV0cnlhZnRlcg")
+        .build()
+        .unwrap();
+    assert!(req.get_value().is_err());
+
+    mock.assert_hits(1);
+}
+
+// GET /config/base64:{key} surfaces the rate limit reported in the response
+#[test]
+fn get_value_rate_limit() {
+    let mock = MOCK_SERVER.mock(|w, t| {
+        when(w, GET, "/v1/config/base64:cmF0ZWxpbWl0");
+        then(t, json!({"key": "cmF0ZWxpbWl0", "value": "cmF0ZWxpbWl0"}))
+            .header("x-ratelimit-limit", "100")
+            .header("x-ratelimit-remaining", "42")
+            .header("x-ratelimit-reset", "30");
+    });
+
+    let req = partial_build().encoded_key("cmF0ZWxpbWl0").build().unwrap();
+    assert_eq!(req.rate_limit(), None);
+    req.get_value().unwrap();
+
+    mock.assert();
+    let limit = req.rate_limit().unwrap();
+    assert_eq!(limit.limit, Some(100));
+    assert_eq!(limit.remaining, Some(42));
+    assert_eq!(limit.reset, Some(Duration::from_secs(30)));
+}
+
+// GET /config/base64:{key} is not retried when the rate limit resets after the maximum backoff
+#[test]
+fn get_value_rate_limit_exhausted() {
+    let mock = MOCK_SERVER.mock(|w, t| {
+        when(w, GET, "/v1/config/base64:ZXhoYXVzdGVk");
+        t.status(429)
+            .header("ratelimit-remaining", "0")
+            .header("ratelimit-reset", "3600")
+            .json_body(json!({"title": "Too Many Requests", "status": 429}));
+    });
+
+    let req = partial_build().encoded_key("ZXhoYXVzdGVk").build().unwrap();
+    let err = req.get_value().unwrap_err();
+
+    mock.assert_hits(1);
+    assert_eq!(err.kind(), ErrorKind::RateLimited);
+    let limit = err.rate_limit().unwrap();
+    assert!(limit.is_exhausted());
+    assert_eq!(limit.reset, Some(Duration::from_secs(3600)));
+}
+
+// PUT /config/base64:{key} is throttled by a rate limiter shared between requests
+#[test]
+fn put_value_rate_limiter() {
+    let mock = MOCK_SERVER.mock(|w, t| {
+        when(w, PUT, "/v1/config/base64:dGhyb3R0bGU");
+        then(t, json!({"status": 200, "title": "Ok"}));
+    });
+
+    let limiter = RateLimiter::per_second(20).burst(1);
+    let start = Instant::now();
+    for _ in 0..3 {
+        let req = partial_build()
+            .encoded_key("dGhyb3R0bGU")
+            .rate_limiter(limiter.clone())
+            .build()
+            .unwrap();
+        req.put_value_unencoded("bar").unwrap();
+    }
+
+    mock.assert_hits(3);
+    // The first request uses the burst, the others wait 50ms each
+    assert!(start.elapsed() >= Duration::from_millis(100));
+}
+
+// GET /config/base64:{key} fails once the configured timeout elapses
+#[test]
+fn get_value_timeout() {
+    let mock = MOCK_SERVER.mock(|w, t| {
+        when(w, GET, "/v1/config/base64:dGltZW91dA");
+        then(t, json!({"key": "dGltZW91dA", "value": "dGltZW91dA"})).delay(Duration::from_secs(2));
+    });
+
+    let req = partial_build()
+        .encoded_key("dGltZW91dA")
+        .http_options(HttpOptions::new().timeout(Duration::from_millis(100)))
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap();
+    assert!(req.get_value().is_err());
+
+    mock.assert();
+}
+
+#[test]
+fn invalid_http_options() {
+    let res = partial_build()
+        .http_options(HttpOptions::new().proxy("not a url"))
+        .build();
+    assert!(res.is_err());
+}
+
+// GET /config/base64:{key} where a gateway responds with an HTML error page
+#[test]
+fn get_value_non_json_error() {
+    let mock = MOCK_SERVER.mock(|w, t| {
+        when(w, GET, "/v1/config/base64:aHRtbA");
+        t.status(502)
+            .header("content-type", "text/html")
+            .body("<html><body>Bad Gateway</body></html>");
+    });
+
+    let req = partial_build()
+        .encoded_key("aHRtbA")
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap();
+    let err = req.get_value().unwrap_err();
+
+    mock.assert();
+
+    assert_eq!(err.kind(), ErrorKind::Unavailable);
+    let SeaplaneError::HttpResponse(e) = err else { panic!("expected an HTTP error") };
+    assert_eq!(e.status, 502);
+    assert_eq!(e.headers["content-type"], "text/html");
+    assert_eq!(e.body, "<html><body>Bad Gateway</body></html>");
+}
+
+// DELETE /config/base64:{key} where a gateway responds with an empty body
+#[test]
+fn delete_value_empty_error() {
+    let mock = MOCK_SERVER.mock(|w, t| {
+        when(w, DELETE, "/v1/config/base64:ZW1wdHk");
+        t.status(503);
+    });
+
+    let req = partial_build()
+        .encoded_key("ZW1wdHk")
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap();
+    let err = req.delete_value().unwrap_err();
+
+    mock.assert();
+
+    assert!(err.is_retryable());
+    assert_eq!(err.to_string(), "HTTP 503 Service Unavailable");
+}
+
+// Secrets must never show up in logs
+#[test]
+fn debug_redacts_token() {
+    let builder = partial_build().encoded_key("Zm9v");
+    assert!(!format!("{builder:?}").contains("abc123"));
+    let req = builder.build().unwrap();
+    assert!(!format!("{req:?}").contains("abc123"));
+}
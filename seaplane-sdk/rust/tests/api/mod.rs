@@ -1,16 +1,26 @@
 // We have to go through this little bit of indirection because of how integration directory
 // structure works.
 
+#[cfg(all(feature = "cassette", feature = "metadata_api_v1"))]
+mod cassette;
+#[cfg(all(feature = "metadata_api_v1", feature = "locks_api_v1", feature = "identity_api_v1"))]
+mod client;
 #[cfg(feature = "compute_api_v2")]
 mod compute_v2;
+#[cfg(feature = "testing")]
+mod fake;
 #[cfg(feature = "locks_api_v1")]
 mod locks_v1;
 #[cfg(feature = "metadata_api_v1")]
 mod metadata_v1;
+#[cfg(all(feature = "testing", feature = "metadata_api_v1", feature = "identity_api_v1"))]
+mod observer;
 #[cfg(feature = "restrict_api_v1")]
 mod restrict_v1;
 #[cfg(feature = "identity_api_v1")]
 mod token_v1;
+#[cfg(all(feature = "metadata_api_v1", feature = "identity_api_v1"))]
+mod transport;
 
 use httpmock::prelude::*;
 use once_cell::sync::Lazy;
@@ -0,0 +1,86 @@
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use reqwest::{header::HeaderMap, Method, StatusCode};
+use seaplane::{
+    api::{
+        metadata::v1::Key, CallEvent, HttpRequest, HttpResponse, RetryPolicy, SeaplaneClient,
+        Service, Transport,
+    },
+    error::{ErrorKind, Result},
+    testing::FakeSeaplane,
+};
+
+// Responds with `503 Service Unavailable` to the first `failures` requests to the `/config` API
+// and passes everything else on to the fake
+#[derive(Debug)]
+struct Flaky {
+    fake: FakeSeaplane,
+    failures: AtomicU32,
+}
+
+impl Transport for Flaky {
+    fn send(&self, req: HttpRequest) -> Result<HttpResponse> {
+        let fail = req.url.path().starts_with("/v1/config")
+            && self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+        if fail {
+            return Ok(HttpResponse::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                HeaderMap::new(),
+                vec![],
+            ));
+        }
+        self.fake.send(req)
+    }
+}
+
+// Every call is reported to the observer, including fetching the access token and failed calls
+#[test]
+fn observer_sees_every_call() {
+    let fake = FakeSeaplane::new().api_key("jkl012");
+    let events = Arc::new(Mutex::new(Vec::<CallEvent>::new()));
+    let recorded = Arc::clone(&events);
+    let client = SeaplaneClient::builder()
+        .api_key("jkl012")
+        .transport(Flaky { fake, failures: AtomicU32::new(1) })
+        .retry_policy(RetryPolicy::new().initial_backoff(Duration::from_millis(1)))
+        .observer(move |event: &CallEvent| recorded.lock().unwrap().push(event.clone()))
+        .build()
+        .unwrap();
+    let request = |key: &str| {
+        client
+            .metadata()
+            .encoded_key(Key::from_unencoded(key).encoded())
+            .build()
+            .unwrap()
+    };
+
+    request("foo").put_value_unencoded("bar").unwrap();
+    let err = request("missing").get_value().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    let events = events.lock().unwrap();
+    let summary: Vec<_> = events
+        .iter()
+        .map(|e| (e.service, e.operation, e.status, e.attempts))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (Service::Identity, "access_token_json", Some(StatusCode::CREATED), 1),
+            (Service::Metadata, "put_value", Some(StatusCode::OK), 2),
+            (Service::Metadata, "get_value", Some(StatusCode::NOT_FOUND), 1),
+        ]
+    );
+    assert_eq!(events[1].method, Method::PUT);
+    assert!(events[1].is_success());
+    assert_eq!(events[2].error, Some(ErrorKind::NotFound));
+}
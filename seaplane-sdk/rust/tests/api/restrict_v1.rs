@@ -200,3 +200,47 @@ fn get_all_page() {
     mock.assert();
     assert_eq!(resp, serde_json::from_value(resp_json).unwrap());
 }
+
+// GET /restrict/{api}/base64:{key}/
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn get_restriction_async() {
+    let resp_json = json!({
+        "api": "Locks",
+        "directory": "YmF6L3F1eA",
+        "details": {
+            "regions_allowed": [],
+            "regions_denied": ["XC"],
+            "providers_allowed": [],
+            "providers_denied": []
+        },
+        "state": "Pending"
+    });
+
+    let mock = MOCK_SERVER
+        .mock_async(|w, t| {
+            when(w, GET, "/v1/restrict/locks/base64:YmF6L3F1eA/");
+            then(t, resp_json.clone());
+        })
+        .await;
+
+    let req = partial_build()
+        .single_restriction("locks", "YmF6L3F1eA")
+        .build()
+        .unwrap();
+    let resp = req.get_restriction_async().await.unwrap();
+
+    // Ensure the endpoint was hit
+    mock.assert_async().await;
+
+    assert_eq!(resp, serde_json::from_value(resp_json).unwrap());
+}
+
+#[test]
+fn invalid_api_url() {
+    let res = RestrictRequestBuilder::new()
+        .token("abc123")
+        .api_url("not a url")
+        .build();
+    assert!(res.is_err());
+}
@@ -55,3 +55,182 @@ fn access_token_json() {
 
     assert_eq!(resp, serde_json::from_value(resp_json).unwrap());
 }
+
+// Accept: application/json POST /token
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn access_token_json_async() {
+    let resp_json =
+        json!({"token": "abc.123.def", "tenant": "tnt-abcdef1234567890", "subdomain": "pequod"});
+    let mock = MOCK_SERVER
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/v1/token")
+                .header("authorization", "Bearer def456")
+                .header("accept", "application/json")
+                .header("host", format!("{}:{}", MOCK_SERVER.host(), MOCK_SERVER.port()));
+            then.status(201).json_body(resp_json.clone());
+        })
+        .await;
+
+    let req = TokenRequest::builder()
+        .api_key("def456")
+        .base_url(MOCK_SERVER.base_url())
+        .build()
+        .unwrap();
+    let resp = req.access_token_json_async().await.unwrap();
+
+    // Ensure the endpoint was hit
+    mock.assert_async().await;
+
+    assert_eq!(resp, serde_json::from_value(resp_json).unwrap());
+}
+
+// A rejected token is refreshed and the request retried once
+#[cfg(feature = "metadata_api_v1")]
+#[test]
+fn credentials_refresh_on_unauthorized() {
+    use seaplane::api::{
+        identity::v1::{AccessToken, Credentials},
+        metadata::v1::MetadataRequest,
+    };
+
+    let token_mock = MOCK_SERVER.mock(|when, then| {
+        when.method(POST)
+            .path("/v1/token")
+            .header("authorization", "Bearer ghi789");
+        then.status(201).json_body(
+            json!({"token": "fresh", "tenant": "tnt-abcdef1234567890", "subdomain": "pequod"}),
+        );
+    });
+    let stale_mock = MOCK_SERVER.mock(|when, then| {
+        when.method(GET)
+            .path("/v1/config/base64:Y3JlZHM")
+            .header("authorization", "Bearer stale");
+        then.status(401)
+            .json_body(json!({"title": "Unauthorized", "status": 401}));
+    });
+    let fresh_mock = MOCK_SERVER.mock(|when, then| {
+        when.method(GET)
+            .path("/v1/config/base64:Y3JlZHM")
+            .header("authorization", "Bearer fresh");
+        then.status(200)
+            .json_body(json!({"key": "Y3JlZHM", "value": "dmFsdWU"}));
+    });
+
+    let token_req = TokenRequest::builder()
+        .api_key("ghi789")
+        .base_url(MOCK_SERVER.base_url())
+        .build()
+        .unwrap();
+    let creds = Credentials::new(token_req).with_access_token(AccessToken {
+        token: "stale".into(),
+        tenant: "tnt-abcdef1234567890".into(),
+        subdomain: "pequod".into(),
+    });
+
+    let req = MetadataRequest::builder()
+        .credentials(creds.clone())
+        .encoded_key("Y3JlZHM")
+        .base_url(MOCK_SERVER.base_url())
+        .build()
+        .unwrap();
+    assert!(req.get_value().is_ok());
+
+    // Ensure the stale token was rejected, and a new one was fetched and used
+    stale_mock.assert();
+    token_mock.assert();
+    fresh_mock.assert();
+
+    // The new token is cached for subsequent requests
+    assert_eq!(creds.access_token().unwrap().token, "fresh");
+    token_mock.assert_hits(1);
+}
+
+// Concurrent callers needing a new token wait on a single token request
+#[test]
+fn credentials_concurrent_refresh() {
+    use std::time::Duration;
+
+    use seaplane::api::identity::v1::Credentials;
+
+    // A server of its own, so that the token requests made by other tests cannot be counted
+    let server = MockServer::start();
+    let mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/v1/token")
+            .header("authorization", "Bearer mno345");
+        then.status(201)
+            .delay(Duration::from_millis(200))
+            .json_body(
+                json!({"token": "shared", "tenant": "tnt-abcdef1234567890", "subdomain": "pequod"}),
+            );
+    });
+
+    let creds = Credentials::new(
+        TokenRequest::builder()
+            .api_key("mno345")
+            .base_url(server.base_url())
+            .build()
+            .unwrap(),
+    );
+    let threads: Vec<_> = (0..8)
+        .map(|_| {
+            let creds = creds.clone();
+            std::thread::spawn(move || creds.access_token().map(|t| t.token))
+        })
+        .collect();
+    for thread in threads {
+        assert_eq!(thread.join().unwrap().unwrap(), "shared");
+    }
+
+    mock.assert_hits(1);
+}
+
+// Concurrent callers needing a new token wait on a single token request
+#[cfg(feature = "async")]
+#[tokio::test(flavor = "multi_thread")]
+async fn credentials_concurrent_refresh_async() {
+    use std::time::Duration;
+
+    use seaplane::api::identity::v1::Credentials;
+
+    // A server of its own, so that the token requests made by other tests cannot be counted
+    let server = MockServer::start_async().await;
+    let mock = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/v1/token")
+                .header("authorization", "Bearer jkl012");
+            then.status(201)
+                .delay(Duration::from_millis(200))
+                .json_body(json!({"token": "shared", "tenant": "tnt-abcdef1234567890", "subdomain": "pequod"}));
+        })
+        .await;
+
+    let creds = Credentials::new(
+        TokenRequest::builder()
+            .api_key("jkl012")
+            .base_url(server.base_url())
+            .build()
+            .unwrap(),
+    );
+    let tasks: Vec<_> = (0..8)
+        .map(|_| {
+            let creds = creds.clone();
+            tokio::spawn(async move { creds.access_token_async().await.map(|t| t.token) })
+        })
+        .collect();
+    for task in tasks {
+        assert_eq!(task.await.unwrap().unwrap(), "shared");
+    }
+
+    mock.assert_hits_async(1).await;
+}
+
+// Secrets must never show up in logs
+#[test]
+fn debug_redacts_api_key() {
+    let req = build_req();
+    assert!(!format!("{req:?}").contains("abc123"));
+}
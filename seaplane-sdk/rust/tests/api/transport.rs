@@ -0,0 +1,136 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use reqwest::{header::HeaderMap, Method, StatusCode};
+use seaplane::{
+    api::{
+        metadata::v1::{MetadataRequest, Value},
+        HttpRequest, HttpResponse, RetryPolicy, SeaplaneClient, Transport, TransportError,
+    },
+    error::{ErrorKind, Result},
+};
+
+// An in-memory transport which records every request and responds with a canned response for
+// each path
+#[derive(Debug, Default)]
+struct FakeTransport {
+    requests: Mutex<Vec<HttpRequest>>,
+    responses: Vec<(&'static str, StatusCode, &'static str)>,
+}
+
+impl FakeTransport {
+    fn respond(mut self, path: &'static str, status: StatusCode, body: &'static str) -> Self {
+        self.responses.push((path, status, body));
+        self
+    }
+
+    fn requests(&self) -> Vec<HttpRequest> { self.requests.lock().unwrap().clone() }
+}
+
+impl Transport for FakeTransport {
+    fn send(&self, req: HttpRequest) -> Result<HttpResponse> {
+        let path = req.url.path().to_owned();
+        self.requests.lock().unwrap().push(req);
+        match self.responses.iter().find(|(p, ..)| *p == path) {
+            Some((_, status, body)) => {
+                Ok(HttpResponse::new(*status, HeaderMap::new(), body.as_bytes().to_vec()))
+            }
+            None => Err(TransportError::new(ErrorKind::Connect, "connection refused").into()),
+        }
+    }
+}
+
+// Requests are sent through the transport with the bearer token, without any HTTP server
+#[test]
+fn get_value_through_transport() {
+    let transport = Arc::new(FakeTransport::default().respond(
+        "/v1/config/base64:Zm9v",
+        StatusCode::OK,
+        r#"{"key": "Zm9v", "value": "YmFy"}"#,
+    ));
+
+    let req = MetadataRequest::builder()
+        .token("abc123")
+        .encoded_key("Zm9v")
+        .transport(Arc::clone(&transport))
+        .build()
+        .unwrap();
+    assert_eq!(req.get_value().unwrap(), Value::from_encoded("YmFy"));
+
+    let requests = transport.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, Method::GET);
+    assert_eq!(requests[0].url.as_str(), "https://metadata.cplane.cloud/v1/config/base64:Zm9v");
+    assert_eq!(requests[0].headers["authorization"], "Bearer abc123");
+    assert!(!format!("{:?}", requests[0]).contains("abc123"));
+}
+
+// Error statuses returned by a transport are mapped like those from the default client
+#[test]
+fn error_status_through_transport() {
+    let transport = FakeTransport::default().respond(
+        "/v1/config/base64:bm90Zm91bmQ",
+        StatusCode::NOT_FOUND,
+        r#"{"title": "Not Found", "status": 404}"#,
+    );
+
+    let req = MetadataRequest::builder()
+        .token("abc123")
+        .encoded_key("bm90Zm91bmQ")
+        .transport(transport)
+        .build()
+        .unwrap();
+    let err = req.get_value().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
+
+// Retryable transport errors are retried according to the retry policy
+#[test]
+fn transport_error_is_retried() {
+    let transport = Arc::new(FakeTransport::default());
+
+    let req = MetadataRequest::builder()
+        .token("abc123")
+        .encoded_key("Zm9v")
+        .transport(Arc::clone(&transport))
+        .retry_policy(
+            RetryPolicy::new()
+                .max_attempts(3)
+                .initial_backoff(Duration::from_millis(1)),
+        )
+        .build()
+        .unwrap();
+    let err = req.get_value().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Connect);
+    assert_eq!(transport.requests().len(), 3);
+}
+
+// The token is fetched through the client's transport as well
+#[test]
+fn client_transport() {
+    let transport = Arc::new(
+        FakeTransport::default()
+            .respond(
+                "/v1/token",
+                StatusCode::CREATED,
+                r#"{"token": "fake", "tenant": "tnt-abcdef1234567890", "subdomain": "pequod"}"#,
+            )
+            .respond("/v1/config/base64:Zm9v", StatusCode::OK, r#"{"status": 200, "title": "Ok"}"#),
+    );
+
+    let client = SeaplaneClient::builder()
+        .api_key("jkl012")
+        .transport(Arc::clone(&transport))
+        .build()
+        .unwrap();
+    let req = client.metadata().encoded_key("Zm9v").build().unwrap();
+    req.put_value_unencoded("bar").unwrap();
+
+    let requests = transport.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].headers["authorization"], "Bearer jkl012");
+    assert_eq!(requests[1].method, Method::PUT);
+    assert_eq!(requests[1].headers["authorization"], "Bearer fake");
+}